@@ -1,13 +1,26 @@
 use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::str::FromStr;
 
+use chrono::{DateTime, TimeZone, Utc};
+use clap::builder::{TypedValueParser, ValueParser};
 use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use ecow::EcoString;
 use semver::Version;
+use typst::syntax::PackageSpec;
 
 /// The character typically used to separate path components
 /// in environment variables.
 const ENV_PATH_SEP: char = if cfg!(windows) { ';' } else { ':' };
 
+/// The character used to separate multiple `key=value` pairs in the
+/// `TYPST_INPUTS` environment variable.
+const ENV_INPUT_SEP: char = '\n';
+
 /// The Typst compiler.
 #[derive(Debug, Clone, Parser)]
 #[clap(name = "typst", version = crate::typst_version(), author)]
@@ -36,11 +49,23 @@ pub enum Command {
 
     /// Watches an input file and recompiles on changes
     #[command(visible_alias = "w")]
-    Watch(CompileCommand),
+    Watch(WatchCommand),
+
+    /// Initializes a new project from a template
+    Init(InitCommand),
 
     /// Processes an input file to extract provided metadata
     Query(QueryCommand),
 
+    /// Formats the code in Typst source files
+    Fmt(FmtCommand),
+
+    /// Starts a language server that communicates over stdin and stdout
+    Lsp(LspCommand),
+
+    /// Manages local and cached packages
+    Package(PackageCommand),
+
     /// Lists all discovered fonts in system and custom font paths
     Fonts(FontsCommand),
 
@@ -52,21 +77,114 @@ pub enum Command {
 /// Compiles an input file into a supported output format
 #[derive(Debug, Clone, Parser)]
 pub struct CompileCommand {
+    /// Path to input Typst file, use `-` to read input from stdin
+    #[clap(
+        value_parser = input_value_parser(),
+        required_unless_present_any = ["batch", "manifest"],
+    )]
+    pub input: Option<Input>,
+
+    /// Path to output file (PDF, PNG, SVG, TXT, HTML, or EPUB), use `-` to write
+    /// output to stdout
+    #[clap(value_parser = output_value_parser())]
+    pub output: Option<Output>,
+
     /// Shared arguments
     #[clap(flatten)]
     pub common: SharedArgs,
 
-    /// Path to output file (PDF, PNG, or SVG)
-    pub output: Option<PathBuf>,
+    /// Compiles all of the given input files in one process, writing each
+    /// output next to its input
+    #[arg(
+        long = "batch",
+        value_name = "INPUT",
+        num_args = 1..,
+        conflicts_with_all = ["input", "output", "manifest", "deps", "open"],
+    )]
+    pub batch: Vec<PathBuf>,
+
+    /// Compiles all documents listed in a TOML manifest in one process
+    ///
+    /// The manifest contains a `[[document]]` table with an `input` and an
+    /// optional `output` path for each document. Relative paths are resolved
+    /// against the manifest's directory.
+    #[arg(
+        long = "manifest",
+        value_name = "FILE",
+        conflicts_with_all = ["input", "output", "deps", "open"],
+    )]
+    pub manifest: Option<PathBuf>,
+
+    /// Compiles the input once for every record of a CSV or JSON data file
+    ///
+    /// The fields of each record are visible through `sys.inputs`. The first
+    /// row of a CSV file names the fields, while a JSON file must contain an
+    /// array of objects. The output path names each output after the record's
+    /// fields, like `out/{name}.pdf`.
+    #[arg(
+        long = "data",
+        value_name = "FILE",
+        requires = "output",
+        conflicts_with_all = ["batch", "manifest", "deps", "open"],
+    )]
+    pub data: Option<PathBuf>,
+
+    /// The number of documents to compile in parallel when compiling a batch,
+    /// defaults to the number of available CPUs
+    #[arg(long = "jobs", short = 'j', value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
 
     /// The format of the output file, inferred from the extension by default
     #[arg(long = "format", short = 'f')]
     pub format: Option<OutputFormat>,
 
+    /// Which pages to export. When unspecified, all document pages are
+    /// exported.
+    ///
+    /// Pages to export are separated by commas, and can be either simple page
+    /// numbers (e.g. '2,5' to export only pages 2 and 5) or page ranges (e.g.
+    /// '2,3-6,8-' to export page 2, pages 3 to 6 (inclusive), page 8 and any
+    /// pages after it).
+    ///
+    /// Page numbers are one-indexed and correspond to real page numbers in the
+    /// document (therefore not being affected by the document's page counter).
+    #[arg(long = "pages", value_delimiter = ',')]
+    pub pages: Option<Vec<Pages>>,
+
+    /// File path to which a list of the files the compilation depended on will
+    /// be written, along with the output files
+    ///
+    /// Input from stdin is not listed. The Makefile format needs an output
+    /// path, since a rule without a target is invalid.
+    #[arg(long = "deps", value_name = "PATH")]
+    pub deps: Option<PathBuf>,
+
+    /// The format of the dependency file
+    #[arg(
+        long = "deps-format",
+        value_name = "FORMAT",
+        default_value_t = DepsFormat::Make,
+        value_parser = clap::value_parser!(DepsFormat),
+    )]
+    pub deps_format: DepsFormat,
+
     /// Opens the output file using the default viewer after compilation
     #[arg(long = "open")]
     pub open: Option<Option<String>>,
 
+    /// The PDF standard that PDF output must conform to
+    ///
+    /// With `a-2b`, the output conforms to PDF/A-2b for long-term archival and
+    /// content that cannot be represented in it, as well as images without
+    /// an alternative description, is reported as errors.
+    #[arg(
+        long = "pdf-standard",
+        value_name = "STANDARD",
+        default_value_t = PdfStandard::V1_7,
+        value_parser = clap::value_parser!(PdfStandard),
+    )]
+    pub pdf_standard: PdfStandard,
+
     /// The PPI (pixels per inch) to use for PNG export
     #[arg(long = "ppi", default_value_t = 144.0)]
     pub ppi: f32,
@@ -76,9 +194,44 @@ pub struct CompileCommand {
     pub flamegraph: Option<Option<PathBuf>>,
 }
 
+/// Watches an input file and recompiles on changes
+#[derive(Debug, Clone, Parser)]
+pub struct WatchCommand {
+    /// Arguments for compilation
+    #[clap(flatten)]
+    pub args: CompileCommand,
+
+    /// Serves a live preview of the document on the given port of localhost
+    #[arg(long = "serve", value_name = "PORT")]
+    pub serve: Option<u16>,
+}
+
+/// Initializes a new project from a template
+#[derive(Debug, Clone, Parser)]
+pub struct InitCommand {
+    /// The template to use, e.g. `@preview/charged-ieee`
+    ///
+    /// You can specify the version by appending e.g. `:0.1.0`. If no version
+    /// is specified, Typst will default to the latest version.
+    ///
+    /// Supports both local and published templates.
+    pub template: String,
+
+    /// The project directory, defaults to the template's name
+    pub dir: Option<String>,
+
+    /// Arguments related to storage of packages in the system
+    #[clap(flatten)]
+    pub package: PackageStorageArgs,
+}
+
 /// Processes an input file to extract provided metadata
 #[derive(Debug, Clone, Parser)]
 pub struct QueryCommand {
+    /// Path to input Typst file, use `-` to read input from stdin
+    #[clap(value_parser = input_value_parser())]
+    pub input: Input,
+
     /// Shared arguments
     #[clap(flatten)]
     pub common: SharedArgs,
@@ -99,6 +252,173 @@ pub struct QueryCommand {
     pub format: SerializationFormat,
 }
 
+/// Formats the code in Typst source files
+///
+/// Code expressions, argument lists, collections, and set and show rules are
+/// normalized. Markup and math are kept as written, apart from trailing
+/// whitespace.
+#[derive(Debug, Clone, Parser)]
+pub struct FmtCommand {
+    /// Files to format, directories are searched for `.typ` files recursively
+    #[clap(required = true)]
+    pub input: Vec<PathBuf>,
+
+    /// Only checks whether the files are formatted and lists those that are
+    /// not, without changing them
+    #[clap(long = "check")]
+    pub check: bool,
+
+    /// The number of spaces per indentation level
+    #[clap(long = "indent", default_value_t = 2)]
+    pub indent: usize,
+
+    /// The line width the formatter tries to stay within
+    #[clap(long = "line-width", default_value_t = 80)]
+    pub line_width: usize,
+}
+
+/// Starts a language server that communicates over stdin and stdout
+///
+/// Besides diagnostics, completions, and hovers, the server offers two
+/// commands through `workspace/executeCommand` to sync an editor with a
+/// preview of the document:
+///
+/// `typst.jumpFromClick` takes `{ page, x, y }` with a one-based page number
+/// and a point in points. If the click leads to source code, the result is an
+/// LSP location like for `textDocument/definition` and the client is asked to
+/// show it. A link yields `{ url }`, and a link into the document yields
+/// `{ page, x, y }`.
+///
+/// `typst.jumpFromCursor` takes `{ textDocument, position }` like other
+/// requests and yields the matching `{ page, x, y }` in the document.
+#[derive(Debug, Clone, Parser)]
+pub struct LspCommand {
+    /// The file to compile, defaults to the most recently edited one
+    #[clap(long = "main", value_name = "FILE")]
+    pub main: Option<PathBuf>,
+
+    /// Shared arguments, where the project root defaults to the editor's
+    /// workspace
+    #[clap(flatten)]
+    pub common: SharedArgs,
+}
+
+/// Manages local and cached packages
+#[derive(Debug, Clone, Parser)]
+pub struct PackageCommand {
+    /// What to do with the packages
+    #[command(subcommand)]
+    pub command: PackageSubcommand,
+}
+
+/// What to do with the packages.
+#[derive(Debug, Clone, Subcommand)]
+pub enum PackageSubcommand {
+    /// Lists local and cached packages along with their sizes
+    List(PackageListCommand),
+
+    /// Removes cached packages, by default all but the latest version of each
+    Clean(PackageCleanCommand),
+
+    /// Prints the package directories or the directory of a single package
+    Path(PackagePathCommand),
+
+    /// Downloads all packages that the given files depend on, including the
+    /// packages that those depend on in turn
+    Fetch(PackageFetchCommand),
+}
+
+/// Lists local and cached packages along with their sizes
+#[derive(Debug, Clone, Parser)]
+pub struct PackageListCommand {
+    /// Arguments related to storage of packages in the system
+    #[clap(flatten)]
+    pub package: PackageStorageArgs,
+}
+
+/// Removes cached packages, by default all but the latest version of each
+#[derive(Debug, Clone, Parser)]
+pub struct PackageCleanCommand {
+    /// Removes all cached packages, including the latest versions
+    #[clap(long = "all")]
+    pub all: bool,
+
+    /// The lockfile whose pinned packages are kept
+    #[clap(long = "lockfile", value_name = "FILE", default_value = "typst.lock")]
+    pub lockfile: PathBuf,
+
+    /// Arguments related to storage of packages in the system
+    #[clap(flatten)]
+    pub package: PackageStorageArgs,
+}
+
+/// Prints the package directories or the directory of a single package
+#[derive(Debug, Clone, Parser)]
+pub struct PackagePathCommand {
+    /// The package to locate, e.g. `@preview/cetz:0.2.0`
+    #[clap(value_parser = ValueParser::new(parse_package_spec))]
+    pub spec: Option<PackageSpec>,
+
+    /// Arguments related to storage of packages in the system
+    #[clap(flatten)]
+    pub package: PackageStorageArgs,
+}
+
+/// Downloads all packages that the given files depend on, including the
+/// packages that those depend on in turn
+#[derive(Debug, Clone, Parser)]
+pub struct PackageFetchCommand {
+    /// Typst files whose imports to fetch, directories are searched for `.typ`
+    /// files recursively
+    #[clap(required = true)]
+    pub input: Vec<PathBuf>,
+
+    /// Configures the project root (for absolute paths)
+    #[clap(long = "root", env = "TYPST_ROOT", value_name = "DIR")]
+    pub root: Option<PathBuf>,
+
+    /// Fails if a package is missing from `typst.lock` instead of adding it
+    /// to the lockfile
+    #[clap(long = "locked")]
+    pub locked: bool,
+
+    /// Replaces checksums pinned in `typst.lock` that don't match the fetched
+    /// packages instead of failing
+    #[clap(long = "update", conflicts_with = "locked")]
+    pub update: bool,
+
+    /// Arguments related to storage of packages in the system
+    #[clap(flatten)]
+    pub package: PackageStorageArgs,
+}
+
+/// Arguments related to storage of packages in the system
+#[derive(Debug, Clone, Args)]
+pub struct PackageStorageArgs {
+    /// Custom directory with local packages, searched before the packages in
+    /// the system-dependent data directory
+    #[clap(long = "package-path", env = "TYPST_PACKAGE_PATH", value_name = "DIR")]
+    pub package_path: Option<PathBuf>,
+
+    /// Custom directory for downloaded packages, defaults to a
+    /// system-dependent cache directory
+    #[clap(
+        long = "package-cache-path",
+        env = "TYPST_PACKAGE_CACHE_PATH",
+        value_name = "DIR"
+    )]
+    pub package_cache_path: Option<PathBuf>,
+
+    /// Fails instead of downloading missing packages from the network
+    #[clap(long = "offline", env = "TYPST_OFFLINE")]
+    pub offline: bool,
+}
+
+/// Parses a fully specified package, like `@preview/cetz:0.2.0`.
+fn parse_package_spec(raw: &str) -> Result<PackageSpec, String> {
+    raw.parse().map_err(|err: EcoString| err.to_string())
+}
+
 // Output file format for query command
 #[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
 pub enum SerializationFormat {
@@ -109,13 +429,21 @@ pub enum SerializationFormat {
 /// Common arguments of compile, watch, and query.
 #[derive(Debug, Clone, Args)]
 pub struct SharedArgs {
-    /// Path to input Typst file
-    pub input: PathBuf,
-
     /// Configures the project root (for absolute paths)
     #[clap(long = "root", env = "TYPST_ROOT", value_name = "DIR")]
     pub root: Option<PathBuf>,
 
+    /// Add a string key-value pair visible through `sys.inputs`
+    #[clap(
+        long = "input",
+        env = "TYPST_INPUTS",
+        value_name = "key=value",
+        action = ArgAction::Append,
+        value_delimiter = ENV_INPUT_SEP,
+        value_parser = ValueParser::new(parse_input_pair),
+    )]
+    pub inputs: Vec<(String, String)>,
+
     /// Adds additional directories to search for fonts
     #[clap(
         long = "font-path",
@@ -125,7 +453,32 @@ pub struct SharedArgs {
     )]
     pub font_paths: Vec<PathBuf>,
 
+    /// The document's creation date formatted as a UNIX timestamp
+    ///
+    /// Fixes the PDF's creation date and the value of `datetime.today()`,
+    /// which otherwise reflect the current time, so that builds are
+    /// reproducible.
+    #[clap(
+        long = "creation-timestamp",
+        env = "SOURCE_DATE_EPOCH",
+        value_name = "UNIX_TIMESTAMP",
+        value_parser = parse_source_date_epoch,
+    )]
+    pub creation_timestamp: Option<DateTime<Utc>>,
+
+    /// Fails if a package is missing from `typst.lock` instead of adding it
+    /// to the lockfile
+    #[clap(long = "locked")]
+    pub locked: bool,
+
+    /// Arguments related to storage of packages in the system
+    #[clap(flatten)]
+    pub package: PackageStorageArgs,
+
     /// The format to emit diagnostics in
+    ///
+    /// With `json`, every diagnostic is printed as a JSON object on its own
+    /// line.
     #[clap(
         long,
         default_value_t = DiagnosticFormat::Human,
@@ -134,6 +487,110 @@ pub struct SharedArgs {
     pub diagnostic_format: DiagnosticFormat,
 }
 
+/// An input that is either stdin or a real path.
+#[derive(Debug, Clone)]
+pub enum Input {
+    /// Stdin, represented by `-`.
+    Stdin,
+    /// A non-empty path.
+    Path(PathBuf),
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Stdin => f.pad("stdin"),
+            Input::Path(path) => path.display().fmt(f),
+        }
+    }
+}
+
+/// An output that is either stdout or a real path.
+#[derive(Debug, Clone)]
+pub enum Output {
+    /// Stdout, represented by `-`.
+    Stdout,
+    /// A non-empty path.
+    Path(PathBuf),
+}
+
+impl Output {
+    /// Writes the given bytes to the output.
+    pub fn write(&self, buffer: &[u8]) -> io::Result<()> {
+        match self {
+            Output::Stdout => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(buffer)?;
+                stdout.flush()
+            }
+            Output::Path(path) => fs::write(path, buffer),
+        }
+    }
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Stdout => f.pad("stdout"),
+            Output::Path(path) => path.display().fmt(f),
+        }
+    }
+}
+
+/// The clap value parser used by `SharedArgs.input`
+fn input_value_parser() -> impl TypedValueParser<Value = Input> {
+    clap::builder::OsStringValueParser::new().try_map(|value| {
+        if value.is_empty() {
+            Err(clap::Error::new(clap::error::ErrorKind::InvalidValue))
+        } else if value == "-" {
+            Ok(Input::Stdin)
+        } else {
+            Ok(Input::Path(value.into()))
+        }
+    })
+}
+
+/// The clap value parser used by `CompileCommand.output`
+fn output_value_parser() -> impl TypedValueParser<Value = Output> {
+    clap::builder::OsStringValueParser::new().try_map(|value| {
+        // Empty value also handled by clap for `Option<Output>`
+        if value.is_empty() {
+            Err(clap::Error::new(clap::error::ErrorKind::InvalidValue))
+        } else if value == "-" {
+            Ok(Output::Stdout)
+        } else {
+            Ok(Output::Path(value.into()))
+        }
+    })
+}
+
+/// Parses key/value pairs split by the first equal sign.
+///
+/// This function will return an error if the argument contains no equals sign
+/// or if the key (before the equals sign) is empty.
+fn parse_input_pair(raw: &str) -> Result<(String, String), String> {
+    let (key, val) = raw
+        .split_once('=')
+        .ok_or("input must be a key and a value separated by an equal sign")?;
+    let key = key.trim().to_owned();
+    if key.is_empty() {
+        return Err("the key was missing or empty".to_owned());
+    }
+    let val = val.trim().to_owned();
+    Ok((key, val))
+}
+
+/// Parses a UNIX timestamp according to
+/// <https://reproducible-builds.org/specs/source-date-epoch/>.
+fn parse_source_date_epoch(raw: &str) -> Result<DateTime<Utc>, String> {
+    let timestamp: i64 = raw
+        .parse()
+        .map_err(|err| format!("timestamp must be a decimal integer ({err})"))?;
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .ok_or_else(|| "timestamp is out of range".to_owned())
+}
+
 /// Lists all discovered fonts in system and custom font paths
 #[derive(Debug, Clone, Parser)]
 pub struct FontsCommand {
@@ -151,11 +608,95 @@ pub struct FontsCommand {
     pub variants: bool,
 }
 
+/// Implements parsing of page ranges (`1-3`, `4`, `5-`, `-2`), used by the
+/// `CompileCommand.pages` argument, through the `FromStr` trait instead of a
+/// value parser, in order to generate better errors.
+///
+/// See also: <https://github.com/clap-rs/clap/issues/5065>
+#[derive(Debug, Clone)]
+pub struct Pages(pub RangeInclusive<Option<NonZeroUsize>>);
+
+impl FromStr for Pages {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split('-').map(str::trim).collect::<Vec<_>>().as_slice() {
+            [] | [""] => Err("page export range must not be empty"),
+            [single_page] => {
+                let page_number = parse_page_number(single_page)?;
+                Ok(Pages(Some(page_number)..=Some(page_number)))
+            }
+            ["", ""] => Err("page export range must have start or end"),
+            [start, ""] => Ok(Pages(Some(parse_page_number(start)?)..=None)),
+            ["", end] => Ok(Pages(None..=Some(parse_page_number(end)?))),
+            [start, end] => {
+                let start = parse_page_number(start)?;
+                let end = parse_page_number(end)?;
+                if start > end {
+                    Err("page export range must end at a page after the start")
+                } else {
+                    Ok(Pages(Some(start)..=Some(end)))
+                }
+            }
+            [_, _, _, ..] => Err("page export range must have a single hyphen"),
+        }
+    }
+}
+
+/// Parses a single page number.
+fn parse_page_number(value: &str) -> Result<NonZeroUsize, &'static str> {
+    if value == "0" {
+        Err("page numbers start at one")
+    } else {
+        NonZeroUsize::from_str(value).map_err(|_| "not a valid page number")
+    }
+}
+
+/// Which format to use for dependency files.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, ValueEnum)]
+pub enum DepsFormat {
+    /// A Makefile rule with the outputs as targets and the inputs as
+    /// prerequisites
+    Make,
+    /// A JSON object with `outputs` and `inputs` arrays
+    Json,
+}
+
+impl Display for DepsFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+/// A PDF standard that PDF output can conform to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, ValueEnum)]
+pub enum PdfStandard {
+    /// PDF 1.7
+    #[value(name = "1.7")]
+    V1_7,
+    /// PDF/A-2b
+    #[value(name = "a-2b")]
+    A2b,
+}
+
+impl Display for PdfStandard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
 /// Which format to use for diagnostics.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, ValueEnum)]
 pub enum DiagnosticFormat {
     Human,
     Short,
+    Json,
 }
 
 impl Display for DiagnosticFormat {
@@ -189,6 +730,9 @@ pub enum OutputFormat {
     Pdf,
     Png,
     Svg,
+    Txt,
+    Html,
+    Epub,
 }
 
 impl Display for OutputFormat {
@@ -199,3 +743,44 @@ impl Display for OutputFormat {
             .fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(n: usize) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(n)
+    }
+
+    fn parse(value: &str) -> Result<RangeInclusive<Option<NonZeroUsize>>, &'static str> {
+        Pages::from_str(value).map(|pages| pages.0)
+    }
+
+    #[test]
+    fn test_pages_from_str() {
+        assert_eq!(parse("3"), Ok(page(3)..=page(3)));
+        assert_eq!(parse("2-5"), Ok(page(2)..=page(5)));
+        assert_eq!(parse(" 2 - 5 "), Ok(page(2)..=page(5)));
+        assert_eq!(parse("4-4"), Ok(page(4)..=page(4)));
+    }
+
+    #[test]
+    fn test_pages_from_str_open() {
+        assert_eq!(parse("3-"), Ok(page(3)..=None));
+        assert_eq!(parse("-5"), Ok(None..=page(5)));
+    }
+
+    #[test]
+    fn test_pages_from_str_invalid() {
+        assert_eq!(parse(""), Err("page export range must not be empty"));
+        assert_eq!(parse("-"), Err("page export range must have start or end"));
+        assert_eq!(parse("0"), Err("page numbers start at one"));
+        assert_eq!(parse("0-3"), Err("page numbers start at one"));
+        assert_eq!(
+            parse("5-3"),
+            Err("page export range must end at a page after the start")
+        );
+        assert_eq!(parse("1-2-3"), Err("page export range must have a single hyphen"));
+        assert_eq!(parse("a"), Err("not a valid page number"));
+    }
+}
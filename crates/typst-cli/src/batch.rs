@@ -0,0 +1,135 @@
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use comemo::Prehashed;
+use ecow::eco_format;
+use serde::Deserialize;
+use typst::diag::{bail, FileError, StrResult};
+
+use crate::args::{CompileCommand, Input, Output};
+use crate::compile::compile_once;
+use crate::fonts::FontSearcher;
+use crate::print_error;
+use crate::world::SystemWorld;
+
+/// Execute a batch compilation, which compiles many documents in one process.
+///
+/// The documents share one font search, the compiler's in-memory caches, and
+/// the package directories, and are distributed across parallel workers.
+pub fn compile_batch(command: &CompileCommand) -> StrResult<()> {
+    let jobs = jobs(command)?;
+    if jobs.is_empty() {
+        return Ok(());
+    }
+
+    let mut searcher = FontSearcher::new();
+    searcher.search(&command.common.font_paths);
+    let book = Arc::new(Prehashed::new(searcher.book));
+    let fonts = Arc::new(searcher.fonts);
+
+    let workers = command
+        .jobs
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+        .min(jobs.len());
+
+    // Workers pick the next job until none are left. Diagnostics are printed
+    // as the documents finish, while other errors are collected and printed at
+    // the end.
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let errors = Mutex::new(vec![]);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(job) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let mut job = job.clone();
+                    let result = SystemWorld::with_fonts(
+                        job.input(),
+                        &job.common,
+                        book.clone(),
+                        fonts.clone(),
+                    )
+                    .and_then(|mut world| compile_once(&mut world, &mut job, false));
+
+                    match result {
+                        Ok(Some(_)) => {}
+                        Ok(None) => {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(err) => {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                            errors
+                                .lock()
+                                .unwrap()
+                                .push(eco_format!("{}: {err}", job.input()));
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    for error in errors.into_inner().unwrap() {
+        print_error(&error).expect("failed to print error");
+    }
+
+    let failed = failed.into_inner();
+    if failed > 0 {
+        bail!("{failed} of {} documents failed to compile", jobs.len());
+    }
+
+    Ok(())
+}
+
+/// Turn the documents of a batch into single compilation commands.
+fn jobs(command: &CompileCommand) -> StrResult<Vec<CompileCommand>> {
+    let mut documents: Vec<(PathBuf, Option<PathBuf>)> =
+        command.batch.iter().map(|input| (input.clone(), None)).collect();
+
+    if let Some(path) = &command.manifest {
+        let text =
+            fs::read_to_string(path).map_err(|err| FileError::from_io(err, path))?;
+        let manifest: Manifest = toml::from_str(&text).map_err(|err| {
+            eco_format!("batch manifest is malformed ({})", err.message())
+        })?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        documents.extend(manifest.documents.into_iter().map(|document| {
+            (dir.join(document.input), document.output.map(|output| dir.join(output)))
+        }));
+    }
+
+    Ok(documents
+        .into_iter()
+        .map(|(input, output)| {
+            let mut job = command.clone();
+            job.input = Some(Input::Path(input));
+            job.output = output.map(Output::Path);
+            job.batch.clear();
+            job.manifest = None;
+            job
+        })
+        .collect())
+}
+
+/// A manifest listing the documents of a batch compilation.
+#[derive(Deserialize)]
+struct Manifest {
+    /// The documents to compile.
+    #[serde(default, rename = "document")]
+    documents: Vec<ManifestDocument>,
+}
+
+/// A document in a batch manifest.
+#[derive(Deserialize)]
+struct ManifestDocument {
+    /// The path to the Typst file.
+    input: PathBuf,
+    /// The path to the output file, which is placed next to the input by
+    /// default.
+    output: Option<PathBuf>,
+}
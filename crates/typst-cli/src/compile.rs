@@ -1,20 +1,30 @@
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
 
-use chrono::{Datelike, Timelike};
+use chrono::{DateTime, Datelike, Timelike, Utc};
 use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::files::Files;
 use codespan_reporting::term::{self, termcolor};
 use ecow::eco_format;
+use serde_json::json;
 use termcolor::{ColorChoice, StandardStream};
-use typst::diag::{bail, At, Severity, SourceDiagnostic, StrResult};
+use typst::diag::{bail, At, Severity, SourceDiagnostic, SourceResult, StrResult};
 use typst::eval::Tracer;
 use typst::foundations::Datetime;
+use typst::layout::{Frame, PageRanges};
 use typst::model::Document;
 use typst::syntax::{FileId, Source, Span};
 use typst::visualize::Color;
 use typst::{World, WorldExt};
 
-use crate::args::{CompileCommand, DiagnosticFormat, OutputFormat};
+use crate::args::{
+    CompileCommand, DepsFormat, DiagnosticFormat, Input, Output, OutputFormat,
+    PdfStandard,
+};
+use crate::batch::compile_batch;
+use crate::merge::compile_merge;
 use crate::watch::Status;
 use crate::world::SystemWorld;
 use crate::{color_stream, set_failed};
@@ -23,16 +33,37 @@ type CodespanResult<T> = Result<T, CodespanError>;
 type CodespanError = codespan_reporting::files::Error;
 
 impl CompileCommand {
+    /// The input file, which is only missing when compiling a batch.
+    pub fn input(&self) -> &Input {
+        self.input.as_ref().expect("input is required outside of batch mode")
+    }
+
     /// The output path.
-    pub fn output(&self) -> PathBuf {
-        self.output.clone().unwrap_or_else(|| {
-            self.common.input.with_extension(
+    ///
+    /// Without an explicit output, the output is written next to the input
+    /// file or, when reading from stdin, to stdout.
+    pub fn output(&self) -> Output {
+        self.output.clone().unwrap_or_else(|| match self.input() {
+            Input::Stdin => Output::Stdout,
+            Input::Path(path) => Output::Path(path.with_extension(
                 match self.output_format().unwrap_or(OutputFormat::Pdf) {
                     OutputFormat::Pdf => "pdf",
                     OutputFormat::Png => "png",
                     OutputFormat::Svg => "svg",
+                    OutputFormat::Txt => "txt",
+                    OutputFormat::Html => "html",
+                    OutputFormat::Epub => "epub",
                 },
-            )
+            )),
+        })
+    }
+
+    /// The ranges of the pages to be exported as specified by the user.
+    ///
+    /// This returns `None` if all pages should be exported.
+    pub fn exported_page_ranges(&self) -> Option<PageRanges> {
+        self.pages.as_ref().map(|export_ranges| {
+            PageRanges::new(export_ranges.iter().map(|r| r.0.clone()).collect())
         })
     }
 
@@ -42,11 +73,14 @@ impl CompileCommand {
     pub fn output_format(&self) -> StrResult<OutputFormat> {
         Ok(if let Some(specified) = self.format {
             specified
-        } else if let Some(output) = &self.output {
+        } else if let Some(Output::Path(output)) = &self.output {
             match output.extension() {
                 Some(ext) if ext.eq_ignore_ascii_case("pdf") => OutputFormat::Pdf,
                 Some(ext) if ext.eq_ignore_ascii_case("png") => OutputFormat::Png,
                 Some(ext) if ext.eq_ignore_ascii_case("svg") => OutputFormat::Svg,
+                Some(ext) if ext.eq_ignore_ascii_case("txt") => OutputFormat::Txt,
+                Some(ext) if ext.eq_ignore_ascii_case("html") => OutputFormat::Html,
+                Some(ext) if ext.eq_ignore_ascii_case("epub") => OutputFormat::Epub,
                 _ => bail!("could not infer output format for path {}.\nconsider providing the format manually with `--format/-f`", output.display()),
             }
         } else {
@@ -57,20 +91,39 @@ impl CompileCommand {
 
 /// Execute a compilation command.
 pub fn compile(mut command: CompileCommand) -> StrResult<()> {
-    let mut world = SystemWorld::new(&command.common)?;
+    if !command.batch.is_empty() || command.manifest.is_some() {
+        return compile_batch(&command);
+    }
+
+    if let Some(data) = &command.data {
+        return compile_merge(&command, data);
+    }
+
+    // A Makefile rule needs a target to which the inputs lead.
+    if command.deps.is_some()
+        && command.deps_format == DepsFormat::Make
+        && matches!(command.output(), Output::Stdout)
+    {
+        bail!(
+            "cannot write a Makefile dependency file when writing to stdout\n\
+             consider passing `--deps-format json` or an output path"
+        );
+    }
+
+    let mut world = SystemWorld::new(command.input(), &command.common)?;
     compile_once(&mut world, &mut command, false)?;
     Ok(())
 }
 
 /// Compile a single time.
 ///
-/// Returns whether it compiled without errors.
+/// Returns the document if it compiled without errors.
 #[tracing::instrument(skip_all)]
 pub fn compile_once(
     world: &mut SystemWorld,
     command: &mut CompileCommand,
     watching: bool,
-) -> StrResult<()> {
+) -> StrResult<Option<Document>> {
     tracing::info!("Starting compilation");
 
     let start = std::time::Instant::now();
@@ -90,17 +143,21 @@ pub fn compile_once(
         print_diagnostics(world, &errors, &[], command.common.diagnostic_format)
             .map_err(|err| eco_format!("failed to print diagnostics ({err})"))?;
 
-        return Ok(());
+        return Ok(None);
     }
 
     let mut tracer = Tracer::new();
-    let result = typst::compile(world, &mut tracer);
+    let result = typst::compile(world, &mut tracer).and_then(|document| {
+        // Export the PDF / PNG.
+        let outputs = export(world, &document, command, watching)?;
+        Ok((document, outputs))
+    });
     let warnings = tracer.warnings();
 
     match result {
-        // Export the PDF / PNG.
-        Ok(document) => {
-            export(world, &document, command, watching)?;
+        Ok((document, outputs)) => {
+            write_deps(world, command, &outputs)?;
+            world.write_lockfile()?;
             let duration = start.elapsed();
 
             tracing::info!("Compilation succeeded in {duration:?}");
@@ -116,8 +173,13 @@ pub fn compile_once(
                 .map_err(|err| eco_format!("failed to print diagnostics ({err})"))?;
 
             if let Some(open) = command.open.take() {
-                open_file(open.as_deref(), &command.output())?;
+                let Output::Path(path) = command.output() else {
+                    bail!("cannot open output that is written to stdout");
+                };
+                open_file(open.as_deref(), &path)?;
             }
+
+            Ok(Some(document))
         }
 
         // Print diagnostics.
@@ -136,47 +198,128 @@ pub fn compile_once(
                 command.common.diagnostic_format,
             )
             .map_err(|err| eco_format!("failed to print diagnostics ({err})"))?;
+
+            Ok(None)
         }
     }
-
-    Ok(())
 }
 
 /// Export into the target format.
+///
+/// Returns the paths of all output files.
 fn export(
     world: &mut SystemWorld,
     document: &Document,
     command: &CompileCommand,
     watching: bool,
-) -> StrResult<()> {
-    match command.output_format()? {
+) -> SourceResult<Vec<PathBuf>> {
+    match command.output_format().at(Span::detached())? {
         OutputFormat::Png => {
             export_image(world, document, command, watching, ImageExportFormat::Png)
+                .at(Span::detached())
         }
         OutputFormat::Svg => {
             export_image(world, document, command, watching, ImageExportFormat::Svg)
+                .at(Span::detached())
         }
         OutputFormat::Pdf => export_pdf(document, command, world),
+        OutputFormat::Txt => export_txt(document, command).at(Span::detached()),
+        OutputFormat::Html => export_html(document, command, world),
+        OutputFormat::Epub => export_epub(document, command, world),
     }
 }
 
 /// Export to a PDF.
+///
+/// Content that cannot be represented in the requested PDF standard is
+/// reported as errors.
 fn export_pdf(
     document: &Document,
     command: &CompileCommand,
     world: &SystemWorld,
-) -> StrResult<()> {
-    let ident = world.input().to_string_lossy();
-    let buffer = typst_pdf::pdf(document, Some(&ident), now());
+) -> SourceResult<Vec<PathBuf>> {
+    let ident = world.input().map(|input| input.to_string_lossy());
+    let timestamp = convert_datetime(world.now());
+    let standard = match command.pdf_standard {
+        PdfStandard::V1_7 => typst_pdf::PdfStandard::V1_7,
+        PdfStandard::A2b => typst_pdf::PdfStandard::A2b,
+    };
+    let buffer = typst_pdf::pdf(
+        document,
+        ident.as_deref(),
+        timestamp,
+        command.exported_page_ranges(),
+        standard,
+    )?;
     let output = command.output();
-    fs::write(output, buffer)
-        .map_err(|err| eco_format!("failed to write PDF file ({err})"))?;
-    Ok(())
+    output
+        .write(&buffer)
+        .map_err(|err| eco_format!("failed to write PDF file ({err})"))
+        .at(Span::detached())?;
+    Ok(match output {
+        Output::Stdout => vec![],
+        Output::Path(path) => vec![path],
+    })
+}
+
+/// Export to plain text.
+fn export_txt(document: &Document, command: &CompileCommand) -> StrResult<Vec<PathBuf>> {
+    let text = typst_txt::txt(document, command.exported_page_ranges());
+    let output = command.output();
+    output
+        .write(text.as_bytes())
+        .map_err(|err| eco_format!("failed to write text file ({err})"))?;
+    Ok(match output {
+        Output::Stdout => vec![],
+        Output::Path(path) => vec![path],
+    })
 }
 
-/// Get the current date and time in UTC.
-fn now() -> Option<Datetime> {
-    let now = chrono::Local::now().naive_utc();
+/// Export to semantic HTML.
+///
+/// Content that cannot be represented in HTML is reported as errors.
+fn export_html(
+    document: &Document,
+    command: &CompileCommand,
+    world: &SystemWorld,
+) -> SourceResult<Vec<PathBuf>> {
+    let html = typst_html::html(world, document)?;
+    let output = command.output();
+    output
+        .write(html.as_bytes())
+        .map_err(|err| eco_format!("failed to write HTML file ({err})"))
+        .at(Span::detached())?;
+    Ok(match output {
+        Output::Stdout => vec![],
+        Output::Path(path) => vec![path],
+    })
+}
+
+/// Export to an EPUB e-book.
+///
+/// Content that cannot be represented in EPUB is reported as errors.
+fn export_epub(
+    document: &Document,
+    command: &CompileCommand,
+    world: &SystemWorld,
+) -> SourceResult<Vec<PathBuf>> {
+    let ident = world.input().map(|input| input.to_string_lossy());
+    let timestamp = convert_datetime(world.now());
+    let buffer = typst_epub::epub(world, document, ident.as_deref(), timestamp)?;
+    let output = command.output();
+    output
+        .write(&buffer)
+        .map_err(|err| eco_format!("failed to write EPUB file ({err})"))
+        .at(Span::detached())?;
+    Ok(match output {
+        Output::Stdout => vec![],
+        Output::Path(path) => vec![path],
+    })
+}
+
+/// Convert a date and time in UTC into a Typst datetime.
+fn convert_datetime(date_time: DateTime<Utc>) -> Option<Datetime> {
+    let now = date_time.naive_utc();
     Datetime::from_ymd_hms(
         now.year(),
         now.month().try_into().ok()?,
@@ -200,54 +343,140 @@ fn export_image(
     command: &CompileCommand,
     watching: bool,
     fmt: ImageExportFormat,
-) -> StrResult<()> {
+) -> StrResult<Vec<PathBuf>> {
+    // Only export the pages within the requested ranges, but keep their
+    // original indices for numbering and caching.
+    let exported_page_ranges = command.exported_page_ranges();
+    let exported_pages: Vec<(usize, &Frame)> = document
+        .pages
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| {
+            exported_page_ranges
+                .as_ref()
+                .map_or(true, |ranges| ranges.includes_page_index(i))
+        })
+        .collect();
+
     // Determine whether we have a `{n}` numbering.
     let output = command.output();
-    let string = output.to_str().unwrap_or_default();
+    let string = match &output {
+        Output::Path(path) => path.to_str().unwrap_or_default(),
+        Output::Stdout => "",
+    };
     let numbered = string.contains("{n}");
-    if !numbered && document.pages.len() > 1 {
-        bail!("cannot export multiple images without `{{n}}` in output path");
+    if !numbered && exported_pages.len() > 1 {
+        match output {
+            Output::Stdout => bail!(
+                "cannot export multiple images to stdout\n\
+                 consider selecting a single page with `--pages`"
+            ),
+            Output::Path(_) => {
+                bail!("cannot export multiple images without `{{n}}` in output path")
+            }
+        }
     }
 
     // Find a number width that accommodates all pages. For instance, the
     // first page should be numbered "001" if there are between 100 and
     // 999 pages.
     let width = 1 + document.pages.len().checked_ilog10().unwrap_or(0) as usize;
-    let mut storage;
+    let mut outputs = vec![];
 
     let cache = world.export_cache();
-    for (i, frame) in document.pages.iter().enumerate() {
-        let path = if numbered {
-            storage = string.replace("{n}", &format!("{:0width$}", i + 1));
-            Path::new(&storage)
+    for (i, frame) in exported_pages {
+        let output = if numbered {
+            Output::Path(PathBuf::from(
+                string.replace("{n}", &format!("{:0width$}", i + 1)),
+            ))
         } else {
-            output.as_path()
+            output.clone()
         };
 
-        // If we are not watching, don't use the cache.
-        // If the frame is in the cache, skip it.
-        // If the file does not exist, always create it.
-        if watching && cache.is_cached(i, frame) && path.exists() {
-            continue;
+        if let Output::Path(path) = &output {
+            outputs.push(path.clone());
+
+            // If we are not watching, don't use the cache.
+            // If the frame is in the cache, skip it.
+            // If the file does not exist, always create it.
+            if watching && cache.is_cached(i, frame) && path.exists() {
+                continue;
+            }
         }
 
         match fmt {
             ImageExportFormat::Png => {
                 let pixmap =
                     typst_render::render(frame, command.ppi / 72.0, Color::WHITE);
-                pixmap
-                    .save_png(path)
+                let buffer = pixmap
+                    .encode_png()
+                    .map_err(|err| eco_format!("failed to encode PNG file ({err})"))?;
+                output
+                    .write(&buffer)
                     .map_err(|err| eco_format!("failed to write PNG file ({err})"))?;
             }
             ImageExportFormat::Svg => {
                 let svg = typst_svg::svg(frame);
-                fs::write(path, svg.as_bytes())
+                output
+                    .write(svg.as_bytes())
                     .map_err(|err| eco_format!("failed to write SVG file ({err})"))?;
             }
         }
     }
 
-    Ok(())
+    Ok(outputs)
+}
+
+/// Write the files that the last compilation depended on to the path given
+/// with `--deps`, if any.
+fn write_deps(
+    world: &mut SystemWorld,
+    command: &CompileCommand,
+    outputs: &[PathBuf],
+) -> StrResult<()> {
+    let Some(path) = &command.deps else { return Ok(()) };
+
+    let mut inputs: Vec<PathBuf> = world.dependencies().collect();
+    inputs.sort();
+    inputs.dedup();
+
+    let contents = match command.deps_format {
+        DepsFormat::Make => make_deps(outputs, &inputs),
+        DepsFormat::Json => {
+            let strings = |paths: &[PathBuf]| -> Vec<String> {
+                paths.iter().map(|path| path.to_string_lossy().into()).collect()
+            };
+            json!({ "outputs": strings(outputs), "inputs": strings(&inputs) }).to_string()
+        }
+    };
+
+    fs::write(path, contents)
+        .map_err(|err| eco_format!("failed to write dependency file ({err})"))
+}
+
+/// Format dependencies as a Makefile rule without a recipe.
+fn make_deps(outputs: &[PathBuf], inputs: &[PathBuf]) -> String {
+    fn escape(path: &Path) -> String {
+        let mut escaped = String::new();
+        for c in path.to_string_lossy().chars() {
+            match c {
+                ' ' | '#' => escaped.push('\\'),
+                '$' => escaped.push('$'),
+                _ => {}
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    let mut rule = outputs.iter().map(|path| escape(path)).collect::<Vec<_>>().join(" ");
+    rule.push(':');
+    for input in inputs {
+        rule.push_str(" \\\n  ");
+        rule.push_str(&escape(input));
+    }
+    rule.push('\n');
+    rule
 }
 
 /// Opens the given file using:
@@ -270,9 +499,15 @@ pub fn print_diagnostics(
     warnings: &[SourceDiagnostic],
     diagnostic_format: DiagnosticFormat,
 ) -> Result<(), codespan_reporting::files::Error> {
+    // Keep the diagnostics of one document together when documents are
+    // compiled in parallel.
+    static PRINTING: Mutex<()> = Mutex::new(());
+    let _guard = PRINTING.lock().unwrap_or_else(PoisonError::into_inner);
+
     let mut w = match diagnostic_format {
         DiagnosticFormat::Human => color_stream(),
         DiagnosticFormat::Short => StandardStream::stderr(ColorChoice::Never),
+        DiagnosticFormat::Json => return print_json_diagnostics(world, errors, warnings),
     };
 
     let mut config = term::Config { tab_width: 2, ..Default::default() };
@@ -311,6 +546,69 @@ pub fn print_diagnostics(
     Ok(())
 }
 
+/// Print diagnostic messages as JSON, one object per line.
+fn print_json_diagnostics(
+    world: &SystemWorld,
+    errors: &[SourceDiagnostic],
+    warnings: &[SourceDiagnostic],
+) -> Result<(), codespan_reporting::files::Error> {
+    let mut w = std::io::stderr().lock();
+    for diagnostic in warnings.iter().chain(errors) {
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        let trace: Vec<_> = diagnostic
+            .trace
+            .iter()
+            .map(|point| {
+                json!({
+                    "message": point.v.to_string(),
+                    "span": json_span(world, point.span),
+                })
+            })
+            .collect();
+
+        let value = json!({
+            "severity": severity,
+            "message": diagnostic.message,
+            "span": json_span(world, diagnostic.span),
+            "hints": diagnostic.hints,
+            "trace": trace,
+        });
+
+        writeln!(w, "{value}")?;
+    }
+
+    Ok(())
+}
+
+/// Describe the location of a span with its file, byte offsets and one-based
+/// lines and columns.
+///
+/// Returns `null` for detached spans.
+fn json_span(world: &SystemWorld, span: Span) -> serde_json::Value {
+    let Some((id, range)) = span.id().zip(world.range(span)) else {
+        return serde_json::Value::Null;
+    };
+
+    let source = world.lookup(id);
+    let position = |byte: usize| {
+        json!({
+            "byte": byte,
+            "line": source.byte_to_line(byte).map(|line| line + 1),
+            "column": source.byte_to_column(byte).map(|column| column + 1),
+        })
+    };
+
+    json!({
+        "file": world.name(id).ok(),
+        "start": position(range.start),
+        "end": position(range.end),
+    })
+}
+
 /// Create a label for a span.
 fn label(world: &SystemWorld, span: Span) -> Option<Label<FileId>> {
     Some(Label::primary(span.id()?, world.range(span)?))
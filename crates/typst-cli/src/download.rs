@@ -174,8 +174,8 @@ impl RemoteReader {
         let len = self.downloaded_last_few_secs.len();
         let speed = if len > 0 { sum / len } else { self.content_len.unwrap_or(0) };
 
-        let total = as_time_unit(self.total_downloaded, false);
-        let speed_h = as_time_unit(speed, true);
+        let total = as_bytes_unit(self.total_downloaded, false);
+        let speed_h = as_bytes_unit(speed, true);
         let elapsed =
             time_suffix(Instant::now().saturating_duration_since(self.start_time));
 
@@ -187,7 +187,7 @@ impl RemoteReader {
                 format!(
                     "{} / {} ({:3.0} %) {} in {} ETA: {}",
                     total,
-                    as_time_unit(content_len, false),
+                    as_bytes_unit(content_len, false),
                     percent,
                     speed_h,
                     elapsed,
@@ -234,9 +234,9 @@ fn format_dhms(sec: u64) -> (u64, u8, u8, u8) {
     (days, hours, mins, sec)
 }
 
-/// Format a given size as a unit of time. Setting `include_suffix` to true
+/// Format a size in bytes with a binary unit. Setting `include_suffix` to true
 /// appends a '/s' (per second) suffix.
-fn as_time_unit(size: usize, include_suffix: bool) -> String {
+pub fn as_bytes_unit(size: usize, include_suffix: bool) -> String {
     const KI: f64 = 1024.0;
     const MI: f64 = KI * KI;
     const GI: f64 = KI * KI * KI;
@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use ecow::eco_format;
+use typst::diag::{bail, FileError, StrResult};
+use typst::syntax::{format, FormatConfig};
+
+use crate::args::FmtCommand;
+use crate::{print_error, set_failed};
+
+/// Execute a formatting command.
+pub fn fmt(command: &FmtCommand) -> StrResult<()> {
+    let config = FormatConfig {
+        indent: command.indent,
+        max_width: command.line_width,
+    };
+
+    let mut files = vec![];
+    for path in &command.input {
+        collect_files(path, &mut files).map_err(|err| FileError::from_io(err, path))?;
+    }
+
+    let mut unformatted = 0;
+    for path in &files {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| FileError::from_io(err, path))?;
+
+        // Files that can't be formatted are reported, but don't stop us from
+        // formatting the others.
+        let formatted = match format(&text, &config) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                set_failed();
+                print_error(&eco_format!("failed to format {} ({err})", path.display()))
+                    .expect("failed to print error");
+                continue;
+            }
+        };
+
+        if formatted == text {
+            continue;
+        }
+
+        if command.check {
+            println!("{}", path.display());
+            unformatted += 1;
+        } else {
+            std::fs::write(path, formatted).map_err(|err| {
+                eco_format!("failed to write {} ({err})", path.display())
+            })?;
+        }
+    }
+
+    if unformatted > 0 {
+        bail!("{unformatted} of {} files would be reformatted", files.len());
+    }
+
+    Ok(())
+}
+
+/// Collect the files at `path` in a stable order, descending into
+/// directories to find Typst files.
+pub fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.into());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry.extension().is_some_and(|ext| ext == "typ") {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
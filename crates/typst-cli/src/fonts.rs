@@ -1,6 +1,6 @@
-use std::cell::OnceCell;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use fontdb::{Database, Source};
 use typst::diag::StrResult;
@@ -41,8 +41,9 @@ pub struct FontSlot {
     /// The index of the font in its collection. Zero if the path does not point
     /// to a collection.
     index: u32,
-    /// The lazily loaded font.
-    font: OnceCell<Option<Font>>,
+    /// The lazily loaded font. Synchronized so that the fonts can be shared by
+    /// the worlds of a batch compilation.
+    font: OnceLock<Option<Font>>,
 }
 
 impl FontSlot {
@@ -55,6 +56,12 @@ impl FontSlot {
             })
             .clone()
     }
+
+    /// The path of the font's file if the font was loaded from disk.
+    pub fn loaded_path(&self) -> Option<&Path> {
+        let loaded = matches!(self.font.get(), Some(Some(_)));
+        (loaded && !self.path.as_os_str().is_empty()).then_some(self.path.as_path())
+    }
 }
 
 impl FontSearcher {
@@ -92,7 +99,7 @@ impl FontSearcher {
                 self.fonts.push(FontSlot {
                     path: path.clone(),
                     index: face.index,
-                    font: OnceCell::new(),
+                    font: OnceLock::new(),
                 });
             }
         }
@@ -112,7 +119,7 @@ impl FontSearcher {
                 self.fonts.push(FontSlot {
                     path: PathBuf::new(),
                     index: i as u32,
-                    font: OnceCell::from(Some(font)),
+                    font: OnceLock::from(Some(font)),
                 });
             }
         };
@@ -0,0 +1,141 @@
+use std::io::Write;
+use std::path::{Component, Path};
+
+use codespan_reporting::term::{self, termcolor};
+use ecow::eco_format;
+use termcolor::WriteColor;
+use typst::diag::{bail, FileError, StrResult};
+use typst::syntax::{PackageManifest, PackageSpec, VersionlessPackageSpec};
+
+use crate::args::InitCommand;
+use crate::color_stream;
+use crate::package::PackageStorage;
+
+/// Execute an initialization command.
+pub fn init(command: &InitCommand) -> StrResult<()> {
+    let package_storage = PackageStorage::from_args(&command.package);
+
+    // Parse the package specification. If the user didn't specify the version,
+    // we try to figure it out automatically by downloading the package index
+    // or searching the disk.
+    let spec: PackageSpec = command.template.parse().or_else(|err| {
+        // Try to parse without version, but prefer the error message of the
+        // normal package spec parsing if it fails.
+        let spec: VersionlessPackageSpec = command.template.parse().map_err(|_| err)?;
+        let version = package_storage.determine_latest_version(&spec)?;
+        StrResult::Ok(spec.at(version))
+    })?;
+
+    // Find or download the package.
+    let package_path = package_storage.prepare_package(&spec)?;
+
+    // Parse the manifest.
+    let manifest = parse_manifest(&package_path)?;
+    manifest.validate(&spec)?;
+
+    // Ensure that it is indeed a template.
+    let Some(template) = &manifest.template else {
+        bail!("package {spec} is not a template");
+    };
+
+    // Determine the directory at which we will create the project.
+    let project_dir = Path::new(command.dir.as_deref().unwrap_or(&manifest.package.name));
+
+    // Set up the project.
+    scaffold_project(project_dir, &package_path, &template.path)?;
+
+    // Print the summary.
+    print_summary(spec, project_dir, &template.entrypoint)
+        .map_err(|err| eco_format!("failed to print summary ({err})"))?;
+
+    Ok(())
+}
+
+/// Parses the manifest of the package located at `package_path`.
+fn parse_manifest(package_path: &Path) -> StrResult<PackageManifest> {
+    let toml_path = package_path.join("typst.toml");
+    let string = std::fs::read_to_string(&toml_path).map_err(|err| {
+        eco_format!(
+            "failed to read package manifest ({})",
+            FileError::from_io(err, &toml_path)
+        )
+    })?;
+
+    toml::from_str(&string)
+        .map_err(|err| eco_format!("package manifest is malformed ({})", err.message()))
+}
+
+/// Creates the project directory with the template's contents.
+fn scaffold_project(
+    project_dir: &Path,
+    package_path: &Path,
+    template_path: &str,
+) -> StrResult<()> {
+    if project_dir.exists() {
+        bail!("project directory already exists (at {})", project_dir.display());
+    }
+
+    // The template must not point outside of the package.
+    if !Path::new(template_path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        bail!("template directory must be within the package (is {template_path})");
+    }
+
+    let template_dir = package_path.join(template_path);
+    if !template_dir.exists() {
+        bail!("template directory does not exist (at {})", template_dir.display());
+    }
+
+    copy_dir(&template_dir, project_dir)
+        .map_err(|err| eco_format!("failed to create project directory ({err})"))?;
+
+    Ok(())
+}
+
+/// Recursively copies the contents of `src` into a new directory `dst`.
+fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints a summary after successful initialization.
+fn print_summary(
+    spec: PackageSpec,
+    project_dir: &Path,
+    entrypoint: &str,
+) -> std::io::Result<()> {
+    let mut gray = termcolor::ColorSpec::new();
+    gray.set_fg(Some(termcolor::Color::White));
+    gray.set_dimmed(true);
+
+    let mut out = color_stream();
+    let styles = term::Styles::default();
+
+    out.set_color(&styles.header_note)?;
+    write!(out, "Successfully created new project from {spec}")?;
+    out.reset()?;
+    writeln!(out, " 🎉")?;
+
+    writeln!(out, "To start writing, run:")?;
+    out.set_color(&gray)?;
+    write!(out, "> ")?;
+    out.reset()?;
+    writeln!(out, "cd {}", project_dir.display())?;
+    out.set_color(&gray)?;
+    write!(out, "> ")?;
+    out.reset()?;
+    writeln!(out, "typst watch {entrypoint}")?;
+    writeln!(out)?;
+    Ok(())
+}
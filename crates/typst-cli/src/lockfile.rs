@@ -0,0 +1,287 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use codespan_reporting::term::{self, termcolor};
+use ecow::{eco_format, EcoString};
+use serde::{Deserialize, Serialize};
+use termcolor::WriteColor;
+use typst::diag::{FileError, PackageError, PackageResult, StrResult};
+use typst::syntax::PackageSpec;
+
+use crate::color_stream;
+
+/// The file name of the lockfile, which lives next to the main file.
+pub const LOCKFILE_NAME: &str = "typst.lock";
+
+/// The version of the lockfile format.
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Pins the exact packages a project uses, along with the SHA-256 checksums
+/// of their archives.
+pub struct Lockfile {
+    /// Where the lockfile is stored.
+    path: PathBuf,
+    /// How deviations from the lockfile are handled.
+    mode: LockfileMode,
+    /// The packages and checksums currently recorded in the lockfile.
+    recorded: HashMap<PackageSpec, Option<EcoString>>,
+    /// The packages and checksums resolved in the ongoing compilation.
+    resolved: RefCell<HashMap<PackageSpec, Option<EcoString>>>,
+}
+
+/// How deviations from the lockfile are handled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LockfileMode {
+    /// Record packages that are not pinned yet. A checksum that differs from
+    /// the pinned one is an error.
+    Record,
+    /// Fail for packages that are not pinned and never write the lockfile.
+    Locked,
+    /// Like `Record`, but replace pinned checksums that differ.
+    Update,
+}
+
+impl Lockfile {
+    /// Load the lockfile at the given path. A missing file is treated like an
+    /// empty one.
+    pub fn load(path: PathBuf, mode: LockfileMode) -> StrResult<Self> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(FileError::from_io(err, &path).into()),
+        };
+
+        let data: LockfileData = toml::from_str(&text).map_err(|err| {
+            eco_format!("{LOCKFILE_NAME} is malformed ({})", err.message())
+        })?;
+
+        if data.version > LOCKFILE_VERSION {
+            return Err(eco_format!(
+                "{LOCKFILE_NAME} has version {}, but at most version \
+                 {LOCKFILE_VERSION} is supported",
+                data.version,
+            ));
+        }
+
+        let mut recorded = HashMap::new();
+        for package in data.packages {
+            let spec: PackageSpec = package.spec.parse().map_err(|err| {
+                eco_format!("{LOCKFILE_NAME} contains an invalid package ({err})")
+            })?;
+            recorded.insert(spec, package.sha256);
+        }
+
+        Ok(Self { path, mode, recorded, resolved: RefCell::default() })
+    }
+
+    /// Whether the lockfile pins a package, with or without a checksum.
+    pub fn contains(&self, spec: &PackageSpec) -> bool {
+        self.recorded.contains_key(spec)
+    }
+
+    /// The checksum that the lockfile pins for a package, if any.
+    pub fn pinned(&self, spec: &PackageSpec) -> Option<&str> {
+        self.recorded.get(spec)?.as_deref()
+    }
+
+    /// Whether a package was already checked in the ongoing compilation.
+    pub fn is_resolved(&self, spec: &PackageSpec) -> bool {
+        self.resolved.borrow().contains_key(spec)
+    }
+
+    /// Check a resolved package against the lockfile and remember it for the
+    /// next write.
+    ///
+    /// The checksum is that of the package's archive and is `None` for
+    /// packages that were never downloaded or whose cached files changed
+    /// since. A pinned checksum is only ever replaced in update mode and never
+    /// by an unknown one.
+    pub fn verify(&self, spec: &PackageSpec, sha256: Option<&str>) -> PackageResult<()> {
+        if self.is_resolved(spec) {
+            return Ok(());
+        }
+
+        match (self.recorded.get(spec), sha256) {
+            (Some(Some(expected)), Some(actual)) if actual != expected.as_str() => {
+                let message =
+                    eco_format!("checksum of {spec} does not match {LOCKFILE_NAME}");
+                if self.mode != LockfileMode::Update {
+                    return Err(PackageError::Other(Some(eco_format!(
+                        "{message} (if the change is expected, pin the new \
+                         checksum with `typst package fetch --update`)"
+                    ))));
+                }
+                print_warning(&eco_format!("{message}, updating it")).unwrap();
+            }
+            (Some(Some(_)), None) => {
+                return Err(PackageError::Other(Some(eco_format!(
+                    "checksum of {spec} is unknown and cannot be verified \
+                     against {LOCKFILE_NAME}"
+                ))));
+            }
+            (None, _) if self.mode == LockfileMode::Locked => {
+                return Err(PackageError::Other(Some(eco_format!(
+                    "{spec} is missing from {LOCKFILE_NAME}"
+                ))));
+            }
+            _ => {}
+        }
+
+        self.resolved
+            .borrow_mut()
+            .insert(spec.clone(), sha256.map(EcoString::from));
+        Ok(())
+    }
+
+    /// Forget the packages resolved in the previous compilation.
+    pub fn reset(&mut self) {
+        self.resolved.get_mut().clear();
+    }
+
+    /// Record the packages resolved in the last compilation in the lockfile
+    /// on disk, unless nothing changed.
+    ///
+    /// Entries of other packages are kept, since other documents next to the
+    /// same lockfile may use them. In locked mode, the lockfile is never
+    /// touched.
+    pub fn write(&mut self) -> StrResult<()> {
+        let resolved = self.resolved.get_mut();
+        if self.mode == LockfileMode::Locked
+            || resolved
+                .iter()
+                .all(|(spec, sha256)| self.recorded.get(spec) == Some(sha256))
+        {
+            return Ok(());
+        }
+
+        // The documents of a batch compilation may share a lockfile, so we
+        // merge with its latest state on disk.
+        static WRITING: Mutex<()> = Mutex::new(());
+        let _guard = WRITING.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut recorded = Self::load(self.path.clone(), self.mode)?.recorded;
+        recorded
+            .extend(resolved.iter().map(|(spec, sha256)| (spec.clone(), sha256.clone())));
+
+        // Sort the packages so that the file is stable across runs.
+        let mut packages: Vec<_> = recorded
+            .iter()
+            .map(|(spec, sha256)| LockedPackage {
+                spec: eco_format!("{spec}"),
+                sha256: sha256.clone(),
+            })
+            .collect();
+        packages.sort_by(|a, b| a.spec.cmp(&b.spec));
+
+        let data = LockfileData { version: LOCKFILE_VERSION, packages };
+        let body = toml::to_string(&data)
+            .map_err(|err| eco_format!("failed to serialize {LOCKFILE_NAME} ({err})"))?;
+        let text = format!(
+            "# This file is automatically generated by Typst.\n\
+             # It is not intended for manual editing.\n{body}"
+        );
+
+        fs::write(&self.path, text).map_err(|err| FileError::from_io(err, &self.path))?;
+        self.recorded = recorded;
+        Ok(())
+    }
+}
+
+/// The serialized form of a lockfile.
+#[derive(Serialize, Deserialize)]
+struct LockfileData {
+    /// The version of the lockfile format.
+    #[serde(default)]
+    version: u32,
+    /// The pinned packages.
+    #[serde(default, rename = "package")]
+    packages: Vec<LockedPackage>,
+}
+
+/// A package pinned in the lockfile.
+#[derive(Serialize, Deserialize)]
+struct LockedPackage {
+    /// The package specification, e.g. `@preview/cetz:0.2.0`.
+    spec: EcoString,
+    /// The SHA-256 checksum of the package's archive in hexadecimal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<EcoString>,
+}
+
+/// Print a warning about the lockfile.
+fn print_warning(message: &str) -> io::Result<()> {
+    let mut w = color_stream();
+    let styles = term::Styles::default();
+
+    w.set_color(&styles.header_warning)?;
+    write!(w, "warning")?;
+
+    w.reset()?;
+    writeln!(w, ": {message}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PINNED: &str = "0123abcd";
+
+    fn spec() -> PackageSpec {
+        "@preview/example:0.1.0".parse().unwrap()
+    }
+
+    fn lockfile(mode: LockfileMode) -> Lockfile {
+        Lockfile {
+            path: PathBuf::from(LOCKFILE_NAME),
+            mode,
+            recorded: [(spec(), Some(PINNED.into()))].into_iter().collect(),
+            resolved: RefCell::default(),
+        }
+    }
+
+    fn resolved(lockfile: &Lockfile) -> Option<EcoString> {
+        lockfile.resolved.borrow().get(&spec()).cloned().flatten()
+    }
+
+    const MODES: [LockfileMode; 3] =
+        [LockfileMode::Record, LockfileMode::Locked, LockfileMode::Update];
+
+    #[test]
+    fn test_lockfile_verify_pinned() {
+        for mode in MODES {
+            let lockfile = lockfile(mode);
+            assert!(lockfile.verify(&spec(), Some(PINNED)).is_ok());
+            assert_eq!(resolved(&lockfile).as_deref(), Some(PINNED));
+        }
+    }
+
+    #[test]
+    fn test_lockfile_verify_mismatch() {
+        assert!(lockfile(LockfileMode::Record).verify(&spec(), Some("ffff")).is_err());
+        assert!(lockfile(LockfileMode::Locked).verify(&spec(), Some("ffff")).is_err());
+
+        let lockfile = lockfile(LockfileMode::Update);
+        assert!(lockfile.verify(&spec(), Some("ffff")).is_ok());
+        assert_eq!(resolved(&lockfile).as_deref(), Some("ffff"));
+    }
+
+    #[test]
+    fn test_lockfile_verify_missing_checksum() {
+        for mode in MODES {
+            let lockfile = lockfile(mode);
+            assert!(lockfile.verify(&spec(), None).is_err());
+            assert!(!lockfile.is_resolved(&spec()));
+        }
+    }
+
+    #[test]
+    fn test_lockfile_verify_unpinned() {
+        let other: PackageSpec = "@preview/other:1.0.0".parse().unwrap();
+        assert!(lockfile(LockfileMode::Record).verify(&other, Some(PINNED)).is_ok());
+        assert!(lockfile(LockfileMode::Update).verify(&other, Some(PINNED)).is_ok());
+        assert!(lockfile(LockfileMode::Locked).verify(&other, Some(PINNED)).is_err());
+    }
+}
@@ -0,0 +1,750 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use ecow::{eco_format, EcoString};
+use serde_json::{json, Value};
+use typst::diag::{Severity, SourceDiagnostic, StrResult};
+use typst::eval::Tracer;
+use typst::layout::{Abs, Point, Position};
+use typst::model::Document;
+use typst::syntax::{is_newline, FileId, Source, Span, VirtualPath};
+use typst::{World, WorldExt};
+use typst_ide::{CompletionKind, Jump, Tooltip};
+
+use crate::args::{Input, LspCommand};
+use crate::set_failed;
+use crate::world::SystemWorld;
+
+/// The error code for requests with an unknown method.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The error code for requests with malformed parameters.
+const INVALID_PARAMS: i64 = -32602;
+/// The error code for requests that could not be handled.
+const REQUEST_FAILED: i64 = -32803;
+
+/// The commands that can be run through `workspace/executeCommand`, as
+/// documented on [`LspCommand`].
+const COMMANDS: &[&str] = &["typst.jumpFromClick", "typst.jumpFromCursor"];
+
+/// The result of a request.
+type Response = Result<Value, ResponseError>;
+
+/// The error code and message of a failed request.
+type ResponseError = (i64, EcoString);
+
+/// Execute a language server command.
+pub fn lsp(command: &LspCommand) -> StrResult<()> {
+    let mut server = Server::new(command);
+    let mut input = io::stdin().lock();
+
+    loop {
+        let message = read_message(&mut input)
+            .map_err(|err| eco_format!("failed to read message ({err})"))?;
+        let Some(message) = message else { break };
+
+        let exit = server
+            .handle(message)
+            .map_err(|err| eco_format!("failed to send message ({err})"))?;
+        if exit {
+            break;
+        }
+    }
+
+    // The client is supposed to shut the server down before it exits.
+    if !server.shutdown {
+        set_failed();
+    }
+
+    Ok(())
+}
+
+/// A language server for one project.
+///
+/// The server compiles whenever a document is opened or changed and publishes
+/// the resulting diagnostics. The contents of open documents are kept as
+/// overlays in the world, so that unsaved changes are compiled.
+struct Server<'a> {
+    /// The command line arguments.
+    command: &'a LspCommand,
+    /// The project root, from the command line or the client's workspace.
+    root: Option<PathBuf>,
+    /// The world, which is created when the first document is opened.
+    world: Option<SystemWorld>,
+    /// The document of the last successful compilation.
+    document: Option<Document>,
+    /// The most recently opened or changed file.
+    active: Option<PathBuf>,
+    /// The URIs of files for which diagnostics were published.
+    published: HashSet<String>,
+    /// The id of the last request to the client.
+    last_id: Cell<u64>,
+    /// Whether the client requested a shutdown.
+    shutdown: bool,
+}
+
+impl<'a> Server<'a> {
+    /// Create a server that wasn't initialized yet.
+    fn new(command: &'a LspCommand) -> Self {
+        Self {
+            command,
+            root: command.common.root.clone(),
+            world: None,
+            document: None,
+            active: None,
+            published: HashSet::new(),
+            last_id: Cell::new(0),
+            shutdown: false,
+        }
+    }
+
+    /// Handle a message from the client.
+    ///
+    /// Returns `true` once the client asks the server to exit.
+    fn handle(&mut self, message: Value) -> io::Result<bool> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+
+        // Responses to our own requests don't need handling.
+        if method.is_empty() {
+            return Ok(false);
+        }
+
+        let response = match self.request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+
+        send(&response)?;
+        Ok(false)
+    }
+
+    /// Handle a notification, which doesn't need a response.
+    fn notification(&mut self, method: &str, params: Value) -> io::Result<bool> {
+        let document = &params["textDocument"];
+        match method {
+            "exit" => return Ok(true),
+            "textDocument/didOpen" => {
+                let Some(path) = document_path(document) else { return Ok(false) };
+                let text = document["text"].as_str().unwrap_or_default();
+                self.open(path, text.into())?;
+            }
+            "textDocument/didChange" => {
+                let Some(path) = document_path(document) else { return Ok(false) };
+                let changes = params["contentChanges"].as_array();
+                self.change(path, changes.map_or(&[], Vec::as_slice))?;
+            }
+            "textDocument/didClose" => {
+                let Some(path) = document_path(document) else { return Ok(false) };
+                if let Some(world) = &mut self.world {
+                    world.set_overlay(path, None);
+                    self.compile()?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Handle a request.
+    fn request(&mut self, method: &str, params: Value) -> Response {
+        match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/completion" => self.completion(&params),
+            "textDocument/hover" => self.hover(&params),
+            "workspace/executeCommand" => self.execute_command(&params),
+            _ => Err((METHOD_NOT_FOUND, eco_format!("unknown method {method}"))),
+        }
+    }
+
+    /// Determine the project root and announce the server's capabilities.
+    fn initialize(&mut self, params: &Value) -> Value {
+        if self.root.is_none() {
+            let folder = &params["workspaceFolders"][0]["uri"];
+            self.root = folder
+                .as_str()
+                .or_else(|| params["rootUri"].as_str())
+                .and_then(uri_to_path);
+        }
+
+        json!({
+            "capabilities": {
+                "positionEncoding": "utf-16",
+                "textDocumentSync": { "openClose": true, "change": 2 },
+                "completionProvider": {
+                    "triggerCharacters": ["#", ".", "@", "<", "("],
+                },
+                "hoverProvider": true,
+                "executeCommandProvider": { "commands": COMMANDS },
+            },
+            "serverInfo": {
+                "name": "typst",
+                "version": crate::typst_version(),
+            },
+        })
+    }
+
+    /// Update the contents of a document and recompile.
+    fn open(&mut self, path: PathBuf, text: String) -> io::Result<()> {
+        if self.world.is_none() {
+            let input = self.command.main.clone().unwrap_or_else(|| path.clone());
+            let mut args = self.command.common.clone();
+            args.root = self.root.clone();
+
+            match SystemWorld::new(&Input::Path(input), &args) {
+                Ok(world) => self.world = Some(world),
+                Err(err) => return show_error(&err),
+            }
+        }
+
+        if let Some(world) = &mut self.world {
+            world.set_overlay(path.clone(), Some(text));
+        }
+
+        self.active = Some(path);
+        self.compile()
+    }
+
+    /// Apply the changes to an open document and recompile.
+    ///
+    /// We ask for incremental synchronization, so each change replaces a
+    /// range of the text that is given in positions of the text after the
+    /// previous changes. A change without a range replaces the whole text.
+    fn change(&mut self, path: PathBuf, changes: &[Value]) -> io::Result<()> {
+        let Some(world) = &mut self.world else { return Ok(()) };
+
+        let mut valid = true;
+        let result = world.edit_overlay(path.clone(), |source| {
+            for change in changes {
+                let text = change["text"].as_str().unwrap_or_default();
+                let range = &change["range"];
+                if range.is_null() {
+                    source.replace(text);
+                    continue;
+                }
+
+                let (Some(start), Some(end)) =
+                    (offset(source, &range["start"]), offset(source, &range["end"]))
+                else {
+                    valid = false;
+                    break;
+                };
+
+                if start > end {
+                    valid = false;
+                    break;
+                }
+
+                source.edit(start..end, text);
+            }
+        });
+
+        if let Err(err) = result {
+            return show_error(&eco_format!("failed to edit document ({err})"));
+        }
+
+        if !valid {
+            show_error("document change is out of bounds")?;
+        }
+
+        self.active = Some(path);
+        self.compile()
+    }
+
+    /// Compile the main file and publish the diagnostics.
+    fn compile(&mut self) -> io::Result<()> {
+        let Some(world) = &mut self.world else { return Ok(()) };
+
+        if let Some(main) = self.command.main.as_ref().or(self.active.as_ref()) {
+            if let Err(err) = world.set_main(main) {
+                return show_error(&err);
+            }
+        }
+
+        world.reset();
+        if let Err(err) = world.source(world.main()) {
+            return show_error(&eco_format!("failed to read main file ({err})"));
+        }
+
+        let mut tracer = Tracer::new();
+        let result = typst::compile(world, &mut tracer);
+        let mut diagnostics = tracer.warnings().to_vec();
+        match result {
+            Ok(document) => self.document = Some(document),
+            Err(errors) => diagnostics.extend(errors.iter().cloned()),
+        }
+
+        // Evict cache entries that weren't used recently, so that memory
+        // usage doesn't grow without bounds across edits.
+        comemo::evict(10);
+
+        self.publish(&diagnostics)
+    }
+
+    /// Publish diagnostics, clearing those of files that have none anymore.
+    fn publish(&mut self, diagnostics: &[SourceDiagnostic]) -> io::Result<()> {
+        let Some(world) = &self.world else { return Ok(()) };
+
+        let mut files: HashMap<String, Vec<Value>> = HashMap::new();
+        for uri in self.published.drain() {
+            files.insert(uri, vec![]);
+        }
+
+        for diagnostic in diagnostics {
+            // Diagnostics without a location are shown at the start of the
+            // main file.
+            let id = diagnostic.span.id().unwrap_or(world.main());
+            let Some(location) = lsp_location(world, id, diagnostic.span) else {
+                continue;
+            };
+
+            let mut message = diagnostic.message.to_string();
+            for hint in &diagnostic.hints {
+                write!(message, "\nhint: {hint}").unwrap();
+            }
+
+            let related: Vec<_> = diagnostic
+                .trace
+                .iter()
+                .filter_map(|point| {
+                    let location = lsp_location(world, point.span.id()?, point.span)?;
+                    Some(json!({ "location": location, "message": point.v.to_string() }))
+                })
+                .collect();
+
+            let severity = match diagnostic.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            };
+
+            files
+                .entry(location["uri"].as_str().unwrap().into())
+                .or_default()
+                .push(json!({
+                    "range": location["range"],
+                    "severity": severity,
+                    "source": "typst",
+                    "message": message,
+                    "relatedInformation": related,
+                }));
+        }
+
+        for (uri, diagnostics) in files {
+            if !diagnostics.is_empty() {
+                self.published.insert(uri.clone());
+            }
+            notify(
+                "textDocument/publishDiagnostics",
+                json!({ "uri": uri, "diagnostics": diagnostics }),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Complete the code at a position.
+    fn completion(&self, params: &Value) -> Response {
+        let (world, source, cursor) = self.locate(params)?;
+
+        // Completions that the user explicitly asked for are more eager.
+        let explicit = params["context"]["triggerKind"].as_u64() == Some(1);
+        let Some((from, completions)) = typst_ide::autocomplete(
+            world,
+            self.document.as_ref(),
+            &source,
+            cursor,
+            explicit,
+        ) else {
+            return Ok(Value::Null);
+        };
+
+        let range = lsp_range(&source, from..cursor);
+        let items: Vec<_> = completions
+            .iter()
+            .map(|completion| {
+                let kind = match completion.kind {
+                    CompletionKind::Syntax => 15,
+                    CompletionKind::Func => 3,
+                    CompletionKind::Type => 7,
+                    CompletionKind::Param => 6,
+                    CompletionKind::Constant => 21,
+                    CompletionKind::Symbol(_) => 1,
+                };
+                let detail = match completion.kind {
+                    CompletionKind::Symbol(c) if completion.detail.is_none() => {
+                        Some(eco_format!("{c}"))
+                    }
+                    _ => completion.detail.clone(),
+                };
+                let apply = completion.apply.as_ref().unwrap_or(&completion.label);
+                json!({
+                    "label": completion.label,
+                    "kind": kind,
+                    "detail": detail,
+                    "insertTextFormat": 2,
+                    "textEdit": { "range": range, "newText": snippet(apply) },
+                })
+            })
+            .collect();
+
+        Ok(json!({ "isIncomplete": false, "items": items }))
+    }
+
+    /// Describe the item at a position.
+    fn hover(&self, params: &Value) -> Response {
+        let (world, source, cursor) = self.locate(params)?;
+        let tooltip = typst_ide::tooltip(world, self.document.as_ref(), &source, cursor);
+
+        let value = match tooltip {
+            Some(Tooltip::Text(text)) => text.to_string(),
+            Some(Tooltip::Code(code)) => format!("```typst\n{code}\n```"),
+            None => return Ok(Value::Null),
+        };
+
+        Ok(json!({ "contents": { "kind": "markdown", "value": value } }))
+    }
+
+    /// Run one of the server's [`COMMANDS`].
+    ///
+    /// `typst.jumpFromClick` takes a one-based page number and a point in
+    /// page coordinates (in points). If the click leads to source code or a
+    /// URL, the client is asked to show it. Source code is returned as a
+    /// location, like `textDocument/definition` does. `typst.jumpFromCursor`
+    /// takes a document and position like other requests and returns the
+    /// matching position in the compiled document.
+    fn execute_command(&self, params: &Value) -> Response {
+        let argument = &params["arguments"][0];
+        match params["command"].as_str().unwrap_or_default() {
+            "typst.jumpFromClick" => {
+                let (Some(world), Some(document)) = (&self.world, &self.document) else {
+                    return Ok(Value::Null);
+                };
+
+                let (Some(page), Some(x), Some(y)) = (
+                    argument["page"].as_u64(),
+                    argument["x"].as_f64(),
+                    argument["y"].as_f64(),
+                ) else {
+                    return Err((INVALID_PARAMS, "expected page, x and y".into()));
+                };
+
+                let Some(frame) =
+                    (page as usize).checked_sub(1).and_then(|i| document.pages.get(i))
+                else {
+                    return Ok(Value::Null);
+                };
+
+                let click = Point::new(Abs::pt(x), Abs::pt(y));
+                match typst_ide::jump_from_click(world, document, frame, click) {
+                    Some(Jump::Source(id, offset)) => {
+                        let (Ok(path), Ok(source)) =
+                            (world.system_path(id), world.source(id))
+                        else {
+                            return Ok(Value::Null);
+                        };
+                        let uri = path_to_uri(&path);
+                        let range = lsp_range(&source, offset..offset);
+                        self.request_client(
+                            "window/showDocument",
+                            json!({ "uri": uri, "takeFocus": true, "selection": range }),
+                        )
+                        .map_err(|err| (REQUEST_FAILED, eco_format!("{err}")))?;
+                        Ok(json!({ "uri": uri, "range": range }))
+                    }
+                    Some(Jump::Url(url)) => {
+                        self.request_client(
+                            "window/showDocument",
+                            json!({ "uri": url, "external": true }),
+                        )
+                        .map_err(|err| (REQUEST_FAILED, eco_format!("{err}")))?;
+                        Ok(json!({ "url": url }))
+                    }
+                    Some(Jump::Position(position)) => Ok(document_position(position)),
+                    None => Ok(Value::Null),
+                }
+            }
+            "typst.jumpFromCursor" => {
+                let (_, source, cursor) = self.locate(argument)?;
+                let Some(document) = &self.document else { return Ok(Value::Null) };
+                Ok(typst_ide::jump_from_cursor(document, &source, cursor)
+                    .map(document_position)
+                    .unwrap_or(Value::Null))
+            }
+            command => Err((INVALID_PARAMS, eco_format!("unknown command {command}"))),
+        }
+    }
+
+    /// Find the world, source file and cursor offset that a request with a
+    /// text document and position refers to.
+    fn locate(
+        &self,
+        params: &Value,
+    ) -> Result<(&SystemWorld, Source, usize), ResponseError> {
+        let Some(world) = &self.world else {
+            return Err((REQUEST_FAILED, "no document was opened".into()));
+        };
+
+        let path = document_path(&params["textDocument"])
+            .ok_or((INVALID_PARAMS, "expected a file URI".into()))?;
+        let vpath = VirtualPath::within_root(&path, world.root())
+            .ok_or((REQUEST_FAILED, "file is not contained in project root".into()))?;
+        let source = world.source(FileId::new(None, vpath)).map_err(|err| {
+            (REQUEST_FAILED, eco_format!("failed to read source ({err})"))
+        })?;
+        let cursor = offset(&source, &params["position"])
+            .ok_or((INVALID_PARAMS, "position is out of bounds".into()))?;
+
+        Ok((world, source, cursor))
+    }
+
+    /// Send a request to the client. We don't wait for the response.
+    fn request_client(&self, method: &str, params: Value) -> io::Result<()> {
+        let id = self.last_id.get() + 1;
+        self.last_id.set(id);
+        send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }))
+    }
+}
+
+/// The canonical path of the file behind a text document identifier.
+fn document_path(document: &Value) -> Option<PathBuf> {
+    let path = uri_to_path(document["uri"].as_str()?)?;
+    Some(path.canonicalize().unwrap_or(path))
+}
+
+/// The LSP location of a span in a file.
+fn lsp_location(world: &SystemWorld, id: FileId, span: Span) -> Option<Value> {
+    let uri = path_to_uri(&world.system_path(id).ok()?);
+    let source = world.source(id).ok()?;
+    let range = world.range(span).unwrap_or(0..0);
+    Some(json!({ "uri": uri, "range": lsp_range(&source, range) }))
+}
+
+/// Convert a byte range into an LSP range.
+fn lsp_range(source: &Source, range: Range<usize>) -> Value {
+    json!({ "start": lsp_position(source, range.start), "end": lsp_position(source, range.end) })
+}
+
+/// Convert a byte offset into an LSP position, whose characters are counted
+/// in UTF-16 code units.
+fn lsp_position(source: &Source, offset: usize) -> Value {
+    let line = source.byte_to_line(offset).unwrap_or(0);
+    let line_start = source.line_to_byte(line).unwrap_or(0);
+    let character = source
+        .byte_to_utf16(offset)
+        .unwrap_or(0)
+        .saturating_sub(source.byte_to_utf16(line_start).unwrap_or(0));
+    json!({ "line": line, "character": character })
+}
+
+/// Convert an LSP position into a byte offset.
+///
+/// Like the LSP specification demands, characters past the end of the line
+/// refer to its end.
+fn offset(source: &Source, position: &Value) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+    let range = source.line_to_range(line)?;
+    let text = source.text()[range.clone()].trim_end_matches(is_newline);
+    let start = source.byte_to_utf16(range.start)?;
+    let end = source.byte_to_utf16(range.start + text.len())?;
+    source.utf16_to_byte((start + character).min(end))
+}
+
+/// Describe a position in the document with a one-based page number and a
+/// point in points.
+fn document_position(position: Position) -> Value {
+    json!({
+        "page": position.page.get(),
+        "x": position.point.x.to_pt(),
+        "y": position.point.y.to_pt(),
+    })
+}
+
+/// Convert the `${name}` placeholders of a completion into the numbered
+/// placeholders of LSP snippets, escaping the rest.
+fn snippet(apply: &str) -> String {
+    fn escape(text: &str, out: &mut String) {
+        for c in text.chars() {
+            if matches!(c, '$' | '}' | '\\') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+    }
+
+    let mut out = String::new();
+    let mut rest = apply;
+    let mut index = 0;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else { break };
+        escape(&rest[..start], &mut out);
+        index += 1;
+        let name = &rest[start + 2..start + len];
+        if name.is_empty() {
+            write!(out, "${index}").unwrap();
+        } else {
+            write!(out, "${{{index}:").unwrap();
+            escape(name, &mut out);
+            out.push('}');
+        }
+        rest = &rest[start + len + 1..];
+    }
+
+    escape(rest, &mut out);
+    out
+}
+
+/// Convert a `file://` URI into a path.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut iter = encoded.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+
+    let decoded = String::from_utf8(bytes).ok()?;
+
+    // Windows paths are written like `/C:/dir/file.typ` in URIs.
+    let path = match decoded.as_bytes() {
+        [b'/', _, b':', ..] if cfg!(windows) => &decoded[1..],
+        _ => decoded.as_str(),
+    };
+
+    Some(PathBuf::from(path))
+}
+
+/// Convert a path into a `file://` URI.
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'/' => uri.push(byte as char),
+            _ => write!(uri, "%{byte:02X}").unwrap(),
+        }
+    }
+
+    uri
+}
+
+/// Tell the user about an error through the client.
+fn show_error(message: &str) -> io::Result<()> {
+    notify("window/showMessage", json!({ "type": 1, "message": message }))
+}
+
+/// Send a notification to the client.
+fn notify(method: &str, params: Value) -> io::Result<()> {
+    send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+}
+
+/// Write a message with its header to stdout.
+fn send(message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    let mut out = io::stdout().lock();
+    write!(out, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    out.flush()
+}
+
+/// Read a message with its header from the input.
+///
+/// Returns `None` once the input is closed.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing content length")
+    })?;
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lsp_position_round_trip() {
+        let source = Source::detached("a\n🏳️‍🌈 b\nc");
+        let b = source.text().find('b').unwrap();
+        let position = lsp_position(&source, b);
+        assert_eq!(position, json!({ "line": 1, "character": 7 }));
+        assert_eq!(offset(&source, &position), Some(b));
+        assert_eq!(offset(&source, &json!({ "line": 5, "character": 0 })), None);
+    }
+
+    #[test]
+    fn test_lsp_offset_past_line_end() {
+        let source = Source::detached("ab\r\ncd");
+        let position = |character| json!({ "line": 0, "character": character });
+        assert_eq!(offset(&source, &position(2)), Some(2));
+        assert_eq!(offset(&source, &position(100)), Some(2));
+        assert_eq!(offset(&source, &json!({ "line": 1, "character": 100 })), Some(6));
+    }
+
+    #[test]
+    fn test_lsp_position_out_of_bounds() {
+        let source = Source::detached("ab\ncd");
+        assert_eq!(lsp_position(&source, 100), json!({ "line": 0, "character": 0 }));
+    }
+}
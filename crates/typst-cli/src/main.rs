@@ -1,9 +1,16 @@
 mod args;
+mod batch;
 mod compile;
 mod download;
+mod fmt;
 mod fonts;
+mod init;
+mod lockfile;
+mod lsp;
+mod merge;
 mod package;
 mod query;
+mod serve;
 mod tracing;
 #[cfg(feature = "self-update")]
 mod update;
@@ -42,7 +49,11 @@ fn main() -> ExitCode {
     let res = match &ARGS.command {
         Command::Compile(command) => crate::compile::compile(command.clone()),
         Command::Watch(command) => crate::watch::watch(command.clone()),
+        Command::Init(command) => crate::init::init(command),
         Command::Query(command) => crate::query::query(command),
+        Command::Fmt(command) => crate::fmt::fmt(command),
+        Command::Lsp(command) => crate::lsp::lsp(command),
+        Command::Package(command) => crate::package::package(command),
         Command::Fonts(command) => crate::fonts::fonts(command),
         Command::Update(command) => crate::update::update(command),
     };
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ecow::{eco_format, EcoString};
+use typst::diag::{bail, FileError, SourceResult, StrResult};
+use typst::foundations::{Bytes, Dict, IntoValue, Value};
+use typst::loading::{csv, json, Delimiter, Readable};
+use typst::syntax::{Span, Spanned};
+
+use crate::args::{CompileCommand, Output};
+use crate::compile::compile_once;
+use crate::world::SystemWorld;
+
+/// A record of a data file, with its fields in their original order.
+type Record = Vec<(EcoString, EcoString)>;
+
+/// Execute a mail merge, which compiles the input once for every record of a
+/// data file.
+///
+/// The fields of the record are visible through `sys.inputs` and the output
+/// path may refer to them as `{field}`. The world and its library are reused
+/// between the records, so that only the modules that refer to `sys` are
+/// evaluated again and layouts that don't depend on the record are cached.
+pub fn compile_merge(command: &CompileCommand, data: &Path) -> StrResult<()> {
+    let records = load_records(data)?;
+
+    let Some(Output::Path(pattern)) = &command.output else {
+        bail!(
+            "mail merge requires an output path\n\
+             consider naming the outputs after a field, like `out/{{name}}.pdf`"
+        );
+    };
+    let pattern = pattern.to_str().ok_or("output path must be valid UTF-8")?;
+
+    // Determine all outputs upfront so that no record silently overwrites the
+    // output of another one.
+    let mut outputs = vec![];
+    let mut seen = HashMap::new();
+    for (i, record) in records.iter().enumerate() {
+        let output = fill_pattern(pattern, record)
+            .map(PathBuf::from)
+            .map_err(|err| eco_format!("record {}: {err}", i + 1))?;
+        if let Some(prev) = seen.insert(output.clone(), i) {
+            bail!(
+                "records {} and {} would both be written to {}\n\
+                 consider naming the outputs after a field that differs between \
+                 records, like `out/{{name}}.pdf`",
+                prev + 1,
+                i + 1,
+                output.display(),
+            );
+        }
+        outputs.push(output);
+    }
+
+    let base: Dict = command
+        .common
+        .inputs
+        .iter()
+        .map(|(k, v)| (k.as_str().into(), v.as_str().into_value()))
+        .collect();
+
+    let mut world = SystemWorld::new(command.input(), &command.common)?;
+    let mut failed = 0;
+    for (record, output) in records.iter().zip(outputs) {
+        let mut inputs = base.clone();
+        for (key, value) in record {
+            inputs.insert(key.as_str().into(), value.as_str().into_value());
+        }
+
+        world.set_inputs(inputs);
+        world.reset();
+
+        let mut job = command.clone();
+        job.output = Some(Output::Path(output));
+        job.data = None;
+        if compile_once(&mut world, &mut job, false)?.is_none() {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {} records failed to compile", records.len());
+    }
+
+    Ok(())
+}
+
+/// Load the records of a CSV file with a header row or of a JSON array of
+/// objects.
+///
+/// Values of JSON records that aren't strings are turned into JSON, so that
+/// documents can decode them with `json.decode`.
+fn load_records(path: &Path) -> StrResult<Vec<Record>> {
+    let data: Bytes = fs::read(path).map_err(|err| FileError::from_io(err, path))?.into();
+    let data = Spanned::new(Readable::Bytes(data), Span::detached());
+
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "csv" => {
+            let rows = first_error(csv::decode(data, Delimiter::default()))?;
+            let mut rows = rows.into_iter().map(Value::cast::<Vec<EcoString>>);
+            let Some(header) = rows.next().transpose()? else {
+                return Ok(vec![]);
+            };
+            // The parser already rejects rows whose length differs from the
+            // first row's, but a record must never silently lose fields. The
+            // header is the first row of the file.
+            rows.enumerate()
+                .map(|(i, row)| -> StrResult<Record> {
+                    let row = row?;
+                    if row.len() != header.len() {
+                        bail!(
+                            "row {} of the data file has {} fields, but the header \
+                             has {}",
+                            i + 2,
+                            row.len(),
+                            header.len(),
+                        );
+                    }
+                    Ok(header.iter().cloned().zip(row).collect())
+                })
+                .collect()
+        }
+        "json" => {
+            let records = first_error(json::decode(data))?
+                .cast::<Vec<Dict>>()
+                .map_err(|err| eco_format!("data must be an array of objects ({err})"))?;
+            records
+                .into_iter()
+                .map(|record| {
+                    record
+                        .into_iter()
+                        .map(|(key, value)| -> StrResult<(EcoString, EcoString)> {
+                            let value = match value {
+                                Value::Str(string) => string.into(),
+                                value => serde_json::to_string(&value)
+                                    .map_err(|err| eco_format!("{err}"))?
+                                    .into(),
+                            };
+                            Ok((key.into(), value))
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+        _ => bail!(
+            "could not infer the format of the data file {}\n\
+             the data file must be a CSV or JSON file",
+            path.display(),
+        ),
+    }
+}
+
+/// Reduce the errors of loading a data file to the first one's message.
+fn first_error<T>(result: SourceResult<T>) -> StrResult<T> {
+    result.map_err(|errors| {
+        errors
+            .first()
+            .map_or_else(|| "failed to load data".into(), |error| error.message.clone())
+    })
+}
+
+/// Replace `{field}` in the output path pattern with the record's values.
+///
+/// The pattern is filled in a single pass, so braces in values are kept as
+/// they are. Path separators in values are replaced and values that would
+/// turn into a `.` or `..` component are rejected, so that a value can't
+/// place its output in another directory.
+fn fill_pattern(pattern: &str, record: &Record) -> StrResult<String> {
+    let mut output = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let field = after.find('}').and_then(|end| {
+            let key = &after[..end];
+            let (_, value) = record.iter().find(|(k, _)| k.as_str() == key)?;
+            Some((end, value))
+        });
+
+        match field {
+            Some((end, value)) => {
+                output.push_str(&value.replace(['/', '\\'], "_"));
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+
+    // Values can't contain separators, so the components line up with the
+    // pattern's.
+    let separators = ['/', '\\'];
+    for (filled, original) in output.split(separators).zip(pattern.split(separators)) {
+        if matches!(filled, "." | "..") && filled != original {
+            bail!("a field adds a `{filled}` component to the output path {output}");
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fields: &[(&str, &str)]) -> Record {
+        fields
+            .iter()
+            .map(|&(key, value)| (key.into(), value.into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_load_records_unequal_rows() {
+        let mut file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        std::io::Write::write_all(&mut file, b"name,id\nAda,1\nBob\n").unwrap();
+        let err = load_records(file.path()).unwrap_err();
+        assert!(err.contains("line 3") || err.contains("row 3"), "{err}");
+    }
+
+    #[test]
+    fn test_fill_pattern() {
+        let record = record(&[("name", "Ada"), ("id", "7")]);
+        assert_eq!(
+            fill_pattern("out/{name}-{id}.pdf", &record).unwrap(),
+            "out/Ada-7.pdf"
+        );
+        assert_eq!(fill_pattern("{missing}/{id}", &record).unwrap(), "{missing}/7");
+        assert_eq!(fill_pattern("{name", &record).unwrap(), "{name");
+    }
+
+    #[test]
+    fn test_fill_pattern_separators() {
+        let record = record(&[("name", "a/b\\c")]);
+        assert_eq!(fill_pattern("out/{name}.pdf", &record).unwrap(), "out/a_b_c.pdf");
+    }
+
+    #[test]
+    fn test_fill_pattern_single_pass() {
+        let record = record(&[("a", "{b}"), ("b", "x")]);
+        assert_eq!(fill_pattern("{a}-{b}", &record).unwrap(), "{b}-x");
+    }
+
+    #[test]
+    fn test_fill_pattern_dot_components() {
+        let record = record(&[("up", ".."), ("dot", "."), ("name", "a")]);
+        assert!(fill_pattern("out/{up}/x.pdf", &record).is_err());
+        assert!(fill_pattern("out/{dot}/x.pdf", &record).is_err());
+        assert!(fill_pattern("out/{dot}{dot}/x.pdf", &record).is_err());
+        assert!(fill_pattern("out/{up}", &record).is_err());
+        assert_eq!(fill_pattern("../out/{name}.pdf", &record).unwrap(), "../out/a.pdf");
+        assert_eq!(fill_pattern("out/{up}.pdf", &record).unwrap(), "out/...pdf");
+    }
+}
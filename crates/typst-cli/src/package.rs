@@ -1,46 +1,610 @@
+use std::collections::HashSet;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
 
 use codespan_reporting::term::{self, termcolor};
-use ecow::eco_format;
+use ecow::{eco_format, EcoString};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use termcolor::WriteColor;
-use typst::diag::{PackageError, PackageResult};
-use typst::syntax::PackageSpec;
+use typst::diag::{bail, FileError, PackageError, PackageResult, StrResult};
+use typst::syntax::{
+    ast, PackageSpec, PackageVersion, SyntaxKind, SyntaxNode, VersionlessPackageSpec,
+};
 
+use crate::args::{
+    PackageCleanCommand, PackageCommand, PackageFetchCommand, PackageListCommand,
+    PackagePathCommand, PackageStorageArgs, PackageSubcommand,
+};
 use crate::color_stream;
-use crate::download::download_with_progress;
+use crate::download::{as_bytes_unit, download, download_with_progress};
+use crate::fmt::collect_files;
+use crate::lockfile::{Lockfile, LockfileMode, LOCKFILE_NAME};
 
-/// Make a package available in the on-disk cache.
-pub fn prepare_package(spec: &PackageSpec) -> PackageResult<PathBuf> {
-    let subdir =
-        format!("typst/packages/{}/{}/{}", spec.namespace, spec.name, spec.version);
+/// The directory within the data and cache directories in which packages are
+/// stored.
+const PACKAGES_SUBDIR: &str = "typst/packages";
 
-    if let Some(data_dir) = dirs::data_dir() {
-        let dir = data_dir.join(&subdir);
-        if dir.exists() {
-            return Ok(dir);
+/// Execute a package management command.
+pub fn package(command: &PackageCommand) -> StrResult<()> {
+    match &command.command {
+        PackageSubcommand::List(command) => list(command),
+        PackageSubcommand::Clean(command) => clean(command),
+        PackageSubcommand::Path(command) => path(command),
+        PackageSubcommand::Fetch(command) => fetch(command),
+    }
+}
+
+/// Where packages are stored on the system and whether missing ones may be
+/// downloaded.
+pub struct PackageStorage {
+    /// The directories that hold local packages, in the order in which they
+    /// are searched.
+    package_paths: Vec<PathBuf>,
+    /// The directory into which packages are downloaded.
+    package_cache_path: Option<PathBuf>,
+    /// Whether downloading packages is forbidden.
+    offline: bool,
+    /// The lockfile that resolved packages are checked against, if any.
+    lockfile: Option<Lockfile>,
+}
+
+impl PackageStorage {
+    /// Determine the package directories from the command line arguments.
+    ///
+    /// A custom package path is searched before the user's data directory and
+    /// a custom cache path replaces the user's cache directory.
+    pub fn from_args(args: &PackageStorageArgs) -> Self {
+        let package_paths = args
+            .package_path
+            .iter()
+            .cloned()
+            .chain(dirs::data_dir().map(|dir| dir.join(PACKAGES_SUBDIR)))
+            .collect();
+        let package_cache_path = args
+            .package_cache_path
+            .clone()
+            .or_else(|| dirs::cache_dir().map(|dir| dir.join(PACKAGES_SUBDIR)));
+        Self {
+            package_paths,
+            package_cache_path,
+            offline: args.offline,
+            lockfile: None,
+        }
+    }
+
+    /// Check resolved packages against the given lockfile.
+    pub fn with_lockfile(mut self, lockfile: Lockfile) -> Self {
+        self.lockfile = Some(lockfile);
+        self
+    }
+
+    /// Access the lockfile, if any.
+    pub fn lockfile_mut(&mut self) -> Option<&mut Lockfile> {
+        self.lockfile.as_mut()
+    }
+
+    /// The directories that hold local packages, which `clean` leaves alone.
+    pub fn package_paths(&self) -> &[PathBuf] {
+        &self.package_paths
+    }
+
+    /// The directory that holds downloaded packages.
+    pub fn package_cache_path(&self) -> Option<&Path> {
+        self.package_cache_path.as_deref()
+    }
+
+    /// All package directories, local ones first.
+    fn all_paths(&self) -> impl Iterator<Item = &Path> {
+        self.package_paths
+            .iter()
+            .map(PathBuf::as_path)
+            .chain(self.package_cache_path())
+    }
+
+    /// Make a package available in the on-disk cache.
+    pub fn prepare_package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
+        for package_path in &self.package_paths {
+            let dir = package_dir(package_path, spec);
+            if dir.exists() {
+                self.verify_cached(spec, &dir)?;
+                return Ok(dir);
+            }
+        }
+
+        if let Some(cache_path) = &self.package_cache_path {
+            let dir = package_dir(cache_path, spec);
+            if dir.exists() && self.is_resolved(spec) {
+                return Ok(dir);
+            }
+
+            // Download from network if it doesn't exist yet or if its files
+            // cannot be verified although the lockfile pins a checksum or they
+            // changed since they were downloaded. The archive is checked
+            // against the lockfile before anything is unpacked.
+            if spec.namespace == "preview" && self.needs_download(spec, &dir) {
+                if self.offline && !dir.exists() {
+                    return Err(PackageError::NetworkFailed(Some(eco_format!(
+                        "{spec} is not available locally and downloads are \
+                         disabled in offline mode"
+                    ))));
+                }
+
+                // Parallel compilations in a batch download one package at a
+                // time, so that they don't unpack the same package twice.
+                static DOWNLOADING: Mutex<()> = Mutex::new(());
+                let _guard = DOWNLOADING.lock().unwrap_or_else(PoisonError::into_inner);
+
+                if !self.offline && self.needs_download(spec, &dir) {
+                    let data = download_package(spec)?;
+                    let digest = eco_format!("{:x}", Sha256::digest(&data));
+                    self.verify(spec, Some(&digest))?;
+                    if dir.exists() {
+                        fs::remove_dir_all(&dir).map_err(|err| {
+                            PackageError::Other(Some(eco_format!(
+                                "failed to replace {spec} ({err})"
+                            )))
+                        })?;
+                    }
+                    unpack_package(&data, &dir)?;
+                    write_digest(&dir, &digest).map_err(|err| {
+                        PackageError::Other(Some(eco_format!(
+                            "failed to store checksum of {spec} ({err})"
+                        )))
+                    })?;
+                    return Ok(dir);
+                }
+            }
+
+            if dir.exists() {
+                self.verify_cached(spec, &dir)?;
+                return Ok(dir);
+            }
+        }
+
+        Err(PackageError::NotFound(spec.clone()))
+    }
+
+    /// Try to determine the latest version of a package.
+    pub fn determine_latest_version(
+        &self,
+        spec: &VersionlessPackageSpec,
+    ) -> StrResult<PackageVersion> {
+        if spec.namespace == "preview" && !self.offline {
+            // For `@preview`, download the package index and find the latest
+            // version.
+            download_index()?
+                .iter()
+                .filter(|package| package.name == spec.name)
+                .map(|package| package.version)
+                .max()
+                .ok_or_else(|| eco_format!("failed to find package {spec}"))
+        } else {
+            // For other namespaces, search locally. We only search in the
+            // local package directories and not the cache directory, because
+            // the latter is not intended for storage of local packages. In
+            // offline mode, the cache is the only place to find `@preview`
+            // packages, though.
+            let dirs: Vec<&Path> = if spec.namespace == "preview" {
+                self.all_paths().collect()
+            } else {
+                self.package_paths.iter().map(PathBuf::as_path).collect()
+            };
+
+            dirs.into_iter()
+                .flat_map(|dir| {
+                    fs::read_dir(
+                        dir.join(spec.namespace.as_str()).join(spec.name.as_str()),
+                    )
+                    .ok()
+                })
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter_map(|path| path.file_name()?.to_string_lossy().parse().ok())
+                .max()
+                .ok_or_else(|| eco_format!("please specify the desired version"))
+        }
+    }
+
+    /// Whether a cached package must be downloaded, either because it is
+    /// missing or because its files cannot be verified although the lockfile
+    /// pins a checksum or they changed since they were downloaded.
+    fn needs_download(&self, spec: &PackageSpec, dir: &Path) -> bool {
+        let Some(lockfile) = &self.lockfile else { return !dir.exists() };
+        !dir.exists()
+            || (read_digest(dir).is_none()
+                && (lockfile.pinned(spec).is_some() || digest_path(dir).exists()))
+    }
+
+    /// Whether a package was already checked against the lockfile in the
+    /// ongoing compilation.
+    fn is_resolved(&self, spec: &PackageSpec) -> bool {
+        self.lockfile
+            .as_ref()
+            .is_some_and(|lockfile| lockfile.is_resolved(spec))
+    }
+
+    /// Check a package in a package directory against the lockfile, if there
+    /// is one.
+    ///
+    /// Hashing a package's files takes a while, so this is only done the
+    /// first time the package is used in a compilation.
+    fn verify_cached(&self, spec: &PackageSpec, dir: &Path) -> PackageResult<()> {
+        if self.lockfile.is_none() || self.is_resolved(spec) {
+            return Ok(());
+        }
+        self.verify(spec, read_digest(dir).as_deref())
+    }
+
+    /// Check a resolved package against the lockfile, if there is one.
+    fn verify(&self, spec: &PackageSpec, sha256: Option<&str>) -> PackageResult<()> {
+        match &self.lockfile {
+            Some(lockfile) => lockfile.verify(spec, sha256),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The file next to a downloaded package's directory that holds the SHA-256
+/// checksums of the package's archive and of its unpacked files.
+fn digest_path(dir: &Path) -> PathBuf {
+    let mut path = dir.as_os_str().to_owned();
+    path.push(".sha256");
+    path.into()
+}
+
+/// Store the archive checksum of a freshly unpacked package along with the
+/// checksum of its files.
+fn write_digest(dir: &Path, digest: &str) -> io::Result<()> {
+    let contents = hash_contents(dir)?;
+    fs::write(digest_path(dir), format!("{digest}\n{contents}\n"))
+}
+
+/// Read the archive checksum of a package, if it was downloaded and its files
+/// did not change since.
+fn read_digest(dir: &Path) -> Option<String> {
+    let text = fs::read_to_string(digest_path(dir)).ok()?;
+    let mut lines = text.lines().map(str::trim);
+    let digest = lines.next()?;
+    let contents = lines.next()?;
+    (hash_contents(dir).ok()? == contents).then(|| digest.into())
+}
+
+/// Hash the files of an unpacked package along with their paths.
+fn hash_contents(dir: &Path) -> io::Result<String> {
+    fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                collect(&path, files)?;
+            } else {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    collect(dir, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for path in files {
+        let data = fs::read(&path)?;
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        for component in relative.components() {
+            hasher.update(component.as_os_str().to_string_lossy().as_bytes());
+            hasher.update(b"/");
+        }
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(&data);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The directory of a package within a package directory.
+fn package_dir(dir: &Path, spec: &PackageSpec) -> PathBuf {
+    dir.join(spec.namespace.as_str())
+        .join(spec.name.as_str())
+        .join(spec.version.to_string())
+}
+
+/// List the packages in the local and cache directories.
+fn list(command: &PackageListCommand) -> StrResult<()> {
+    let storage = PackageStorage::from_args(&command.package);
+    let local = storage.package_paths().iter().map(|dir| ("local", dir.as_path()));
+    let cached = storage.package_cache_path().map(|dir| ("cached", dir));
+    for (kind, dir) in local.chain(cached) {
+        let packages = stored_packages(dir)?;
+        if packages.is_empty() {
+            continue;
+        }
+
+        println!("{kind} packages in {}", dir.display());
+        for (spec, path) in packages {
+            println!("{}  {spec}", as_bytes_unit(dir_size(&path) as usize, false));
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove cached packages, keeping the latest version of each package unless
+/// all of them should be removed.
+///
+/// Packages that the lockfile pins are always kept.
+fn clean(command: &PackageCleanCommand) -> StrResult<()> {
+    let storage = PackageStorage::from_args(&command.package);
+    let Some(dir) = storage.package_cache_path() else { return Ok(()) };
+    let lockfile = Lockfile::load(command.lockfile.clone(), LockfileMode::Record)?;
+
+    // Packages are sorted by version, so the last one of a package is its
+    // latest version.
+    let packages = stored_packages(dir)?;
+    let mut freed = 0;
+    for (i, (spec, path)) in packages.iter().enumerate() {
+        let latest = packages.get(i + 1).map_or(true, |(next, _)| {
+            next.namespace != spec.namespace || next.name != spec.name
+        });
+        if latest && !command.all {
+            continue;
+        }
+
+        if lockfile.contains(spec) {
+            println!("kept {spec}, which {} pins", command.lockfile.display());
+            continue;
+        }
+
+        let size = dir_size(path);
+        fs::remove_dir_all(path)
+            .map_err(|err| eco_format!("failed to remove {spec} ({err})"))?;
+        fs::remove_file(digest_path(path)).ok();
+        println!("removed {spec} ({})", as_bytes_unit(size as usize, false).trim());
+        freed += size;
+    }
+
+    println!("freed {}", as_bytes_unit(freed as usize, false).trim());
+    Ok(())
+}
+
+/// Print the package directories, or the directory in which a package is
+/// stored.
+fn path(command: &PackagePathCommand) -> StrResult<()> {
+    let storage = PackageStorage::from_args(&command.package);
+    let Some(spec) = &command.spec else {
+        for dir in storage.all_paths() {
+            println!("{}", dir.display());
+        }
+        return Ok(());
+    };
+
+    let dir = storage
+        .all_paths()
+        .map(|dir| package_dir(dir, spec))
+        .find(|dir| dir.exists())
+        .ok_or_else(|| eco_format!("package {spec} is not stored locally"))?;
+
+    println!("{}", dir.display());
+    Ok(())
+}
+
+/// Download the packages imported by the given files and, transitively, by
+/// those packages.
+///
+/// The packages are checked against and recorded in the lockfile next to each
+/// input, like in a compilation of that input.
+fn fetch(command: &PackageFetchCommand) -> StrResult<()> {
+    for input in &command.input {
+        let dir = if input.is_dir() {
+            input.as_path()
+        } else {
+            input.parent().unwrap_or(Path::new("."))
+        };
+        let root = command.root.clone().unwrap_or_else(|| dir.to_path_buf());
+
+        let mode = if command.locked {
+            LockfileMode::Locked
+        } else if command.update {
+            LockfileMode::Update
+        } else {
+            LockfileMode::Record
+        };
+        let lockfile = Lockfile::load(dir.join(LOCKFILE_NAME), mode)?;
+        let mut storage =
+            PackageStorage::from_args(&command.package).with_lockfile(lockfile);
+
+        let mut files = vec![];
+        collect_files(input, &mut files).map_err(|err| FileError::from_io(err, input))?;
+
+        // Local files may import other local files which in turn import
+        // packages, so we follow those imports, too.
+        let mut seen = HashSet::new();
+        let mut fetched = HashSet::new();
+        while let Some(file) = files.pop() {
+            if !seen.insert(file.clone()) {
+                continue;
+            }
+
+            let text = fs::read_to_string(&file)
+                .map_err(|err| FileError::from_io(err, &file))?;
+            for import in imports(&text) {
+                if import.starts_with('@') {
+                    let spec: PackageSpec = import.parse()?;
+                    if fetched.insert(spec.clone()) {
+                        fetch_package(&storage, &spec, &mut fetched)?;
+                    }
+                } else if let Some(path) = import.strip_prefix('/') {
+                    files.push(root.join(path));
+                } else if let Some(dir) = file.parent() {
+                    files.push(dir.join(import.as_str()));
+                }
+            }
+        }
+
+        if let Some(lockfile) = storage.lockfile_mut() {
+            lockfile.write()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Make a package and all packages imported by its files available.
+fn fetch_package(
+    storage: &PackageStorage,
+    spec: &PackageSpec,
+    fetched: &mut HashSet<PackageSpec>,
+) -> StrResult<()> {
+    let dir = storage.prepare_package(spec)?;
+
+    let mut files = vec![];
+    collect_files(&dir, &mut files).map_err(|err| FileError::from_io(err, &dir))?;
+
+    for file in files {
+        let text =
+            fs::read_to_string(&file).map_err(|err| FileError::from_io(err, &file))?;
+        for import in imports(&text) {
+            if !import.starts_with('@') {
+                continue;
+            }
+
+            let spec: PackageSpec = import.parse()?;
+            if fetched.insert(spec.clone()) {
+                fetch_package(storage, &spec, fetched)?;
+            }
         }
     }
 
-    if let Some(cache_dir) = dirs::cache_dir() {
-        let dir = cache_dir.join(&subdir);
+    Ok(())
+}
+
+/// Find the string paths of all imports and includes in a Typst file.
+///
+/// Imports from computed paths can't be found without evaluating the file,
+/// so those are skipped.
+fn imports(text: &str) -> Vec<EcoString> {
+    fn visit(node: &SyntaxNode, imports: &mut Vec<EcoString>) {
+        let source = match node.kind() {
+            SyntaxKind::ModuleImport => {
+                node.cast::<ast::ModuleImport>().map(|import| import.source())
+            }
+            SyntaxKind::ModuleInclude => {
+                node.cast::<ast::ModuleInclude>().map(|include| include.source())
+            }
+            _ => None,
+        };
+
+        if let Some(ast::Expr::Str(path)) = source {
+            imports.push(path.get());
+        }
+
+        for child in node.children() {
+            visit(child, imports);
+        }
+    }
+
+    let mut imports = vec![];
+    visit(&typst::syntax::parse(text), &mut imports);
+    imports
+}
 
-        // Download from network if it doesn't exist yet.
-        if spec.namespace == "preview" && !dir.exists() {
-            download_package(spec, &dir)?;
+/// Find all packages in a package directory, sorted by namespace, name, and
+/// version.
+fn stored_packages(dir: &Path) -> StrResult<Vec<(PackageSpec, PathBuf)>> {
+    let mut packages = vec![];
+    for namespace_dir in sub_dirs(dir)? {
+        for name_dir in sub_dirs(&namespace_dir)? {
+            for version_dir in sub_dirs(&name_dir)? {
+                let (Some(namespace), Some(name), Some(version)) = (
+                    dir_name(&namespace_dir),
+                    dir_name(&name_dir),
+                    dir_name(&version_dir).and_then(|version| version.parse().ok()),
+                ) else {
+                    continue;
+                };
+                packages.push((PackageSpec { namespace, name, version }, version_dir));
+            }
         }
+    }
+
+    packages.sort_by(|(a, _), (b, _)| {
+        (&a.namespace, &a.name, a.version).cmp(&(&b.namespace, &b.name, b.version))
+    });
+
+    Ok(packages)
+}
+
+/// The last component of a directory's path.
+fn dir_name(path: &Path) -> Option<EcoString> {
+    Some(path.file_name()?.to_string_lossy().into())
+}
+
+/// The sub-directories of a directory, or none if it doesn't exist.
+fn sub_dirs(dir: &Path) -> StrResult<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(FileError::from_io(err, dir).into()),
+    };
 
-        if dir.exists() {
-            return Ok(dir);
+    let mut dirs = vec![];
+    for entry in entries {
+        let path = entry.map_err(|err| FileError::from_io(err, dir))?.path();
+        if path.is_dir() {
+            dirs.push(path);
         }
     }
 
-    Err(PackageError::NotFound(spec.clone()))
+    Ok(dirs)
+}
+
+/// The total size of the files in a directory.
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// An entry in the package index.
+#[derive(Deserialize)]
+struct IndexEntry {
+    /// The name of the package.
+    name: EcoString,
+    /// The package's version.
+    version: PackageVersion,
+}
+
+/// Download the `@preview` package index.
+fn download_index() -> StrResult<Vec<IndexEntry>> {
+    let url = "https://packages.typst.org/preview/index.json";
+    match download(url) {
+        Ok(response) => {
+            let body = response
+                .into_string()
+                .map_err(|err| eco_format!("failed to read package index ({err})"))?;
+            serde_json::from_str(&body)
+                .map_err(|err| eco_format!("failed to parse package index ({err})"))
+        }
+        Err(ureq::Error::Status(404, _)) => {
+            bail!("failed to fetch package index (not found)")
+        }
+        Err(err) => bail!("failed to fetch package index ({err})"),
+    }
 }
 
-/// Download a package over the network.
-fn download_package(spec: &PackageSpec, package_dir: &Path) -> PackageResult<()> {
+/// Download a package's archive over the network.
+fn download_package(spec: &PackageSpec) -> PackageResult<Vec<u8>> {
     // The `@preview` namespace is the only namespace that supports on-demand
     // fetching.
     assert_eq!(spec.namespace, "preview");
@@ -52,15 +616,16 @@ fn download_package(spec: &PackageSpec, package_dir: &Path) -> PackageResult<()>
 
     print_downloading(spec).unwrap();
 
-    let data = match download_with_progress(&url) {
-        Ok(data) => data,
-        Err(ureq::Error::Status(404, _)) => {
-            return Err(PackageError::NotFound(spec.clone()))
-        }
-        Err(err) => return Err(PackageError::NetworkFailed(Some(eco_format!("{err}")))),
-    };
+    match download_with_progress(&url) {
+        Ok(data) => Ok(data),
+        Err(ureq::Error::Status(404, _)) => Err(PackageError::NotFound(spec.clone())),
+        Err(err) => Err(PackageError::NetworkFailed(Some(eco_format!("{err}")))),
+    }
+}
 
-    let decompressed = flate2::read::GzDecoder::new(data.as_slice());
+/// Unpack a package's archive into its directory.
+fn unpack_package(data: &[u8], package_dir: &Path) -> PackageResult<()> {
+    let decompressed = flate2::read::GzDecoder::new(data);
     tar::Archive::new(decompressed).unpack(package_dir).map_err(|err| {
         fs::remove_dir_all(package_dir).ok();
         PackageError::MalformedArchive(Some(eco_format!("{err}")))
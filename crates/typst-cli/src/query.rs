@@ -15,7 +15,7 @@ use crate::world::SystemWorld;
 
 /// Execute a query command.
 pub fn query(command: &QueryCommand) -> StrResult<()> {
-    let mut world = SystemWorld::new(&command.common)?;
+    let mut world = SystemWorld::new(&command.input, &command.common)?;
     tracing::info!("Starting querying");
 
     // Reset everything and ensure that the main file is present.
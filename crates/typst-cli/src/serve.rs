@@ -0,0 +1,398 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ecow::eco_format;
+use serde_json::json;
+use typst::diag::StrResult;
+use typst::layout::{Abs, Frame, Point, Position};
+use typst::model::Document;
+use typst::syntax::{FileId, VirtualPath};
+use typst::util::hash128;
+use typst::World;
+use typst_ide::Jump;
+
+use crate::world::SystemWorld;
+
+/// The page that displays the preview in the browser.
+const PREVIEW_HTML: &str = include_str!("preview.html");
+
+/// How long reading a request or writing to a connection may take before the
+/// connection is given up on.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves a live preview of the document over HTTP.
+///
+/// The preview page is notified about new compilations through server-sent
+/// events. Clicks in the preview and cursor positions reported by editors need
+/// the world to be resolved, so they are handed back to the watch loop as
+/// [`PreviewRequest`]s.
+pub struct PreviewServer {
+    /// The address the server listens on.
+    addr: SocketAddr,
+    /// State shared with the server thread.
+    shared: Arc<Mutex<Shared>>,
+}
+
+/// A request from the preview or an editor that must be resolved against the
+/// current document.
+pub enum PreviewRequest {
+    /// A click on a page (zero-based) at a point in page coordinates.
+    Click { page: usize, point: Point },
+    /// A cursor position in a source file (one-based line and column).
+    Cursor { path: PathBuf, line: usize, column: usize },
+}
+
+impl PreviewServer {
+    /// Start serving on the given port of localhost.
+    ///
+    /// Requests that need the document are passed to `on_request`.
+    pub fn new(
+        port: u16,
+        mut on_request: impl FnMut(PreviewRequest) + Send + 'static,
+    ) -> StrResult<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|err| eco_format!("failed to start preview server ({err})"))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|err| eco_format!("failed to start preview server ({err})"))?;
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let state = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                // A stalled connection must not block all others.
+                if stream.set_read_timeout(Some(TIMEOUT)).is_err()
+                    || stream.set_write_timeout(Some(TIMEOUT)).is_err()
+                {
+                    continue;
+                }
+                if let Err(err) = handle_connection(stream, &state, &mut on_request) {
+                    tracing::warn!("Failed to handle preview request ({err})");
+                }
+            }
+        });
+
+        Ok(Self { addr, shared })
+    }
+
+    /// The URL at which the preview can be opened.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Display a newly compiled document.
+    ///
+    /// Only pages whose frames changed since the last update are rendered
+    /// again.
+    pub fn update(&self, document: &Document) {
+        let mut shared = self.shared.lock().unwrap();
+        let mut previous = std::mem::take(&mut shared.pages).into_iter();
+        shared.pages = document
+            .pages
+            .iter()
+            .map(|frame| {
+                let hash = hash128(frame);
+                match previous.next() {
+                    Some(page) if page.hash == hash => page,
+                    _ => PreviewPage::new(frame, hash),
+                }
+            })
+            .collect();
+
+        let manifest = shared.manifest();
+        shared.broadcast("update", &manifest);
+    }
+
+    /// Resolve a request against the document and notify the preview about
+    /// the result.
+    pub fn handle(
+        &self,
+        world: &SystemWorld,
+        document: &Document,
+        request: PreviewRequest,
+    ) {
+        match request {
+            PreviewRequest::Click { page, point } => {
+                let Some(frame) = document.pages.get(page) else { return };
+                match typst_ide::jump_from_click(world, document, frame, point) {
+                    Some(Jump::Source(id, offset)) => {
+                        self.jump_to_source(world, id, offset)
+                    }
+                    Some(Jump::Url(url)) => self.broadcast("url", json!({ "url": url })),
+                    Some(Jump::Position(position)) => self.scroll_to(position),
+                    None => {}
+                }
+            }
+            PreviewRequest::Cursor { path, line, column } => {
+                let path = world.workdir().join(path);
+                let path = path.canonicalize().unwrap_or(path);
+                let Some(vpath) = VirtualPath::within_root(&path, world.root()) else {
+                    return;
+                };
+                let Ok(source) = world.source(FileId::new(None, vpath)) else { return };
+                let Some(cursor) = source.line_column_to_byte(
+                    line.saturating_sub(1),
+                    column.saturating_sub(1),
+                ) else {
+                    return;
+                };
+                if let Some(position) =
+                    typst_ide::jump_from_cursor(document, &source, cursor)
+                {
+                    self.scroll_to(position);
+                }
+            }
+        }
+    }
+
+    /// Tell the preview and subscribed editors about a clicked source
+    /// location.
+    fn jump_to_source(&self, world: &SystemWorld, id: FileId, offset: usize) {
+        let Ok(source) = world.source(id) else { return };
+        let (Some(line), Some(column)) =
+            (source.byte_to_line(offset), source.byte_to_column(offset))
+        else {
+            return;
+        };
+
+        let vpath = id.vpath();
+        let file = match id.package() {
+            Some(package) => format!("{package}{}", vpath.as_rooted_path().display()),
+            None => vpath
+                .resolve(world.root())
+                .unwrap_or_else(|| vpath.as_rootless_path().into())
+                .display()
+                .to_string(),
+        };
+
+        self.broadcast(
+            "source",
+            json!({ "file": file, "line": line + 1, "column": column + 1 }),
+        );
+    }
+
+    /// Scroll the preview to a position in the document.
+    fn scroll_to(&self, position: Position) {
+        self.broadcast(
+            "scroll",
+            json!({
+                "page": position.page.get(),
+                "x": position.point.x.to_pt(),
+                "y": position.point.y.to_pt(),
+            }),
+        );
+    }
+
+    /// Send an event to all connected clients.
+    fn broadcast(&self, event: &str, data: serde_json::Value) {
+        self.shared.lock().unwrap().broadcast(event, &data);
+    }
+}
+
+/// State shared between the watch loop and the server thread.
+#[derive(Default)]
+struct Shared {
+    /// The rendered pages of the last successful compilation.
+    pages: Vec<PreviewPage>,
+    /// The event queues of connections subscribed to server-sent events.
+    clients: Vec<Sender<String>>,
+}
+
+impl Shared {
+    /// Describes the current pages for the preview.
+    fn manifest(&self) -> serde_json::Value {
+        let pages: Vec<_> = self
+            .pages
+            .iter()
+            .map(|page| {
+                json!({
+                    "width": page.size.0.to_pt(),
+                    "height": page.size.1.to_pt(),
+                    "hash": format!("{:x}", page.hash),
+                })
+            })
+            .collect();
+        json!({ "pages": pages })
+    }
+
+    /// Queue an event for all subscribed clients, dropping those whose
+    /// connection failed.
+    fn broadcast(&mut self, event: &str, data: &serde_json::Value) {
+        let message = format_event(event, data);
+        self.clients.retain(|client| client.send(message.clone()).is_ok());
+    }
+
+    /// Subscribe a connection to server-sent events, starting with the
+    /// current pages.
+    ///
+    /// Events are written on a thread of the connection's own, so that a slow
+    /// client doesn't hold up the others or the watch loop. Once a write fails
+    /// or times out, the thread ends and the client is dropped on the next
+    /// broadcast.
+    fn subscribe(&mut self, mut stream: TcpStream) {
+        let (tx, rx) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for message in rx {
+                if stream
+                    .write_all(message.as_bytes())
+                    .and_then(|_| stream.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        if tx.send(format_event("update", &self.manifest())).is_ok() {
+            self.clients.push(tx);
+        }
+    }
+}
+
+/// A page rendered for the preview.
+struct PreviewPage {
+    /// The hash of the page's frame.
+    hash: u128,
+    /// The width and height of the page.
+    size: (Abs, Abs),
+    /// The page rendered as SVG.
+    svg: String,
+}
+
+impl PreviewPage {
+    /// Render a page.
+    fn new(frame: &Frame, hash: u128) -> Self {
+        let size = frame.size();
+        Self {
+            hash,
+            size: (size.x, size.y),
+            svg: typst_svg::svg(frame),
+        }
+    }
+}
+
+/// Serve a single HTTP request.
+fn handle_connection(
+    mut stream: TcpStream,
+    shared: &Mutex<Shared>,
+    on_request: &mut dyn FnMut(PreviewRequest),
+) -> io::Result<()> {
+    // We only care about the request target and skip all headers.
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let param = |key: &str| query_param(query, key);
+
+    match path {
+        "/" => respond(&mut stream, "200 OK", "text/html", PREVIEW_HTML.as_bytes()),
+        "/events" => {
+            stream.write_all(
+                b"HTTP/1.1 200 OK\r\n\
+                  Content-Type: text/event-stream\r\n\
+                  Cache-Control: no-cache\r\n\r\n",
+            )?;
+            shared.lock().unwrap().subscribe(stream);
+            Ok(())
+        }
+        "/click" => {
+            let (Some(page), Some(x), Some(y)) = (
+                param("page").and_then(|v| v.parse().ok()),
+                param("x").and_then(|v| v.parse().ok()),
+                param("y").and_then(|v| v.parse().ok()),
+            ) else {
+                return respond(&mut stream, "400 Bad Request", "text/plain", b"");
+            };
+            on_request(PreviewRequest::Click {
+                page,
+                point: Point::new(Abs::pt(x), Abs::pt(y)),
+            });
+            respond(&mut stream, "204 No Content", "text/plain", b"")
+        }
+        "/cursor" => {
+            let (Some(path), Some(line), Some(column)) = (
+                param("path"),
+                param("line").and_then(|v| v.parse().ok()),
+                param("column").and_then(|v| v.parse().ok()),
+            ) else {
+                return respond(&mut stream, "400 Bad Request", "text/plain", b"");
+            };
+            on_request(PreviewRequest::Cursor { path: path.into(), line, column });
+            respond(&mut stream, "204 No Content", "text/plain", b"")
+        }
+        _ => {
+            let svg = path
+                .strip_prefix("/page/")
+                .and_then(|rest| rest.strip_suffix(".svg"))
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|i| Some(shared.lock().unwrap().pages.get(i)?.svg.clone()));
+            match svg {
+                Some(svg) => {
+                    respond(&mut stream, "200 OK", "image/svg+xml", svg.as_bytes())
+                }
+                None => respond(&mut stream, "404 Not Found", "text/plain", b""),
+            }
+        }
+    }
+}
+
+/// Write a complete HTTP response.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        body.len(),
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Format a server-sent event.
+fn format_event(event: &str, data: &serde_json::Value) -> String {
+    format!("event: {event}\ndata: {data}\n\n")
+}
+
+/// Extract and percent-decode a parameter from a query string.
+fn query_param(query: &str, key: &str) -> Option<String> {
+    let (_, value) = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|&(k, _)| k == key)?;
+
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
@@ -17,7 +17,7 @@ use crate::args::{CliArguments, Command};
 pub fn setup_tracing(args: &CliArguments) -> io::Result<Option<impl Drop>> {
     let flamegraph = match &args.command {
         Command::Compile(command) => command.flamegraph.as_ref(),
-        Command::Watch(command) if command.flamegraph.is_some() => {
+        Command::Watch(command) if command.args.flamegraph.is_some() => {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "cannot use --flamegraph with watch command",
@@ -26,9 +26,13 @@ pub fn setup_tracing(args: &CliArguments) -> io::Result<Option<impl Drop>> {
         _ => None,
     };
 
+    // Logs are written to stderr so that they don't interfere with output on
+    // stdout, such as the messages of the language server.
+
     // Short circuit if we don't need to initialize flamegraph or debugging.
     if flamegraph.is_none() && args.verbosity == 0 {
         tracing_subscriber::fmt()
+            .with_writer(io::stderr)
             .without_time()
             .with_max_level(level_filter(args))
             .init();
@@ -37,7 +41,10 @@ pub fn setup_tracing(args: &CliArguments) -> io::Result<Option<impl Drop>> {
     }
 
     // Build the FMT layer printing to the console.
-    let fmt_layer = fmt::Layer::default().without_time().with_filter(level_filter(args));
+    let fmt_layer = fmt::Layer::default()
+        .with_writer(io::stderr)
+        .without_time()
+        .with_filter(level_filter(args));
 
     // Error layer for building backtraces
     let error_layer = ErrorLayer::default();
@@ -7,25 +7,72 @@ use ecow::eco_format;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use same_file::is_same_file;
 use termcolor::WriteColor;
-use typst::diag::StrResult;
+use typst::diag::{bail, StrResult};
+use typst::model::Document;
 
-use crate::args::CompileCommand;
+use crate::args::{CompileCommand, Input, Output, WatchCommand};
 use crate::color_stream;
 use crate::compile::compile_once;
+use crate::serve::{PreviewRequest, PreviewServer};
 use crate::world::SystemWorld;
 
+/// A message received by the watch loop.
+enum Message {
+    /// A file system event.
+    Fs(notify::Result<notify::Event>),
+    /// A request from the preview server.
+    Preview(PreviewRequest),
+}
+
 /// Execute a watching compilation command.
-pub fn watch(mut command: CompileCommand) -> StrResult<()> {
+pub fn watch(mut command: WatchCommand) -> StrResult<()> {
+    // Watching only makes sense with files on both ends.
+    if !command.args.batch.is_empty() || command.args.manifest.is_some() {
+        bail!("cannot watch a batch of documents");
+    }
+    if command.args.data.is_some() {
+        bail!("cannot watch a mail merge");
+    }
+    if let Input::Stdin = command.args.input() {
+        bail!("cannot watch input from stdin");
+    }
+    let Output::Path(output) = command.args.output() else {
+        bail!("cannot write output to stdout in watch mode");
+    };
+
     // Create the world that serves sources, files, and fonts.
-    let mut world = SystemWorld::new(&command.common)?;
+    let mut world = SystemWorld::new(command.args.input(), &command.args.common)?;
+
+    // File system events and preview requests are funneled into one channel.
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    // Start the preview server if requested.
+    let server = match command.serve {
+        Some(port) => {
+            let tx = tx.clone();
+            Some(PreviewServer::new(port, move |request| {
+                tx.send(Message::Preview(request)).ok();
+            })?)
+        }
+        None => None,
+    };
 
     // Perform initial compilation.
-    compile_once(&mut world, &mut command, true)?;
+    let mut document = compile_once(&mut world, &mut command.args, true)?;
+    update_preview(server.as_ref(), document.as_ref());
+    if let Some(server) = &server {
+        print_serving(server)
+            .map_err(|err| eco_format!("failed to print preview address ({err})"))?;
+    }
 
     // Setup file watching.
-    let (tx, rx) = std::sync::mpsc::channel();
-    let mut watcher = RecommendedWatcher::new(tx, notify::Config::default())
-        .map_err(|err| eco_format!("failed to setup file watching ({err})"))?;
+    let mut watcher = RecommendedWatcher::new(
+        move |event: notify::Result<notify::Event>| {
+            tx.send(Message::Fs(event)).ok();
+        },
+        notify::Config::default(),
+    )
+    .map_err(|err| eco_format!("failed to setup file watching ({err})"))?;
 
     // Watch all the files that are used by the input file and its dependencies.
     let mut watched = HashMap::new();
@@ -33,16 +80,23 @@ pub fn watch(mut command: CompileCommand) -> StrResult<()> {
 
     // Handle events.
     let timeout = std::time::Duration::from_millis(100);
-    let output = command.output();
     loop {
         let mut recompile = false;
-        for event in rx
+        for message in rx
             .recv()
             .into_iter()
             .chain(std::iter::from_fn(|| rx.recv_timeout(timeout).ok()))
         {
-            let event =
-                event.map_err(|err| eco_format!("failed to watch directory ({err})"))?;
+            let event = match message {
+                Message::Fs(event) => event
+                    .map_err(|err| eco_format!("failed to watch directory ({err})"))?,
+                Message::Preview(request) => {
+                    if let (Some(server), Some(document)) = (&server, &document) {
+                        server.handle(&world, document, request);
+                    }
+                    continue;
+                }
+            };
 
             // Workaround for notify-rs' implicit unwatch on remove/rename
             // (triggered by some editors when saving files) with the inotify
@@ -66,8 +120,12 @@ pub fn watch(mut command: CompileCommand) -> StrResult<()> {
             // Reset all dependencies.
             world.reset();
 
-            // Recompile.
-            compile_once(&mut world, &mut command, true)?;
+            // Recompile. The preview keeps showing the last successfully
+            // compiled document if there were errors.
+            if let Some(compiled) = compile_once(&mut world, &mut command.args, true)? {
+                document = Some(compiled);
+            }
+            update_preview(server.as_ref(), document.as_ref());
             comemo::evict(10);
 
             // Adjust the file watching.
@@ -76,6 +134,25 @@ pub fn watch(mut command: CompileCommand) -> StrResult<()> {
     }
 }
 
+/// Push the latest document to the preview.
+fn update_preview(server: Option<&PreviewServer>, document: Option<&Document>) {
+    if let (Some(server), Some(document)) = (server, document) {
+        server.update(document);
+    }
+}
+
+/// Print the address of the preview server.
+fn print_serving(server: &PreviewServer) -> io::Result<()> {
+    let mut w = color_stream();
+    let styles = term::Styles::default();
+
+    w.set_color(&styles.header_help)?;
+    write!(w, "serving preview at")?;
+
+    w.reset()?;
+    writeln!(w, " {}", server.url())
+}
+
 /// Adjust the file watching. Watches all new dependencies and unwatches
 /// all previously `watched` files that are no relevant anymore.
 #[tracing::instrument(skip_all)]
@@ -169,12 +246,12 @@ impl Status {
         w.set_color(&color)?;
         write!(w, "watching")?;
         w.reset()?;
-        writeln!(w, " {}", command.common.input.display())?;
+        writeln!(w, " {}", command.input())?;
 
         w.set_color(&color)?;
         write!(w, "writing to")?;
         w.reset()?;
-        writeln!(w, " {}", output.display())?;
+        writeln!(w, " {output}")?;
 
         writeln!(w)?;
         writeln!(w, "[{timestamp}] {}", self.message())?;
@@ -1,44 +1,60 @@
 use std::cell::{Cell, OnceCell, RefCell, RefMut};
 use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use chrono::{DateTime, Datelike, Local};
+use chrono::{DateTime, Datelike, Local, Utc};
 use comemo::Prehashed;
 use ecow::eco_format;
 use typst::diag::{FileError, FileResult, StrResult};
-use typst::foundations::{Bytes, Datetime};
+use typst::foundations::{Bytes, Datetime, Dict, IntoValue};
 use typst::layout::Frame;
 use typst::syntax::{FileId, Source, VirtualPath};
 use typst::text::{Font, FontBook};
 use typst::util::hash128;
 use typst::{Library, World};
 
-use crate::args::SharedArgs;
+use crate::args::{Input, SharedArgs};
 use crate::fonts::{FontSearcher, FontSlot};
-use crate::package::prepare_package;
+use crate::lockfile::{Lockfile, LockfileMode, LOCKFILE_NAME};
+use crate::package::PackageStorage;
+
+/// The virtual path under which input from stdin is made available.
+const STDIN_PATH: &str = "<stdin>";
 
 /// A world that provides access to the operating system.
 pub struct SystemWorld {
     /// The working directory.
     workdir: Option<PathBuf>,
-    /// The canonical path to the input file.
-    input: PathBuf,
+    /// The canonical path to the input file, if it wasn't read from stdin.
+    input: Option<PathBuf>,
     /// The root relative to which absolute paths are resolved.
     root: PathBuf,
     /// The input path.
     main: FileId,
     /// Typst's standard library.
     library: Prehashed<Library>,
+    /// Values that replace the library's `sys.inputs`, if any.
+    inputs: Option<Dict>,
     /// Metadata about discovered fonts.
-    book: Prehashed<FontBook>,
+    book: Arc<Prehashed<FontBook>>,
     /// Locations of and storage for lazily loaded fonts.
-    fonts: Vec<FontSlot>,
+    fonts: Arc<Vec<FontSlot>>,
+    /// Where packages are stored and how missing ones are obtained.
+    package_storage: PackageStorage,
     /// Maps file ids to source files and buffers.
     slots: RefCell<HashMap<FileId, FileSlot>>,
+    /// In-memory contents that replace the files at the given paths, for
+    /// example unsaved buffers of an editor.
+    overlays: HashMap<PathBuf, String>,
     /// The current datetime if requested. This is stored here to ensure it is
     /// always the same within one compilation. Reset between compilations.
-    now: OnceCell<DateTime<Local>>,
+    now: OnceCell<DateTime<Utc>>,
+    /// A fixed date and time that is used instead of the current one to make
+    /// builds reproducible.
+    creation_timestamp: Option<DateTime<Utc>>,
     /// The export cache, used for caching output files in `typst watch`
     /// sessions.
     export_cache: ExportCache,
@@ -46,21 +62,35 @@ pub struct SystemWorld {
 
 impl SystemWorld {
     /// Create a new system world.
-    pub fn new(command: &SharedArgs) -> StrResult<Self> {
+    pub fn new(input: &Input, command: &SharedArgs) -> StrResult<Self> {
         let mut searcher = FontSearcher::new();
         searcher.search(&command.font_paths);
+        let book = Arc::new(Prehashed::new(searcher.book));
+        Self::with_fonts(input, command, book, Arc::new(searcher.fonts))
+    }
 
+    /// Create a new system world with fonts that were searched before, so
+    /// that multiple worlds can share them.
+    pub fn with_fonts(
+        input: &Input,
+        command: &SharedArgs,
+        book: Arc<Prehashed<FontBook>>,
+        fonts: Arc<Vec<FontSlot>>,
+    ) -> StrResult<Self> {
         // Resolve the system-global input path.
-        let input = command.input.canonicalize().map_err(|_| {
-            eco_format!("input file not found (searched at {})", command.input.display())
-        })?;
+        let input = match input {
+            Input::Stdin => None,
+            Input::Path(path) => Some(path.canonicalize().map_err(|_| {
+                eco_format!("input file not found (searched at {})", path.display())
+            })?),
+        };
 
         // Resolve the system-global root directory.
         let root = {
             let path = command
                 .root
                 .as_deref()
-                .or_else(|| input.parent())
+                .or_else(|| input.as_deref().and_then(Path::parent))
                 .unwrap_or(Path::new("."));
             path.canonicalize().map_err(|_| {
                 eco_format!("root directory not found (searched at {})", path.display())
@@ -68,19 +98,57 @@ impl SystemWorld {
         };
 
         // Resolve the virtual path of the main file within the project root.
-        let main_path = VirtualPath::within_root(&input, &root)
-            .ok_or("input file must be contained in project root")?;
+        // Input from stdin lives at a virtual path that can't clash with a
+        // real file.
+        let main_path = match &input {
+            Some(input) => VirtualPath::within_root(input, &root)
+                .ok_or("input file must be contained in project root")?,
+            None => VirtualPath::new(STDIN_PATH),
+        };
+
+        // Keep the input from stdin in memory in place of the virtual file.
+        let mut overlays = HashMap::new();
+        if input.is_none() {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|err| eco_format!("failed to read from stdin ({err})"))?;
+            overlays.insert(root.join(STDIN_PATH), text);
+        }
+
+        // Check packages against the lockfile next to the main file.
+        let lockfile = Lockfile::load(
+            input
+                .as_deref()
+                .and_then(Path::parent)
+                .unwrap_or(&root)
+                .join(LOCKFILE_NAME),
+            if command.locked { LockfileMode::Locked } else { LockfileMode::Record },
+        )?;
+        let package_storage =
+            PackageStorage::from_args(&command.package).with_lockfile(lockfile);
+
+        // Convert the input pairs to a dictionary.
+        let inputs: Dict = command
+            .inputs
+            .iter()
+            .map(|(k, v)| (k.as_str().into(), v.as_str().into_value()))
+            .collect();
 
         Ok(Self {
             workdir: std::env::current_dir().ok(),
             input,
             root,
             main: FileId::new(None, main_path),
-            library: Prehashed::new(Library::build()),
-            book: Prehashed::new(searcher.book),
-            fonts: searcher.fonts,
+            library: Prehashed::new(Library::builder().with_inputs(inputs).build()),
+            inputs: None,
+            book,
+            fonts,
+            package_storage,
             slots: RefCell::default(),
+            overlays,
             now: OnceCell::new(),
+            creation_timestamp: command.creation_timestamp,
             export_cache: ExportCache::new(),
         })
     }
@@ -90,6 +158,64 @@ impl SystemWorld {
         self.main
     }
 
+    /// Make the file at the given path the main file.
+    pub fn set_main(&mut self, path: &Path) -> StrResult<()> {
+        let input = path.canonicalize().map_err(|_| {
+            eco_format!("input file not found (searched at {})", path.display())
+        })?;
+        let main_path = VirtualPath::within_root(&input, &self.root)
+            .ok_or("input file must be contained in project root")?;
+        self.input = Some(input);
+        self.main = FileId::new(None, main_path);
+        Ok(())
+    }
+
+    /// Replace the values that are visible through `sys.inputs`.
+    ///
+    /// The library stays the same, so that only the modules that refer to
+    /// `sys` must be evaluated again.
+    pub fn set_inputs(&mut self, inputs: Dict) {
+        self.inputs = Some(inputs);
+    }
+
+    /// Use the given text instead of the contents of the file at `path`, or go
+    /// back to reading the file if `text` is `None`.
+    pub fn set_overlay(&mut self, path: PathBuf, text: Option<String>) {
+        match text {
+            Some(text) => self.overlays.insert(path, text),
+            None => self.overlays.remove(&path),
+        };
+    }
+
+    /// Edit the overlay of the file at `path` through its source.
+    ///
+    /// Unlike [`set_overlay`](Self::set_overlay), this keeps the edited source
+    /// in the cache, so that only the edited parts are reparsed.
+    pub fn edit_overlay(
+        &mut self,
+        path: PathBuf,
+        edit: impl FnOnce(&mut Source),
+    ) -> FileResult<()> {
+        let vpath =
+            VirtualPath::within_root(&path, &self.root).ok_or(FileError::AccessDenied)?;
+        let slot = self.slot(FileId::new(None, vpath))?;
+        let mut source =
+            slot.source(&self.root, &self.package_storage, &self.overlays)?;
+        edit(&mut source);
+
+        let text = source.text().to_string();
+        slot.source.set(Ok(text.clone().into_bytes()), source);
+        drop(slot);
+
+        self.overlays.insert(path, text);
+        Ok(())
+    }
+
+    /// The path of a file on the system.
+    pub fn system_path(&self, id: FileId) -> FileResult<PathBuf> {
+        self.slot(id)?.system_path(&self.root, &self.package_storage)
+    }
+
     /// The root relative to which absolute paths are resolved.
     pub fn root(&self) -> &Path {
         &self.root
@@ -101,12 +227,22 @@ impl SystemWorld {
     }
 
     /// Return all paths the last compilation depended on.
+    ///
+    /// This includes the files of all fonts that were loaded so far, but not
+    /// the main file if it was read from stdin.
     pub fn dependencies(&mut self) -> impl Iterator<Item = PathBuf> + '_ {
-        self.slots
+        // Input from stdin has no file on disk, no matter through which file
+        // id it was accessed.
+        let stdin = self.input.is_none().then(|| self.root.join(STDIN_PATH));
+        let files = self
+            .slots
             .get_mut()
             .values()
             .filter(|slot| slot.accessed())
-            .filter_map(|slot| slot.system_path(&self.root).ok())
+            .filter_map(|slot| slot.system_path(&self.root, &self.package_storage).ok())
+            .filter(move |path| Some(path) != stdin.as_ref());
+        let fonts = self.fonts.iter().filter_map(|slot| slot.loaded_path());
+        files.chain(fonts.map(Path::to_path_buf))
     }
 
     /// Reset the compilation state in preparation of a new compilation.
@@ -114,12 +250,32 @@ impl SystemWorld {
         for slot in self.slots.get_mut().values_mut() {
             slot.reset();
         }
+        if let Some(lockfile) = self.package_storage.lockfile_mut() {
+            lockfile.reset();
+        }
         self.now.take();
     }
 
-    /// Return the canonical path to the input file.
-    pub fn input(&self) -> &PathBuf {
-        &self.input
+    /// Record the packages used by the last compilation in the lockfile.
+    pub fn write_lockfile(&mut self) -> StrResult<()> {
+        match self.package_storage.lockfile_mut() {
+            Some(lockfile) => lockfile.write(),
+            None => Ok(()),
+        }
+    }
+
+    /// The current date and time, or the fixed creation timestamp if there is
+    /// one. Stays the same within one compilation.
+    pub fn now(&self) -> DateTime<Utc> {
+        *self
+            .now
+            .get_or_init(|| self.creation_timestamp.unwrap_or_else(Utc::now))
+    }
+
+    /// Return the canonical path to the input file, or `None` if it was read
+    /// from stdin.
+    pub fn input(&self) -> Option<&PathBuf> {
+        self.input.as_ref()
     }
 
     /// Lookup a source file by id.
@@ -148,11 +304,12 @@ impl World for SystemWorld {
     }
 
     fn source(&self, id: FileId) -> FileResult<Source> {
-        self.slot(id)?.source(&self.root)
+        self.slot(id)?
+            .source(&self.root, &self.package_storage, &self.overlays)
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.slot(id)?.file(&self.root)
+        self.slot(id)?.file(&self.root, &self.package_storage, &self.overlays)
     }
 
     fn font(&self, index: usize) -> Option<Font> {
@@ -160,10 +317,13 @@ impl World for SystemWorld {
     }
 
     fn today(&self, offset: Option<i64>) -> Option<Datetime> {
-        let now = self.now.get_or_init(chrono::Local::now);
+        let now = self.now();
 
         let naive = match offset {
-            None => now.naive_local(),
+            // A fixed timestamp is interpreted in UTC so that the date doesn't
+            // depend on the time zone of the machine.
+            None if self.creation_timestamp.is_some() => now.naive_utc(),
+            None => now.with_timezone(&Local).naive_local(),
             Some(o) => now.naive_utc() + chrono::Duration::hours(o),
         };
 
@@ -173,6 +333,10 @@ impl World for SystemWorld {
             naive.day().try_into().ok()?,
         )
     }
+
+    fn inputs(&self) -> Option<Dict> {
+        self.inputs.clone()
+    }
 }
 
 impl SystemWorld {
@@ -216,9 +380,14 @@ impl FileSlot {
     }
 
     /// Retrieve the source for this file.
-    fn source(&self, root: &Path) -> FileResult<Source> {
+    fn source(
+        &self,
+        root: &Path,
+        package_storage: &PackageStorage,
+        overlays: &HashMap<PathBuf, String>,
+    ) -> FileResult<Source> {
         self.source.get_or_init(
-            || self.system_path(root),
+            || self.load(root, package_storage, overlays),
             |data, prev| {
                 let text = decode_utf8(&data)?;
                 if let Some(mut prev) = prev {
@@ -232,19 +401,44 @@ impl FileSlot {
     }
 
     /// Retrieve the file's bytes.
-    fn file(&self, root: &Path) -> FileResult<Bytes> {
-        self.file
-            .get_or_init(|| self.system_path(root), |data, _| Ok(data.into()))
+    fn file(
+        &self,
+        root: &Path,
+        package_storage: &PackageStorage,
+        overlays: &HashMap<PathBuf, String>,
+    ) -> FileResult<Bytes> {
+        self.file.get_or_init(
+            || self.load(root, package_storage, overlays),
+            |data, _| Ok(data.into()),
+        )
+    }
+
+    /// Read the file's contents, preferring an overlay over the file system.
+    fn load(
+        &self,
+        root: &Path,
+        package_storage: &PackageStorage,
+        overlays: &HashMap<PathBuf, String>,
+    ) -> FileResult<Vec<u8>> {
+        let path = self.system_path(root, package_storage)?;
+        match overlays.get(&path) {
+            Some(text) => Ok(text.clone().into_bytes()),
+            None => read(&path),
+        }
     }
 
     /// The path of the slot on the system.
-    fn system_path(&self, root: &Path) -> FileResult<PathBuf> {
+    fn system_path(
+        &self,
+        root: &Path,
+        package_storage: &PackageStorage,
+    ) -> FileResult<PathBuf> {
         // Determine the root path relative to which the file path
         // will be resolved.
         let buf;
         let mut root = root;
         if let Some(spec) = self.id.package() {
-            buf = prepare_package(spec)?;
+            buf = package_storage.prepare_package(spec)?;
             root = &buf;
         }
 
@@ -285,10 +479,17 @@ impl<T: Clone> SlotCell<T> {
         self.accessed.set(false);
     }
 
+    /// Replaces the contents of the cell with data that was processed from
+    /// the given raw contents.
+    fn set(&self, raw: FileResult<Vec<u8>>, value: T) {
+        self.fingerprint.set(hash128(&raw));
+        *self.data.borrow_mut() = Some(Ok(value));
+    }
+
     /// Gets the contents of the cell or initialize them.
     fn get_or_init(
         &self,
-        path: impl FnOnce() -> FileResult<PathBuf>,
+        load: impl FnOnce() -> FileResult<Vec<u8>>,
         f: impl FnOnce(Vec<u8>, Option<T>) -> FileResult<T>,
     ) -> FileResult<T> {
         let mut borrow = self.data.borrow_mut();
@@ -301,7 +502,7 @@ impl<T: Clone> SlotCell<T> {
         }
 
         // Read and hash the file.
-        let result = path().and_then(|p| read(&p));
+        let result = load();
         let fingerprint = typst::util::hash128(&result);
 
         // If the file contents didn't change, yield the old processed data.
@@ -342,9 +543,10 @@ impl ExportCache {
     pub fn is_cached(&mut self, i: usize, frame: &Frame) -> bool {
         let hash = hash128(frame);
 
+        // Pages may be skipped when only some of them are exported, so we
+        // can't simply push to the end.
         if i >= self.cache.len() {
-            self.cache.push(hash);
-            return false;
+            self.cache.resize(i + 1, 0);
         }
 
         std::mem::replace(&mut self.cache[i], hash) == hash
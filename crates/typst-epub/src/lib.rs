@@ -0,0 +1,704 @@
+//! Exporting of Typst documents into EPUB 3 e-books.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use std::num::NonZeroUsize;
+
+use ecow::{eco_format, EcoString};
+use ttf_parser::{Permissions, RawFace, Tag};
+use typst::diag::{At, SourceResult, StrResult};
+use typst::foundations::{Content, Datetime, Smart};
+use typst::layout::{Dir, Frame, FrameItem};
+use typst::model::{Document, HeadingNode};
+use typst::syntax::Span;
+use typst::text::{Font, FontStyle};
+use typst::util::hash128;
+use typst::World;
+use typst_html::{anchor, escape, HtmlBody, ImageMode};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// The directory in the archive that holds the publication's files.
+const OEBPS: &str = "OEBPS";
+
+/// Export a document into an EPUB 3 file.
+///
+/// The document is written into HTML and split into one XHTML file per
+/// top-level heading. The navigation document mirrors the bookmarks of PDF
+/// export. Images and subsets of the laid-out document's fonts are embedded,
+/// and the title, authors, keywords, and language become the publication's
+/// metadata.
+///
+/// The `ident` parameter shall be a string that uniquely and stably identifies
+/// the document. Its hash becomes the publication's identifier. If `ident` is
+/// `None`, a hash of the document's content is used instead.
+///
+/// The `timestamp`, if given, is expected to be the current date as a UTC
+/// datetime. It becomes the publication's modification date and, if `set
+/// document(date: ..)` is `auto`, also its date.
+#[tracing::instrument(skip_all)]
+pub fn epub(
+    world: &dyn World,
+    document: &Document,
+    ident: Option<&str>,
+    timestamp: Option<Datetime>,
+) -> SourceResult<Vec<u8>> {
+    let body = typst_html::html_body(world, document, ImageMode::External)?;
+    let publication = Publication::new(document, &body, ident, timestamp);
+    publication.write().at(Span::detached())
+}
+
+/// An e-book that is ready to be packaged.
+struct Publication<'a> {
+    /// The document the publication stems from.
+    document: &'a Document,
+    /// The document written into HTML.
+    body: &'a HtmlBody,
+    /// The publication's unique identifier.
+    identifier: EcoString,
+    /// The publication's date, if any.
+    date: Option<Datetime>,
+    /// The publication's modification date.
+    modified: Option<Datetime>,
+    /// The XHTML bodies of the chapters, with links between chapters resolved.
+    chapters: Vec<String>,
+    /// Maps IDs in the chapters to the file they are defined in.
+    ids: HashMap<String, String>,
+    /// The fonts of the laid-out document.
+    fonts: Vec<EmbeddedFont>,
+}
+
+/// A font that is embedded into the publication.
+struct EmbeddedFont {
+    /// The font the document uses.
+    font: Font,
+    /// The standalone font file, reduced to the glyphs the document uses.
+    data: Vec<u8>,
+}
+
+impl<'a> Publication<'a> {
+    /// Split the HTML into chapters and gather everything else the
+    /// publication needs.
+    fn new(
+        document: &'a Document,
+        body: &'a HtmlBody,
+        ident: Option<&str>,
+        timestamp: Option<Datetime>,
+    ) -> Self {
+        let hash = match ident {
+            Some(ident) => hash128(&("EPUB-3", ident)),
+            None => hash128(body.html()),
+        };
+
+        let date = match document.date {
+            Smart::Custom(date) => date,
+            Smart::Auto => timestamp,
+        };
+
+        // Content before the first top-level heading only gets a chapter of its
+        // own if there is any.
+        let mut chapters: Vec<String> = body
+            .sections(NonZeroUsize::ONE)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, section)| *i > 0 || !section.trim().is_empty())
+            .map(|(_, section)| section.into())
+            .collect();
+        if chapters.is_empty() {
+            chapters.push(String::new());
+        }
+
+        // Footnotes are placed at the end of the chapter that first refers
+        // to them.
+        for note in &body.footnotes {
+            let needle = format!(" id=\"{}\"", note.ref_id);
+            let i = chapters.iter().position(|c| c.contains(&needle)).unwrap_or(0);
+            writeln!(
+                chapters[i],
+                "<aside epub:type=\"footnote\" id=\"{}\">\
+                 <p><a href=\"#{}\">{}</a> {}</p></aside>",
+                note.id, note.ref_id, note.number, note.body,
+            )
+            .unwrap();
+        }
+
+        let mut ids: HashMap<String, String> = HashMap::new();
+        for (i, chapter) in chapters.iter().enumerate() {
+            for id in defined_ids(chapter) {
+                ids.entry(id.into()).or_insert_with(|| chapter_path(i));
+            }
+        }
+
+        let chapters = chapters
+            .iter()
+            .enumerate()
+            .map(|(i, chapter)| relink(chapter, &chapter_path(i), &ids))
+            .collect();
+
+        Self {
+            document,
+            body,
+            identifier: eco_format!("urn:uuid:{}", uuid(hash)),
+            date,
+            modified: timestamp.or(date),
+            chapters,
+            ids,
+            fonts: fonts(&document.pages),
+        }
+    }
+
+    /// Package the publication into a ZIP archive.
+    fn write(&self) -> StrResult<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated =
+            FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        // The media type must come first and be stored uncompressed.
+        add(&mut zip, "mimetype", b"application/epub+zip", stored)?;
+        add(&mut zip, "META-INF/container.xml", CONTAINER.as_bytes(), deflated)?;
+        add(&mut zip, &oebps("content.opf"), self.package().as_bytes(), deflated)?;
+        add(&mut zip, &oebps("nav.xhtml"), self.nav().as_bytes(), deflated)?;
+        add(&mut zip, &oebps("style.css"), self.stylesheet().as_bytes(), deflated)?;
+
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            let xhtml = self.xhtml(chapter);
+            add(&mut zip, &oebps(&chapter_path(i)), xhtml.as_bytes(), deflated)?;
+        }
+
+        // Images and fonts are mostly compressed already.
+        for image in &self.body.images {
+            add(&mut zip, &oebps(&image.path), &image.data, stored)?;
+        }
+
+        for (i, font) in self.fonts.iter().enumerate() {
+            add(&mut zip, &oebps(&font_path(i, font)), &font.data, stored)?;
+        }
+
+        let cursor = zip
+            .finish()
+            .map_err(|err| eco_format!("failed to write EPUB ({err})"))?;
+        Ok(cursor.into_inner())
+    }
+
+    /// The package document, which lists the publication's metadata and
+    /// files.
+    fn package(&self) -> String {
+        let lang = self.body.lang.as_str();
+        let mut opf = String::new();
+        opf.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        writeln!(
+            opf,
+            "<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" \
+             unique-identifier=\"uid\" xml:lang=\"{lang}\">"
+        )
+        .unwrap();
+
+        opf.push_str("<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+        writeln!(opf, "<dc:identifier id=\"uid\">{}</dc:identifier>", self.identifier)
+            .unwrap();
+        writeln!(opf, "<dc:title>{}</dc:title>", escape(&self.title())).unwrap();
+        writeln!(opf, "<dc:language>{lang}</dc:language>").unwrap();
+        for author in &self.document.author {
+            writeln!(opf, "<dc:creator>{}</dc:creator>", escape(author)).unwrap();
+        }
+        for keyword in &self.document.keywords {
+            writeln!(opf, "<dc:subject>{}</dc:subject>", escape(keyword)).unwrap();
+        }
+        if let Some(date) = self.date.and_then(format_date) {
+            writeln!(opf, "<dc:date>{date}</dc:date>").unwrap();
+        }
+        let modified = self
+            .modified
+            .and_then(format_datetime)
+            .unwrap_or_else(|| "1970-01-01T00:00:00Z".into());
+        writeln!(opf, "<meta property=\"dcterms:modified\">{modified}</meta>").unwrap();
+        opf.push_str("</metadata>\n");
+
+        opf.push_str("<manifest>\n");
+        opf.push_str(
+            "<item id=\"nav\" href=\"nav.xhtml\" \
+             media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n",
+        );
+        opf.push_str("<item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n");
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            let properties =
+                if chapter.contains("<svg") { " properties=\"svg\"" } else { "" };
+            writeln!(
+                opf,
+                "<item id=\"chapter-{}\" href=\"{}\" \
+                 media-type=\"application/xhtml+xml\"{properties}/>",
+                i + 1,
+                chapter_path(i),
+            )
+            .unwrap();
+        }
+        for (i, image) in self.body.images.iter().enumerate() {
+            writeln!(
+                opf,
+                "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>",
+                i + 1,
+                escape(&image.path),
+                image.mime,
+            )
+            .unwrap();
+        }
+        for (i, font) in self.fonts.iter().enumerate() {
+            writeln!(
+                opf,
+                "<item id=\"font-{}\" href=\"{}\" media-type=\"{}\"/>",
+                i + 1,
+                font_path(i, font),
+                font_mime(font),
+            )
+            .unwrap();
+        }
+        opf.push_str("</manifest>\n");
+
+        let direction = if self.body.lang.dir() == Dir::RTL { "rtl" } else { "ltr" };
+        writeln!(opf, "<spine page-progression-direction=\"{direction}\">").unwrap();
+        for i in 0..self.chapters.len() {
+            writeln!(opf, "<itemref idref=\"chapter-{}\"/>", i + 1).unwrap();
+        }
+        opf.push_str("</spine>\n</package>\n");
+        opf
+    }
+
+    /// The navigation document, which lists the bookmarked headings.
+    fn nav(&self) -> String {
+        let tree = HeadingNode::tree(&self.document.introspector, |_| true);
+        let mut body = String::new();
+        body.push_str("<nav epub:type=\"toc\" id=\"toc\">\n");
+        writeln!(body, "<h1>{}</h1>", escape(&self.title())).unwrap();
+        if tree.is_empty() {
+            writeln!(
+                body,
+                "<ol>\n<li><a href=\"{}\">{}</a></li>\n</ol>",
+                chapter_path(0),
+                escape(&self.title()),
+            )
+            .unwrap();
+        } else {
+            self.nav_list(&tree, &mut body);
+        }
+        body.push_str("</nav>\n");
+        self.xhtml(&body)
+    }
+
+    /// Write a level of the navigation document.
+    fn nav_list(&self, nodes: &[HeadingNode], body: &mut String) {
+        body.push_str("<ol>\n");
+        for node in nodes {
+            let title = node.element.expect_field_by_name::<Content>("body").plain_text();
+            let href = anchor(&node.element)
+                .map(|id| escape(&id))
+                .and_then(|id| Some(format!("{}#{id}", self.ids.get(&id)?)))
+                .unwrap_or_else(|| chapter_path(0));
+            write!(body, "<li><a href=\"{href}\">{}</a>", escape(title.trim())).unwrap();
+            if !node.children.is_empty() {
+                body.push('\n');
+                self.nav_list(&node.children, body);
+            }
+            body.push_str("</li>\n");
+        }
+        body.push_str("</ol>\n");
+    }
+
+    /// The stylesheet, which embeds the fonts of the laid-out document.
+    fn stylesheet(&self) -> String {
+        let mut css = String::new();
+        for (i, font) in self.fonts.iter().enumerate() {
+            let info = font.font.info();
+            let style = match info.variant.style {
+                FontStyle::Normal => "normal",
+                FontStyle::Italic => "italic",
+                FontStyle::Oblique => "oblique",
+            };
+            writeln!(
+                css,
+                "@font-face {{ font-family: \"{}\"; src: url(\"{}\"); \
+                 font-weight: {}; font-style: {style}; }}",
+                info.family,
+                font_path(i, font),
+                info.variant.weight.to_number(),
+            )
+            .unwrap();
+        }
+
+        // The first font the document uses is most likely its body font.
+        if let Some(font) = self.fonts.first() {
+            writeln!(css, "body {{ font-family: \"{}\"; }}", font.font.info().family)
+                .unwrap();
+        }
+
+        css.push_str("img, svg { max-width: 100%; height: auto; }\n");
+        css
+    }
+
+    /// Wrap a body into an XHTML file.
+    fn xhtml(&self, body: &str) -> String {
+        let lang = self.body.lang.as_str();
+        let mut xhtml = String::new();
+        xhtml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n");
+        writeln!(
+            xhtml,
+            "<html xmlns=\"http://www.w3.org/1999/xhtml\" \
+             xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+             lang=\"{lang}\" xml:lang=\"{lang}\">"
+        )
+        .unwrap();
+        xhtml.push_str("<head>\n<meta charset=\"utf-8\"/>\n");
+        writeln!(xhtml, "<title>{}</title>", escape(&self.title())).unwrap();
+        xhtml.push_str(
+            "<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n",
+        );
+        xhtml.push_str("</head>\n<body>\n");
+        xhtml.push_str(body);
+        xhtml.push_str("</body>\n</html>\n");
+        xhtml
+    }
+
+    /// The publication's title, which is required.
+    fn title(&self) -> EcoString {
+        self.document.title.clone().unwrap_or_else(|| "Untitled".into())
+    }
+}
+
+/// The container file, which points reading systems to the package document.
+const CONTAINER: &str = "\
+<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">
+<rootfiles>
+<rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>
+</rootfiles>
+</container>
+";
+
+/// Add a file to the archive.
+fn add(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    path: &str,
+    data: &[u8],
+    options: FileOptions,
+) -> StrResult<()> {
+    zip.start_file(path, options)
+        .map_err(|err| eco_format!("failed to write EPUB ({err})"))?;
+    zip.write_all(data)
+        .map_err(|err| eco_format!("failed to write EPUB ({err})"))
+}
+
+/// The path of a file in the publication's directory.
+fn oebps(path: &str) -> String {
+    format!("{OEBPS}/{path}")
+}
+
+/// The path of a chapter, relative to the package document.
+fn chapter_path(i: usize) -> String {
+    format!("chapter-{}.xhtml", i + 1)
+}
+
+/// The path of a font, relative to the package document.
+fn font_path(i: usize, font: &EmbeddedFont) -> String {
+    let extension = if font.data.starts_with(b"OTTO") { "otf" } else { "ttf" };
+    format!("fonts/font-{}.{extension}", i + 1)
+}
+
+/// The media type of a font.
+fn font_mime(font: &EmbeddedFont) -> &'static str {
+    if font.data.starts_with(b"OTTO") {
+        "font/otf"
+    } else {
+        "font/ttf"
+    }
+}
+
+/// Collect the fonts used in the frames, in the order of their first use, and
+/// subset them to the glyphs the document uses.
+///
+/// Fonts that cannot be embedded are left out, so that reading systems fall
+/// back to their own fonts for the text.
+fn fonts(frames: &[Frame]) -> Vec<EmbeddedFont> {
+    fn visit(
+        frame: &Frame,
+        indices: &mut HashMap<Font, usize>,
+        used: &mut Vec<(Font, BTreeSet<u16>)>,
+    ) {
+        for (_, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => visit(&group.frame, indices, used),
+                FrameItem::Text(text) => {
+                    let font = &text.font;
+                    let i = *indices.entry(font.clone()).or_insert_with(|| {
+                        used.push((font.clone(), BTreeSet::new()));
+                        used.len() - 1
+                    });
+
+                    // Reading systems shape the text on their own, so they
+                    // also need the glyphs the characters map to.
+                    let glyphs = &mut used[i].1;
+                    glyphs.extend(text.glyphs.iter().map(|glyph| glyph.id));
+                    glyphs.extend(
+                        text.text
+                            .chars()
+                            .filter_map(|c| font.ttf().glyph_index(c))
+                            .map(|id| id.0),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut indices = HashMap::new();
+    let mut used = vec![];
+    for frame in frames {
+        visit(frame, &mut indices, &mut used);
+    }
+
+    used.into_iter()
+        .filter(|(font, _)| embeddable(font))
+        .filter_map(|(font, glyphs)| {
+            let glyphs: Vec<u16> = glyphs.into_iter().collect();
+            let data = subset(&font, &glyphs)?;
+            Some(EmbeddedFont { font, data })
+        })
+        .collect()
+}
+
+/// Whether a font can be embedded into the publication.
+///
+/// Fonts whose license restricts embedding must not be distributed with the
+/// book.
+fn embeddable(font: &Font) -> bool {
+    font.ttf().permissions() != Some(Permissions::Restricted)
+}
+
+/// The tables that reading systems need to shape the text.
+const LAYOUT_TABLES: [&[u8; 4]; 3] = [b"GDEF", b"GPOS", b"GSUB"];
+
+/// Reduce a font to the given glyphs and extract it from its collection, if
+/// any.
+///
+/// Subsetting works like in PDF export, but since reading systems shape the
+/// text themselves, the layout tables are carried over. Subsetting keeps the
+/// glyph IDs, so they stay valid. If subsetting fails, a standalone font is
+/// embedded as a whole and a face of a collection is left out.
+fn subset(font: &Font, glyphs: &[u16]) -> Option<Vec<u8>> {
+    let profile = subsetter::Profile::pdf(glyphs);
+    match subsetter::subset(font.data(), font.index(), profile) {
+        Ok(data) => with_layout_tables(&data, font).or(Some(data)),
+        Err(err) if !font.data().starts_with(b"ttcf") => {
+            tracing::warn!("failed to subset font ({err})");
+            Some(font.data().to_vec())
+        }
+        Err(err) => {
+            tracing::warn!("failed to extract font from collection ({err})");
+            None
+        }
+    }
+}
+
+/// Add the layout tables of the original font to a subsetted font.
+fn with_layout_tables(data: &[u8], font: &Font) -> Option<Vec<u8>> {
+    let original = RawFace::parse(font.data(), font.index()).ok()?;
+    let subsetted = RawFace::parse(data, 0).ok()?;
+
+    let mut tables: Vec<(Tag, &[u8])> = subsetted
+        .table_records
+        .into_iter()
+        .filter_map(|record| Some((record.tag, subsetted.table(record.tag)?)))
+        .collect();
+    let count = tables.len();
+    tables.extend(LAYOUT_TABLES.iter().filter_map(|tag| {
+        let tag = Tag::from_bytes(tag);
+        Some((tag, original.table(tag)?))
+    }));
+    if tables.len() == count {
+        return None;
+    }
+    tables.sort_by_key(|&(tag, _)| tag);
+
+    // The table directory, as in the OpenType specification. The records
+    // are filled in once the tables are written.
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range = 16_u16 << entry_selector;
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..4]);
+    out.extend_from_slice(&num_tables.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+    out.resize(12 + 16 * tables.len(), 0);
+
+    let mut head = None;
+    for (i, &(tag, table)) in tables.iter().enumerate() {
+        let offset = out.len();
+        out.extend_from_slice(table);
+        out.resize((out.len() + 3) & !3, 0);
+
+        // The checksum adjustment is zero while the checksums are computed.
+        if tag == Tag::from_bytes(b"head") {
+            out.get_mut(offset + 8..offset + 12)?.fill(0);
+            head = Some(offset + 8);
+        }
+
+        let sum = checksum(&out[offset..offset + table.len()]);
+        let record = 12 + 16 * i;
+        out[record..record + 4].copy_from_slice(&tag.0.to_be_bytes());
+        out[record + 4..record + 8].copy_from_slice(&sum.to_be_bytes());
+        out[record + 8..record + 12].copy_from_slice(&(offset as u32).to_be_bytes());
+        out[record + 12..record + 16]
+            .copy_from_slice(&(table.len() as u32).to_be_bytes());
+    }
+
+    // The adjustment makes the whole file sum up to a magic number.
+    let head = head?;
+    let adjustment = 0xB1B0AFBA_u32.wrapping_sub(checksum(&out));
+    out[head..head + 4].copy_from_slice(&adjustment.to_be_bytes());
+    Some(out)
+}
+
+/// The checksum of a font table: the sum of its big-endian 32-bit words.
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Find the IDs defined in a piece of HTML.
+fn defined_ids(html: &str) -> impl Iterator<Item = &str> {
+    html.match_indices(" id=\"").map(|(i, m)| {
+        let rest = &html[i + m.len()..];
+        &rest[..rest.find('"').unwrap_or(rest.len())]
+    })
+}
+
+/// Point links to IDs in other chapters to those chapters.
+///
+/// IDs defined in the chapter itself take precedence, since inline SVGs in
+/// different chapters may use the same IDs.
+fn relink(html: &str, path: &str, ids: &HashMap<String, String>) -> String {
+    let local: HashSet<&str> = defined_ids(html).collect();
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find("href=\"#") {
+        let (before, after) = rest.split_at(i + "href=\"".len());
+        output.push_str(before);
+
+        let id = &after[1..after.find('"').unwrap_or(after.len())];
+        if !local.contains(id) {
+            if let Some(target) = ids.get(id).filter(|&target| target != path) {
+                output.push_str(target);
+            }
+        }
+
+        rest = after;
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Format a hash like a UUID.
+fn uuid(hash: u128) -> String {
+    let hex = format!("{hash:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Format the date part of a datetime.
+fn format_date(date: Datetime) -> Option<String> {
+    Some(format!("{:04}-{:02}-{:02}", date.year()?, date.month()?, date.day()?))
+}
+
+/// Format a datetime in UTC. A missing time is taken to be midnight.
+fn format_datetime(date: Datetime) -> Option<String> {
+    Some(format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_date(date)?,
+        date.hour().unwrap_or(0),
+        date.minute().unwrap_or(0),
+        date.second().unwrap_or(0),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::foundations::Bytes;
+
+    use super::*;
+
+    /// Load a font from the test assets, optionally with its OS/2 embedding
+    /// permissions replaced.
+    fn font(name: &str, fs_type: Option<u16>) -> Font {
+        let mut data = std::fs::read(format!("../../assets/fonts/{name}")).unwrap();
+        if let Some(fs_type) = fs_type {
+            let num_tables = u16::from_be_bytes([data[4], data[5]]) as usize;
+            let record = (0..num_tables)
+                .map(|i| 12 + 16 * i)
+                .find(|&record| data[record..record + 4] == *b"OS/2")
+                .unwrap();
+            let offset =
+                u32::from_be_bytes(data[record + 8..record + 12].try_into().unwrap());
+            let field = offset as usize + 8;
+            data[field..field + 2].copy_from_slice(&fs_type.to_be_bytes());
+        }
+        Font::new(Bytes::from(data), 0).unwrap()
+    }
+
+    #[test]
+    fn test_embeddable() {
+        assert!(embeddable(&font("IBMPlexSans-Regular.ttf", None)));
+        assert!(embeddable(&font("FiraMath-Regular.otf", None)));
+    }
+
+    #[test]
+    fn test_embeddable_permissions() {
+        // Installable, restricted, preview & print, and editable embedding.
+        assert!(embeddable(&font("IBMPlexSans-Regular.ttf", Some(0x0000))));
+        assert!(!embeddable(&font("IBMPlexSans-Regular.ttf", Some(0x0002))));
+        assert!(embeddable(&font("IBMPlexSans-Regular.ttf", Some(0x0004))));
+        assert!(embeddable(&font("IBMPlexSans-Regular.ttf", Some(0x0008))));
+    }
+
+    #[test]
+    fn test_subset() {
+        let font = font("NotoSansArabic-Regular.ttf", None);
+        let ttf = font.ttf();
+        let glyphs: Vec<u16> = "بسم"
+            .chars()
+            .filter_map(|c| ttf.glyph_index(c))
+            .map(|id| id.0)
+            .collect();
+        let data = subset(&font, &glyphs).unwrap();
+        assert!(data.len() < font.data().len() / 2);
+        assert_eq!(checksum(&data), 0xB1B0AFBA);
+
+        let face = ttf_parser::Face::parse(&data, 0).unwrap();
+        assert_eq!(face.glyph_index('ب'), ttf.glyph_index('ب'));
+        assert!(face.tables().gsub.is_some());
+        assert!(face.tables().gpos.is_some());
+    }
+
+    #[test]
+    fn test_relink() {
+        let ids = HashMap::from([
+            ("intro".to_string(), chapter_path(0)),
+            ("end".to_string(), chapter_path(1)),
+        ]);
+        let html = r##"<a href="#intro">a</a> <a href="#end">b</a> <p id="end"></p>"##;
+        assert_eq!(
+            relink(html, &chapter_path(1), &ids),
+            r##"<a href="chapter-1.xhtml#intro">a</a> <a href="#end">b</a> <p id="end"></p>"##,
+        );
+    }
+}
@@ -0,0 +1,1171 @@
+//! Exporting of Typst documents into semantic HTML.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::mem;
+use std::num::NonZeroUsize;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use comemo::Track;
+use ecow::{eco_format, EcoString};
+use typst::diag::{At, SourceResult};
+use typst::engine::{Engine, Route};
+use typst::eval::Tracer;
+use typst::foundations::{
+    Bytes, Content, Element, Guard, Label, NativeElement, PlainText, Resolve, Selector,
+    Show, Smart, StyleChain, Value,
+};
+use typst::introspection::{Counter, Locator, MetaElem};
+use typst::layout::{
+    Abs, Axes, BoxElem, ColbreakElem, HElem, Layout, Length, PageElem, PagebreakElem,
+    Paper, Regions, Rel, Size, VAlign, VElem,
+};
+use typst::loading::Readable;
+use typst::math::EquationElem;
+use typst::model::{
+    Destination, Document, EmphElem, EnumElem, EnumItem, FigureElem, FootnoteBody,
+    FootnoteElem, HeadingElem, LinkElem, LinkTarget, ListElem, ListItem, Numbering,
+    OutlineEntry, ParbreakElem, QuoteElem, StrongElem, TableElem, TermItem, TermsElem,
+};
+use typst::realize::realize;
+use typst::syntax::Span;
+use typst::text::{
+    FontStyle, Lang, LinebreakElem, RawElem, SmartQuoteElem, SpaceElem, TextElem,
+};
+use typst::util::hash128;
+use typst::visualize::{ImageElem, ImageFormat, Paint, RasterFormat, VectorFormat};
+use typst::World;
+
+/// Export a document into a semantic HTML file.
+///
+/// The main file is evaluated again and its content is written out element by
+/// element: Headings, paragraphs, lists, tables, figures, links, footnotes,
+/// images, and raw text become their HTML counterparts, while math and
+/// arbitrary layout are laid out and embedded as inline SVG. Labelled elements
+/// receive their label as their ID.
+///
+/// The laid-out document provides the introspection data that numberings and
+/// references require, so it must stem from the same world.
+#[tracing::instrument(skip_all)]
+pub fn html(world: &dyn World, document: &Document) -> SourceResult<String> {
+    let body = html_body(world, document, ImageMode::Embedded)?;
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n");
+    writeln!(html, "<html lang=\"{}\">", body.lang.as_str()).unwrap();
+    html.push_str("<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n",
+    );
+    if let Some(title) = &document.title {
+        writeln!(html, "<title>{}</title>", escape(title)).unwrap();
+    }
+    if !document.author.is_empty() {
+        let author = document.author.join(", ");
+        writeln!(html, "<meta name=\"author\" content=\"{}\">", escape(&author)).unwrap();
+    }
+    if !document.keywords.is_empty() {
+        let keywords = document.keywords.join(", ");
+        writeln!(html, "<meta name=\"keywords\" content=\"{}\">", escape(&keywords))
+            .unwrap();
+    }
+    html.push_str("</head>\n<body>\n");
+    html.push_str(body.html());
+
+    if !body.footnotes.is_empty() {
+        html.push_str("<section class=\"footnotes\" role=\"doc-endnotes\">\n");
+        for note in &body.footnotes {
+            html.push_str(&note.html());
+        }
+        html.push_str("</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Write the body of a document into HTML, without assembling it into a file.
+///
+/// This is the basis for formats that package HTML, like e-books. The output
+/// is well-formed XML, so that it can be used as XHTML, too.
+#[tracing::instrument(skip_all)]
+pub fn html_body(
+    world: &dyn World,
+    document: &Document,
+    images: ImageMode,
+) -> SourceResult<HtmlBody> {
+    let world = world.track();
+    let mut tracer = Tracer::new();
+    let module = typst::eval::eval(
+        world,
+        Route::default().track(),
+        tracer.track_mut(),
+        &world.main(),
+    )?;
+
+    let library = world.library();
+    let styles = StyleChain::new(&library.styles);
+    let mut locator = Locator::new();
+    let engine = Engine {
+        world,
+        introspector: document.introspector.track(),
+        route: Route::default(),
+        locator: &mut locator,
+        tracer: tracer.track_mut(),
+    };
+
+    let mut writer = HtmlWriter {
+        engine,
+        buf: Buffer::default(),
+        nested: 0,
+        headings: vec![],
+        visits: HashMap::new(),
+        footnotes: vec![],
+        footnote_labels: HashMap::new(),
+        image_mode: images,
+        images: vec![],
+    };
+    writer.content(&module.content(), styles)?;
+    writer.flush();
+
+    Ok(HtmlBody {
+        lang: TextElem::lang_in(styles),
+        html: writer.buf.out,
+        headings: writer.headings,
+        footnotes: writer.footnotes,
+        images: writer.images,
+    })
+}
+
+/// How images are referenced from the HTML.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ImageMode {
+    /// Images are embedded as data URLs.
+    Embedded,
+    /// Images are referenced by a relative path and collected in
+    /// [`HtmlBody::images`].
+    External,
+}
+
+/// The body of a document written into HTML.
+pub struct HtmlBody {
+    /// The document's language.
+    pub lang: Lang,
+    /// The HTML of the body.
+    html: String,
+    /// The offsets and levels of top-level headings in the HTML.
+    headings: Vec<(usize, NonZeroUsize)>,
+    /// The footnotes, in the order of their first reference.
+    pub footnotes: Vec<Footnote>,
+    /// The images referenced by path. Only used with [`ImageMode::External`].
+    pub images: Vec<HtmlImage>,
+}
+
+impl HtmlBody {
+    /// The HTML of the whole body.
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    /// Split the body into sections that each start with a heading of at most
+    /// the given level.
+    ///
+    /// The first section holds everything before the first such heading and
+    /// may be empty.
+    pub fn sections(&self, level: NonZeroUsize) -> Vec<&str> {
+        let mut sections = vec![];
+        let mut start = 0;
+        for &(offset, _) in self.headings.iter().filter(|(_, l)| *l <= level) {
+            sections.push(&self.html[start..offset]);
+            start = offset;
+        }
+        sections.push(&self.html[start..]);
+        sections
+    }
+}
+
+/// A footnote, which is written at the end of the document.
+pub struct Footnote {
+    /// The ID of the note.
+    pub id: EcoString,
+    /// The ID of the (first) reference to the note.
+    pub ref_id: EcoString,
+    /// The displayed number of the note.
+    pub number: String,
+    /// The HTML of the note's body.
+    pub body: String,
+}
+
+impl Footnote {
+    /// The note as a paragraph, which links back to its reference.
+    pub fn html(&self) -> String {
+        format!(
+            "<p id=\"{}\"><a href=\"#{}\">{}</a> {}</p>\n",
+            self.id, self.ref_id, self.number, self.body,
+        )
+    }
+}
+
+/// An image that is referenced by path.
+pub struct HtmlImage {
+    /// The relative path under which the HTML references the image.
+    pub path: EcoString,
+    /// The image's data.
+    pub data: Bytes,
+    /// The media type of the data.
+    pub mime: &'static str,
+}
+
+/// Writes content into HTML.
+struct HtmlWriter<'a> {
+    /// The engine used to realize and lay out content.
+    engine: Engine<'a>,
+    /// The HTML written so far at the current level of nesting.
+    buf: Buffer,
+    /// How deeply nested the current buffer is.
+    nested: usize,
+    /// The offsets and levels of top-level headings in the output.
+    headings: Vec<(usize, NonZeroUsize)>,
+    /// How often an element with a given span was already visited, to find its
+    /// laid-out counterpart.
+    visits: HashMap<(Element, Span), usize>,
+    /// The footnotes, which are written at the end of the document.
+    footnotes: Vec<Footnote>,
+    /// The indices of labelled footnotes, for footnotes that refer to them.
+    footnote_labels: HashMap<Label, usize>,
+    /// How images are referenced.
+    image_mode: ImageMode,
+    /// The images referenced by path.
+    images: Vec<HtmlImage>,
+}
+
+/// The HTML written at one level of nesting.
+#[derive(Default)]
+struct Buffer {
+    /// The finished block-level HTML.
+    out: String,
+    /// The inline HTML of the current paragraph.
+    par: String,
+    /// The list that is currently open.
+    list: Option<ListKind>,
+    /// Whether paragraphs are written without `<p>` tags, e.g. in headings.
+    inline: bool,
+}
+
+/// A kind of list.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ListKind {
+    Bullet,
+    Enum,
+    Terms,
+}
+
+impl<'a> HtmlWriter<'a> {
+    /// Write arbitrary content.
+    fn content(&mut self, content: &Content, styles: StyleChain) -> SourceResult<()> {
+        if let Some(children) = content.to_sequence() {
+            self.label_anchor(content);
+            for child in children {
+                self.content(child, styles)?;
+            }
+            return Ok(());
+        }
+
+        if let Some((child, local)) = content.to_styled() {
+            self.label_anchor(content);
+            return self.content(child, styles.chain(local));
+        }
+
+        // Synthesize fields and apply the user's show rules, but keep the
+        // built-in elements we have a semantic counterpart for.
+        if content.needs_preparation() || has_recipe(content, styles) {
+            if let Some(realized) = realize(&mut self.engine, content, styles)? {
+                return self.realized(content, &realized, styles);
+            }
+        }
+
+        if let Some(elem) = content.to::<TextElem>() {
+            let text = escape(elem.text());
+            match content.label() {
+                Some(_) => self.inline(&format!("<span{}>{text}</span>", id(content))),
+                None => self.inline(&text),
+            }
+        } else if content.is::<SpaceElem>() {
+            self.space();
+        } else if content.is::<LinebreakElem>() {
+            self.inline("<br/>");
+        } else if content.is::<ParbreakElem>() {
+            self.flush_par();
+        } else if let Some(elem) = content.to::<SmartQuoteElem>() {
+            self.smart_quote(elem, styles);
+        } else if let Some(elem) = content.to::<StrongElem>() {
+            let body = self.render(elem.body(), styles, true)?;
+            self.inline(&format!("<strong{}>{body}</strong>", id(content)));
+        } else if let Some(elem) = content.to::<EmphElem>() {
+            let body = self.render(elem.body(), styles, true)?;
+            self.inline(&format!("<em{}>{body}</em>", id(content)));
+        } else if let Some(elem) = content.to::<LinkElem>() {
+            self.link(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<HeadingElem>() {
+            self.heading(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<ListItem>() {
+            let body = self.render(elem.body(), styles, true)?;
+            self.item(ListKind::Bullet, 1, &format!("<li{}>{body}</li>", id(content)));
+        } else if let Some(elem) = content.to::<EnumItem>() {
+            let body = self.render(elem.body(), styles, true)?;
+            let value = match elem.number(styles) {
+                Some(number) => format!(" value=\"{number}\""),
+                None => String::new(),
+            };
+            let start = EnumElem::start_in(styles);
+            let id = id(content);
+            self.item(ListKind::Enum, start, &format!("<li{id}{value}>{body}</li>"));
+        } else if let Some(elem) = content.to::<TermItem>() {
+            let term = self.render(elem.term(), styles, true)?;
+            let description = self.render(elem.description(), styles, true)?;
+            self.item(
+                ListKind::Terms,
+                1,
+                &format!("<dt{}>{term}</dt>\n<dd>{description}</dd>", id(content)),
+            );
+        } else if let Some(elem) = content.to::<ListElem>() {
+            self.list(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<EnumElem>() {
+            self.enum_(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<TermsElem>() {
+            self.terms(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<TableElem>() {
+            self.table(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<FigureElem>() {
+            self.figure(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<FootnoteElem>() {
+            self.footnote(content, elem, styles)?;
+        } else if let Some(elem) = content.to::<RawElem>() {
+            self.raw(content, elem, styles);
+        } else if content.is::<QuoteElem>() {
+            self.quote(content, styles)?;
+        } else if let Some(elem) = content.to::<OutlineEntry>() {
+            self.outline_entry(elem, styles)?;
+        } else if content.is::<EquationElem>() {
+            self.frame(content, styles)?;
+        } else if let Some(elem) = content.to::<ImageElem>() {
+            self.image(content, elem, styles)?;
+        } else if content.is::<MetaElem>()
+            || content.is::<HElem>()
+            || content.is::<VElem>()
+            || content.is::<ColbreakElem>()
+            || content.is::<PagebreakElem>()
+        {
+            // Spacing and breaks have no meaning in reflowable output.
+            self.label_anchor(content);
+        } else if content.can::<dyn Show>() {
+            match realize(&mut self.engine, content, styles)? {
+                Some(realized) => self.realized(content, &realized, styles)?,
+                None => self.label_anchor(content),
+            }
+        } else if let Some(elem) = content.to::<PageElem>() {
+            self.label_anchor(content);
+            self.content(elem.body(), styles)?;
+        } else if content.can::<dyn Layout>() {
+            self.frame(content, styles)?;
+        } else if content.can::<dyn PlainText>() {
+            let text = escape(&content.plain_text());
+            self.inline(&format!("<span{}>{text}</span>", id(content)));
+        } else {
+            self.label_anchor(content);
+        }
+
+        Ok(())
+    }
+
+    /// Write the realized form of an element.
+    ///
+    /// Show rules produce new content, which doesn't carry the shown element's
+    /// label. If the label is lost that way, it is kept as an anchor.
+    fn realized(
+        &mut self,
+        content: &Content,
+        realized: &Content,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let kept = content
+            .label()
+            .map_or(true, |label| realized.query_first(Selector::Label(label)).is_some());
+        if !kept {
+            self.label_anchor(content);
+        }
+        self.content(realized, styles)
+    }
+
+    /// Write an empty element with the content's label as its ID, so that
+    /// links to the label lead somewhere even though the content has no HTML
+    /// counterpart that could carry the ID.
+    fn label_anchor(&mut self, content: &Content) {
+        if content.label().is_some() {
+            self.inline(&format!("<span{}></span>", id(content)));
+        }
+    }
+
+    /// Write content into a fresh buffer and return the resulting HTML.
+    ///
+    /// In inline mode, paragraphs are not wrapped in `<p>` tags.
+    fn render(
+        &mut self,
+        content: &Content,
+        styles: StyleChain,
+        inline: bool,
+    ) -> SourceResult<String> {
+        let prev = mem::replace(&mut self.buf, Buffer { inline, ..Buffer::default() });
+        self.nested += 1;
+        self.content(content, styles)?;
+        self.flush();
+        self.nested -= 1;
+        Ok(mem::replace(&mut self.buf, prev).out)
+    }
+
+    /// Write inline HTML into the current paragraph.
+    fn inline(&mut self, html: &str) {
+        self.close_list();
+        self.buf.par.push_str(html);
+    }
+
+    /// Write a space between two pieces of inline content.
+    fn space(&mut self) {
+        if self.buf.list.is_none()
+            && !self.buf.par.is_empty()
+            && !self.buf.par.ends_with(' ')
+        {
+            self.buf.par.push(' ');
+        }
+    }
+
+    /// Write block-level HTML.
+    fn block(&mut self, html: &str) {
+        self.flush_par();
+        self.close_list();
+        self.buf.out.push_str(html);
+        self.buf.out.push('\n');
+    }
+
+    /// Write an item of a list, opening the list if necessary.
+    fn item(&mut self, kind: ListKind, start: usize, html: &str) {
+        self.flush_par();
+        if self.buf.list != Some(kind) {
+            self.close_list();
+            self.buf.out.push_str(&open_list(kind, start, ""));
+            self.buf.list = Some(kind);
+        }
+        self.buf.out.push_str(html);
+        self.buf.out.push('\n');
+    }
+
+    /// Finish the current paragraph.
+    fn flush_par(&mut self) {
+        let par = mem::take(&mut self.buf.par);
+        let par = par.trim_end();
+        if par.is_empty() {
+            return;
+        }
+
+        if self.buf.inline {
+            if !self.buf.out.is_empty() {
+                self.buf.out.push_str("<br/>");
+            }
+            self.buf.out.push_str(par);
+        } else {
+            writeln!(self.buf.out, "<p>{par}</p>").unwrap();
+        }
+    }
+
+    /// Close the currently open list, if any.
+    fn close_list(&mut self) {
+        if let Some(kind) = self.buf.list.take() {
+            self.buf.out.push_str(close_list(kind));
+        }
+    }
+
+    /// Finish everything that is still open.
+    fn flush(&mut self) {
+        self.flush_par();
+        self.close_list();
+    }
+
+    /// Find the laid-out counterpart of an element, which knows its location
+    /// in the document.
+    ///
+    /// Elements are matched by their span and the order in which elements
+    /// with the same span are visited.
+    fn locate(&mut self, content: &Content) -> Option<Content> {
+        let func = content.func();
+        let span = content.span();
+        let visits = self.visits.entry((func, span)).or_default();
+        let index = *visits;
+        *visits += 1;
+
+        self.engine
+            .introspector
+            .query(&Selector::Elem(func, None))
+            .iter()
+            .filter(|elem| elem.span() == span)
+            .nth(index)
+            .map(|elem| Content::clone(elem))
+    }
+
+    /// Display the counter of a laid-out element with a numbering.
+    fn number(
+        &mut self,
+        located: &Content,
+        numbering: &Numbering,
+        styles: StyleChain,
+    ) -> SourceResult<Option<String>> {
+        let Some(location) = located.location() else { return Ok(None) };
+        let counter = Counter::of(located.func());
+        let state = counter.at(&mut self.engine, location)?;
+        let number = state.display(&mut self.engine, numbering)?;
+        self.render(&number, styles, true).map(Some)
+    }
+
+    /// Write a smart quote.
+    fn smart_quote(&mut self, elem: &SmartQuoteElem, styles: StyleChain) {
+        let double = elem.double(styles);
+        if !elem.enabled(styles) {
+            self.inline(if double { "&quot;" } else { "&#39;" });
+            return;
+        }
+
+        let opening = self
+            .buf
+            .par
+            .chars()
+            .last()
+            .map_or(true, |c| c.is_whitespace() || matches!(c, '(' | '[' | '{'));
+        self.inline(match (double, opening) {
+            (true, true) => "“",
+            (true, false) => "”",
+            (false, true) => "‘",
+            (false, false) => "’",
+        });
+    }
+
+    /// Write a link.
+    fn link(
+        &mut self,
+        content: &Content,
+        elem: &LinkElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let body = self.render(elem.body(), styles, true)?;
+        let id = id(content);
+        match self.href(elem.dest()) {
+            Some(href) => {
+                self.inline(&format!("<a{id} href=\"{}\">{body}</a>", escape(&href)))
+            }
+            None if id.is_empty() => self.inline(&body),
+            None => self.inline(&format!("<span{id}>{body}</span>")),
+        }
+        Ok(())
+    }
+
+    /// Determine the target of a link, if it has one in HTML.
+    fn href(&mut self, target: &LinkTarget) -> Option<EcoString> {
+        let id = match target {
+            LinkTarget::Dest(Destination::Url(url)) => return Some(url.clone()),
+            LinkTarget::Dest(Destination::Position(_)) => return None,
+            LinkTarget::Dest(Destination::Location(location)) => anchor(
+                &self.engine.introspector.query_first(&Selector::Location(*location))?,
+            )?,
+            LinkTarget::Label(label) => label.as_str().into(),
+        };
+        Some(EcoString::from("#") + id)
+    }
+
+    /// Write a heading, including its number.
+    fn heading(
+        &mut self,
+        content: &Content,
+        elem: &HeadingElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let level = elem.level(styles);
+        let mut body = self.render(elem.body(), styles, true)?;
+
+        let located = self.locate(content);
+        if let Some(located) = &located {
+            if let Some(numbering) = located
+                .to::<HeadingElem>()
+                .and_then(|heading| heading.numbering(styles).clone())
+            {
+                if let Some(number) = self.number(located, &numbering, styles)? {
+                    body = format!("<span class=\"number\">{number}</span> {body}");
+                }
+            }
+        }
+
+        // Remember where top-level headings start, so that the output can be
+        // split into sections.
+        self.flush();
+        if self.nested == 0 {
+            self.headings.push((self.buf.out.len(), level));
+        }
+
+        let tag = level.get().min(6);
+        let id = located_id(content, located.as_ref());
+        self.block(&format!("<h{tag}{id}>{body}</h{tag}>"));
+        Ok(())
+    }
+
+    /// Write an explicitly constructed bullet list.
+    fn list(
+        &mut self,
+        content: &Content,
+        elem: &ListElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let mut html = open_list(ListKind::Bullet, 1, &id(content));
+        for item in elem.children() {
+            let body = self.render(item.body(), styles, true)?;
+            writeln!(html, "<li>{body}</li>").unwrap();
+        }
+        html.push_str(close_list(ListKind::Bullet).trim_end());
+        self.block(&html);
+        Ok(())
+    }
+
+    /// Write an explicitly constructed numbered list.
+    fn enum_(
+        &mut self,
+        content: &Content,
+        elem: &EnumElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let mut html = open_list(ListKind::Enum, elem.start(styles), &id(content));
+        for item in elem.children() {
+            let body = self.render(item.body(), styles, true)?;
+            match item.number(styles) {
+                Some(number) => writeln!(html, "<li value=\"{number}\">{body}</li>"),
+                None => writeln!(html, "<li>{body}</li>"),
+            }
+            .unwrap();
+        }
+        html.push_str(close_list(ListKind::Enum).trim_end());
+        self.block(&html);
+        Ok(())
+    }
+
+    /// Write an explicitly constructed term list.
+    fn terms(
+        &mut self,
+        content: &Content,
+        elem: &TermsElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let mut html = open_list(ListKind::Terms, 1, &id(content));
+        for item in elem.children() {
+            let term = self.render(item.term(), styles, true)?;
+            let description = self.render(item.description(), styles, true)?;
+            writeln!(html, "<dt>{term}</dt>\n<dd>{description}</dd>").unwrap();
+        }
+        html.push_str(close_list(ListKind::Terms).trim_end());
+        self.block(&html);
+        Ok(())
+    }
+
+    /// Write a table with the rows and header cells that table layout assigns
+    /// to its cells.
+    fn table(
+        &mut self,
+        content: &Content,
+        elem: &TableElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let mut rows: Vec<Vec<(bool, String)>> = vec![];
+        for (cell, child) in elem.cells(styles) {
+            let y = *cell.y();
+            if rows.len() <= y {
+                rows.resize_with(y + 1, Vec::new);
+            }
+            let body = self.render(child, styles, true)?;
+            rows[y].push((*cell.header(), body));
+        }
+
+        // Leading rows of header cells form the table's head.
+        let head = rows
+            .iter()
+            .take_while(|row| row.iter().all(|&(header, _)| header))
+            .count();
+
+        let mut html = format!("<table{}>\n", id(content));
+        for (y, row) in rows.iter().enumerate() {
+            if y == 0 && head > 0 {
+                html.push_str("<thead>\n");
+            } else if y == head {
+                if head > 0 {
+                    html.push_str("</thead>\n");
+                }
+                html.push_str("<tbody>\n");
+            }
+
+            html.push_str("<tr>");
+            for (header, body) in row {
+                if *header {
+                    write!(html, "<th scope=\"col\">{body}</th>").unwrap();
+                } else {
+                    write!(html, "<td>{body}</td>").unwrap();
+                }
+            }
+            html.push_str("</tr>\n");
+        }
+
+        match (head, rows.len()) {
+            (0, 0) => {}
+            (head, len) if head == len => html.push_str("</thead>\n"),
+            _ => html.push_str("</tbody>\n"),
+        }
+
+        html.push_str("</table>");
+        self.block(&html);
+        Ok(())
+    }
+
+    /// Write a figure and its caption.
+    fn figure(
+        &mut self,
+        content: &Content,
+        elem: &FigureElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let body = self.render(elem.body(), styles, false)?;
+
+        // The laid-out figure's caption knows the figure's number.
+        let located = self.locate(content);
+        let caption = located
+            .as_ref()
+            .and_then(|located| located.to::<FigureElem>())
+            .unwrap_or(elem)
+            .caption(styles);
+
+        let mut html = format!("<figure{}>\n", located_id(content, located.as_ref()));
+        let caption = match caption {
+            Some(caption) => {
+                let top = caption.position(styles) == VAlign::Top;
+                let rendered = self.render(&caption.pack(), styles, true)?;
+                Some((top, format!("<figcaption>{rendered}</figcaption>\n")))
+            }
+            None => None,
+        };
+
+        if let Some((true, caption)) = &caption {
+            html.push_str(caption);
+        }
+        html.push_str(&body);
+        if let Some((false, caption)) = &caption {
+            html.push_str(caption);
+        }
+
+        html.push_str("</figure>");
+        self.block(&html);
+        Ok(())
+    }
+
+    /// Write a reference to a footnote and remember the note for the end of
+    /// the document.
+    ///
+    /// A footnote that refers to a labelled note further down in the document
+    /// remembers that note right away, so that the reference has a target.
+    fn footnote(
+        &mut self,
+        content: &Content,
+        elem: &FootnoteElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let located = self.locate(content);
+        let known = match elem.body() {
+            FootnoteBody::Reference(label) => Some(*label),
+            FootnoteBody::Content(_) => content.label(),
+        }
+        .and_then(|label| self.footnote_labels.get(&label).copied());
+
+        let (index, first) = match (known, elem.body()) {
+            (Some(index), _) => (index, false),
+            (None, FootnoteBody::Content(_)) => {
+                (self.note(content, elem, located.as_ref(), styles)?, true)
+            }
+            (None, FootnoteBody::Reference(label)) => {
+                let location = elem.declaration_location(&self.engine).at(elem.span())?;
+                let Some(declaration) =
+                    self.engine.introspector.query_first(&Selector::Location(location))
+                else {
+                    return Ok(());
+                };
+                let Some(note) = declaration.to::<FootnoteElem>() else {
+                    return Ok(());
+                };
+                let index = self.note(&declaration, note, Some(&declaration), styles)?;
+                self.footnote_labels.insert(*label, index);
+                (index, true)
+            }
+        };
+
+        let note = &self.footnotes[index];
+        let anchor =
+            if first { format!(" id=\"{}\"", note.ref_id) } else { String::new() };
+
+        let html = format!(
+            "<sup class=\"footnote-ref\"><a{anchor} href=\"#{}\" role=\"doc-noteref\">{}</a></sup>",
+            note.id, note.number,
+        );
+        self.inline(&html);
+        Ok(())
+    }
+
+    /// Remember the body of a footnote for the end of the document and return
+    /// the note's index.
+    fn note(
+        &mut self,
+        content: &Content,
+        elem: &FootnoteElem,
+        located: Option<&Content>,
+        styles: StyleChain,
+    ) -> SourceResult<usize> {
+        let index = self.footnotes.len();
+        let number = match located {
+            Some(located) => {
+                let numbering = elem.numbering(styles);
+                self.number(located, &numbering, styles)?
+            }
+            None => None,
+        };
+
+        let body = match elem.body_content() {
+            Some(body) => self.render(body, styles, true)?,
+            None => String::new(),
+        };
+
+        let id = match content.label() {
+            Some(label) => {
+                self.footnote_labels.insert(label, index);
+                label.as_str().into()
+            }
+            None => eco_format!("fn-{}", index + 1),
+        };
+
+        self.footnotes.push(Footnote {
+            id,
+            ref_id: eco_format!("fnref-{}", index + 1),
+            number: number.unwrap_or_else(|| (index + 1).to_string()),
+            body,
+        });
+        Ok(index)
+    }
+
+    /// Write raw text with its syntax highlighting.
+    fn raw(&mut self, content: &Content, elem: &RawElem, styles: StyleChain) {
+        let mut code = String::new();
+        for (i, line) in elem.lines().iter().enumerate() {
+            if i > 0 {
+                code.push('\n');
+            }
+            highlighted(line.body(), styles, styles, &mut code);
+        }
+
+        let class = match elem.lang(styles) {
+            Some(lang) => format!(" class=\"language-{}\"", escape(lang)),
+            None => String::new(),
+        };
+
+        if elem.block(styles) {
+            self.block(&format!("<pre{}><code{class}>{code}</code></pre>", id(content)));
+        } else {
+            self.inline(&format!("<code{}{class}>{code}</code>", id(content)));
+        }
+    }
+
+    /// Write a quote, which is a block quote if it is a block.
+    fn quote(&mut self, content: &Content, styles: StyleChain) -> SourceResult<()> {
+        let block = matches!(content.get_by_name("block"), Some(Value::Bool(true)));
+        let Some(Value::Content(body)) = content.get_by_name("body") else {
+            return Ok(());
+        };
+
+        if !block {
+            let body = self.render(&body, styles, true)?;
+            self.inline(&format!("<q{}>{body}</q>", id(content)));
+            return Ok(());
+        }
+
+        let mut html = format!("<blockquote{}>\n", id(content));
+        html.push_str(&self.render(&body, styles, false)?);
+        if let Some(Value::Content(attribution)) = content.get_by_name("attribution") {
+            let attribution = self.render(&attribution, styles, true)?;
+            writeln!(html, "<footer>— {attribution}</footer>").unwrap();
+        }
+        html.push_str("</blockquote>");
+        self.block(&html);
+        Ok(())
+    }
+
+    /// Write an entry of an outline as a link to its element.
+    ///
+    /// The page number is left out since the output has no pages.
+    fn outline_entry(
+        &mut self,
+        elem: &OutlineEntry,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let body = self.render(elem.body(), styles, true)?;
+        let html = match anchor(elem.element()) {
+            Some(id) => format!(
+                "<p class=\"outline-entry\"><a href=\"#{}\">{body}</a></p>",
+                escape(&id),
+            ),
+            None => format!("<p class=\"outline-entry\">{body}</p>"),
+        };
+        self.block(&html);
+        Ok(())
+    }
+
+    /// Write an image with its original data.
+    ///
+    /// Images in formats that browsers can't display are laid out like other
+    /// content instead.
+    fn image(
+        &mut self,
+        content: &Content,
+        elem: &ImageElem,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let format = elem.determine_format(styles)?;
+        let Some((extension, mime)) = image_type(elem, format) else {
+            return self.frame(content, styles);
+        };
+
+        let data: Bytes = elem.data().clone().into();
+        let src = match self.image_mode {
+            ImageMode::Embedded => {
+                eco_format!("data:{mime};base64,{}", STANDARD.encode(&data))
+            }
+            ImageMode::External => {
+                match self.images.iter().find(|image| image.data == data) {
+                    Some(image) => image.path.clone(),
+                    None => {
+                        let path = eco_format!(
+                            "images/image-{}.{extension}",
+                            self.images.len() + 1
+                        );
+                        self.images.push(HtmlImage { path: path.clone(), data, mime });
+                        path
+                    }
+                }
+            }
+        };
+
+        let alt = elem.alt(styles).unwrap_or_default();
+        self.block(&format!(
+            "<div class=\"typst-image\"{}><img src=\"{}\" alt=\"{}\"/></div>",
+            id(content),
+            escape(&src),
+            escape(&alt),
+        ));
+        Ok(())
+    }
+
+    /// Lay out content and embed it as inline SVG.
+    ///
+    /// Boxes and inline equations flow with the surrounding text, while
+    /// everything else becomes a block of its own.
+    fn frame(&mut self, content: &Content, styles: StyleChain) -> SourceResult<()> {
+        let inline = content.is::<BoxElem>()
+            || content.to::<EquationElem>().is_some_and(|elem| !elem.block(styles));
+
+        let size = Size::new(region_width(styles), Abs::inf());
+        let regions = Regions::one(size, Axes::splat(false));
+        let frame = content.layout(&mut self.engine, styles, regions)?.into_frame();
+        if frame.is_empty() {
+            self.label_anchor(content);
+            return Ok(());
+        }
+
+        let svg = typst_svg::svg(&frame);
+        if inline {
+            self.inline(&format!(
+                "<span class=\"typst-frame\"{}>{svg}</span>",
+                id(content)
+            ));
+        } else {
+            self.block(&format!("<div class=\"typst-frame\"{}>{svg}</div>", id(content)));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether one of the user's show rules applies to the content.
+fn has_recipe(content: &Content, styles: StyleChain) -> bool {
+    let mut n = styles.recipes().count();
+    for recipe in styles.recipes() {
+        if recipe.applicable(content) && !content.is_guarded(Guard::Nth(n)) {
+            return true;
+        }
+        n -= 1;
+    }
+    false
+}
+
+/// Write the lines of raw text, coloring pieces that the syntax highlighting
+/// styled differently from the surrounding text.
+fn highlighted(
+    content: &Content,
+    styles: StyleChain,
+    base: StyleChain,
+    out: &mut String,
+) {
+    if let Some(children) = content.to_sequence() {
+        for child in children {
+            highlighted(child, styles, base, out);
+        }
+        return;
+    }
+
+    if let Some((child, local)) = content.to_styled() {
+        highlighted(child, styles.chain(local), base, out);
+        return;
+    }
+
+    let text = escape(&content.plain_text());
+    let mut css = String::new();
+    if content.is::<TextElem>() {
+        let fill = TextElem::fill_in(styles);
+        if fill != TextElem::fill_in(base) {
+            if let Paint::Solid(color) = fill {
+                write!(css, "color:{};", color.to_hex()).unwrap();
+            }
+        }
+        if TextElem::weight_in(styles) > TextElem::weight_in(base) {
+            css.push_str("font-weight:bold;");
+        }
+        if TextElem::style_in(styles) != FontStyle::Normal
+            && TextElem::style_in(base) == FontStyle::Normal
+        {
+            css.push_str("font-style:italic;");
+        }
+    }
+
+    if css.is_empty() {
+        out.push_str(&text);
+    } else {
+        write!(out, "<span style=\"{}\">{text}</span>", css.trim_end_matches(';'))
+            .unwrap();
+    }
+}
+
+/// The file extension and media type of an image that browsers can display.
+fn image_type(
+    elem: &ImageElem,
+    format: ImageFormat,
+) -> Option<(&'static str, &'static str)> {
+    Some(match format {
+        ImageFormat::Raster(RasterFormat::Png) => ("png", "image/png"),
+        ImageFormat::Raster(RasterFormat::Jpg) => ("jpg", "image/jpeg"),
+        ImageFormat::Raster(RasterFormat::Gif) => ("gif", "image/gif"),
+        ImageFormat::Vector(VectorFormat::Svg) => {
+            // Compressed SVGs can't be served as they are.
+            let data = elem.data();
+            if matches!(data, Readable::Bytes(bytes) if bytes.starts_with(&[0x1f, 0x8b]))
+            {
+                return None;
+            }
+            ("svg", "image/svg+xml")
+        }
+        // Browsers don't display PDFs in images.
+        ImageFormat::Vector(VectorFormat::Pdf) => return None,
+    })
+}
+
+/// The width available to content on a page with the given styles.
+fn region_width(styles: StyleChain) -> Abs {
+    let width = PageElem::width_in(styles).unwrap_or(Abs::inf());
+    let height = PageElem::height_in(styles).unwrap_or(Abs::inf());
+
+    let mut min = width.min(height);
+    if !min.is_finite() {
+        min = Paper::A4.width();
+    }
+
+    let default = Rel::<Length>::from((2.5 / 21.0) * min);
+    let margin = PageElem::margin_in(styles)
+        .sides
+        .map(|side| side.and_then(Smart::as_custom).unwrap_or(default))
+        .resolve(styles)
+        .relative_to(Size::new(width, height));
+
+    width - margin.sum_by_axis().x
+}
+
+/// The opening tag of a list.
+fn open_list(kind: ListKind, start: usize, id: &str) -> String {
+    match kind {
+        ListKind::Bullet => format!("<ul{id}>\n"),
+        ListKind::Enum if start != 1 => format!("<ol{id} start=\"{start}\">\n"),
+        ListKind::Enum => format!("<ol{id}>\n"),
+        ListKind::Terms => format!("<dl{id}>\n"),
+    }
+}
+
+/// The closing tag of a list.
+fn close_list(kind: ListKind) -> &'static str {
+    match kind {
+        ListKind::Bullet => "</ul>\n",
+        ListKind::Enum => "</ol>\n",
+        ListKind::Terms => "</dl>\n",
+    }
+}
+
+/// The `id` attribute of an element, derived from its label.
+fn id(content: &Content) -> String {
+    match content.label() {
+        Some(label) => format!(" id=\"{}\"", escape(label.as_str())),
+        None => String::new(),
+    }
+}
+
+/// The `id` attribute of an element that may have a laid-out counterpart.
+fn located_id(content: &Content, located: Option<&Content>) -> String {
+    match located.and_then(anchor) {
+        Some(id) => format!(" id=\"{}\"", escape(&id)),
+        None => id(content),
+    }
+}
+
+/// The ID under which a laid-out element can be linked to.
+///
+/// This is the element's label or, for headings and figures without one, an ID
+/// derived from the element's location.
+pub fn anchor(elem: &Content) -> Option<EcoString> {
+    if let Some(label) = elem.label() {
+        return Some(label.as_str().into());
+    }
+
+    if !elem.is::<HeadingElem>() && !elem.is::<FigureElem>() {
+        return None;
+    }
+
+    let location = elem.location()?;
+    Some(eco_format!("loc-{:032x}", hash128(&location)))
+}
+
+/// Escape text for use in HTML content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+use ecow::EcoString;
+use pdf_writer::writers::Destination;
+use pdf_writer::Ref;
+use typst::layout::{Abs, Point};
+
+use crate::{AbsExt, PdfContext};
+
+/// Write a named destination for each labelled element.
+///
+/// The destinations are named like the labels, so that other documents and
+/// web pages can link to `file.pdf#label`. If several elements have the same
+/// label, the first one is the target. Returns the references of the
+/// destinations, keyed and sorted by their names, so that the catalog can
+/// list them.
+#[tracing::instrument(skip_all)]
+pub(crate) fn write_named_destinations(ctx: &mut PdfContext) -> BTreeMap<EcoString, Ref> {
+    let mut dests = BTreeMap::new();
+    for elem in ctx.document.introspector.all() {
+        let (Some(label), Some(loc)) = (elem.label(), elem.location()) else {
+            continue;
+        };
+
+        let name = EcoString::from(label.as_str());
+        if dests.contains_key(&name) {
+            continue;
+        }
+
+        // Elements on pages that were not exported have no destination.
+        let pos = ctx.document.introspector.position(loc);
+        let Some(Some(page)) = ctx.pages.get(pos.page.get() - 1) else { continue };
+
+        let dest_ref = ctx.alloc.bump();
+        let (x, y) = xyz(pos.point, page.size.y);
+        ctx.pdf
+            .indirect(dest_ref)
+            .start::<Destination>()
+            .page(page.id)
+            .xyz(x, y, None);
+
+        dests.insert(name, dest_ref);
+    }
+
+    dests
+}
+
+/// The coordinates of an XYZ destination that shows a point on a page.
+///
+/// PDF coordinates start at the bottom of the page. The view starts a bit
+/// above the point, so that the target is not glued to the window's edge.
+pub(crate) fn xyz(point: Point, page_height: Abs) -> (f32, f32) {
+    let y = (point.y - Abs::pt(10.0)).max(Abs::zero());
+    (point.x.to_f32(), (page_height - y).to_f32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xyz() {
+        let height = Abs::pt(800.0);
+        let point = Point::new(Abs::pt(50.0), Abs::pt(100.0));
+        assert_eq!(xyz(point, height), (50.0, 710.0));
+    }
+
+    #[test]
+    fn test_xyz_top_of_page() {
+        let height = Abs::pt(800.0);
+        let point = Point::new(Abs::pt(20.0), Abs::pt(4.0));
+        assert_eq!(xyz(point, height), (20.0, 800.0));
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use ecow::EcoString;
+use pdf_writer::{Filter, Finish, Name, Ref, Str, TextStr};
+use typst::diag::{bail, SourceResult};
+use typst::foundations::{NativeElement, Repr, Smart, StyleChain};
+use typst::model::pdf::{EmbedElem, EmbedRelationship};
+
+use crate::{deflate, PdfContext};
+
+/// Write the files embedded with `pdf.embed`.
+///
+/// Returns the references of their file specifications, keyed and sorted by
+/// the attachments' names, so that the catalog can list them.
+#[tracing::instrument(skip_all)]
+pub(crate) fn write_embedded_files(
+    ctx: &mut PdfContext,
+) -> SourceResult<BTreeMap<EcoString, Ref>> {
+    let mut files = BTreeMap::new();
+    for elem in ctx.document.introspector.query(&EmbedElem::elem().select()) {
+        let embed = elem.to::<EmbedElem>().unwrap();
+        let name = embed.name();
+        if files.contains_key(name) {
+            bail!(
+                embed.span(), "a file named {} is already embedded", name.repr();
+                hint: "attachments are identified by the last component of their path"
+            );
+        }
+
+        let file_ref = ctx.alloc.bump();
+        let spec_ref = ctx.alloc.bump();
+        let styles = StyleChain::default();
+
+        let data = deflate(embed.data());
+        let mut file = ctx.pdf.stream(file_ref, &data);
+        file.filter(Filter::FlateDecode);
+        file.pair(Name(b"Type"), Name(b"EmbeddedFile"));
+        if let Some(mime_type) = embed.mime_type(styles) {
+            file.pair(Name(b"Subtype"), Name(mime_type.as_bytes()));
+        }
+        file.insert(Name(b"Params"))
+            .dict()
+            .pair(Name(b"Size"), embed.data().len() as i32);
+        file.finish();
+
+        let relationship = match embed.relationship(styles) {
+            Smart::Auto => Name(b"Unspecified"),
+            Smart::Custom(EmbedRelationship::Source) => Name(b"Source"),
+            Smart::Custom(EmbedRelationship::Data) => Name(b"Data"),
+            Smart::Custom(EmbedRelationship::Alternative) => Name(b"Alternative"),
+            Smart::Custom(EmbedRelationship::Supplement) => Name(b"Supplement"),
+        };
+
+        let mut spec = ctx.pdf.indirect(spec_ref).dict();
+        spec.pair(Name(b"Type"), Name(b"Filespec"));
+        spec.pair(Name(b"F"), Str(name.as_bytes()));
+        spec.pair(Name(b"UF"), TextStr(name));
+        if let Some(description) = embed.description(styles) {
+            spec.pair(Name(b"Desc"), TextStr(&description));
+        }
+        spec.pair(Name(b"AFRelationship"), relationship);
+        spec.insert(Name(b"EF"))
+            .dict()
+            .pair(Name(b"F"), file_ref)
+            .pair(Name(b"UF"), file_ref);
+        spec.finish();
+
+        files.insert(name.into(), spec_ref);
+    }
+
+    Ok(files)
+}
@@ -9,7 +9,7 @@ use typst::text::Font;
 use typst::util::SliceExt;
 use unicode_properties::{GeneralCategory, UnicodeGeneralCategory};
 
-use crate::{deflate, EmExt, PdfContext};
+use crate::{deflate, EmExt, PdfContext, PdfStandard};
 
 const CFF: Tag = Tag::from_bytes(b"CFF ");
 const CFF2: Tag = Tag::from_bytes(b"CFF2");
@@ -29,6 +29,7 @@ pub(crate) fn write_fonts(ctx: &mut PdfContext) {
         let descriptor_ref = ctx.alloc.bump();
         let cmap_ref = ctx.alloc.bump();
         let data_ref = ctx.alloc.bump();
+        let cid_set_ref = (ctx.standard == PdfStandard::A2b).then(|| ctx.alloc.bump());
         ctx.font_refs.push(type0_ref);
 
         let glyph_set = ctx.glyph_sets.get_mut(font).unwrap();
@@ -142,8 +143,21 @@ pub(crate) fn write_fonts(ctx: &mut PdfContext) {
             font_descriptor.font_file2(data_ref);
         }
 
+        // PDF/A-2 only requires the CID set to be complete if it is present,
+        // but some archival validators expect one, so we write it.
+        if let Some(cid_set_ref) = cid_set_ref {
+            font_descriptor.cid_set(cid_set_ref);
+        }
+
         font_descriptor.finish();
 
+        if let Some(cid_set_ref) = cid_set_ref {
+            let cid_set = create_cid_set(font, glyph_set);
+            ctx.pdf
+                .stream(cid_set_ref, &deflate(&cid_set))
+                .filter(Filter::FlateDecode);
+        }
+
         // Write the /ToUnicode character map, which maps glyph ids back to
         // unicode codepoints to enable copying out of the PDF.
         let cmap = create_cmap(ttf, glyph_set);
@@ -196,6 +210,23 @@ fn subset_tag(glyphs: &BTreeMap<u16, EcoString>) -> EcoString {
     std::str::from_utf8(&letter).unwrap().into()
 }
 
+/// Create a /CIDSet stream, which is a bit set of the CIDs present in the
+/// subsetted font program, most significant bit first.
+fn create_cid_set(font: &Font, glyph_set: &BTreeMap<u16, EcoString>) -> Vec<u8> {
+    // The `.notdef` glyph is always kept when subsetting.
+    let cids: Vec<u16> = std::iter::once(0)
+        .chain(glyph_set.keys().map(|&gid| glyph_cid(font, gid)))
+        .collect();
+
+    let max = cids.iter().copied().max().unwrap_or(0);
+    let mut set = vec![0; usize::from(max) / 8 + 1];
+    for cid in cids {
+        set[usize::from(cid / 8)] |= 0x80 >> (cid % 8);
+    }
+
+    set
+}
+
 /// Create a /ToUnicode CMap.
 fn create_cmap(
     ttf: &ttf_parser::Face,
@@ -266,3 +297,23 @@ pub(super) fn glyph_cid(font: &Font, glyph_id: u16) -> u16 {
         .and_then(|cff| cff.glyph_cid(ttf_parser::GlyphId(glyph_id)))
         .unwrap_or(glyph_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use typst::foundations::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_font_cid_set() {
+        let data = std::fs::read("../../assets/fonts/IBMPlexSans-Regular.ttf").unwrap();
+        let font = Font::new(Bytes::from(data), 0).unwrap();
+
+        // TrueType fonts use the glyph ids as CIDs.
+        let glyph_set = [(3, "a".into()), (10, "b".into())].into_iter().collect();
+        assert_eq!(create_cid_set(&font, &glyph_set), [0b1001_0000, 0b0010_0000]);
+
+        // The `.notdef` glyph is part of every set.
+        assert_eq!(create_cid_set(&font, &BTreeMap::new()), [0b1000_0000]);
+    }
+}
@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use std::mem::discriminant;
+
+use ecow::{eco_format, EcoString};
+use pdf_writer::types::AnnotationFlags;
+use pdf_writer::writers::Annotation;
+use pdf_writer::{Filter, Finish, Name, Rect, Ref, Str, TextStr};
+use typst::diag::{bail, SourceResult};
+use typst::foundations::Repr;
+use typst::layout::{Frame, Size};
+use typst::model::pdf::{FormField, FormFieldKind};
+use typst::text::{Font, FontFlags, FontStyle, FontWeight};
+
+use crate::page::{construct_page, write_resources, PageResource};
+use crate::{deflate_memoized, AbsExt, PdfContext};
+
+/// A widget annotation through which a form field is filled in.
+pub struct Widget {
+    /// The indirect object id of the annotation.
+    pub id: Ref,
+    /// The field the widget belongs to.
+    pub field: FormField,
+    /// The widget's area in the PDF coordinate system.
+    pub rect: Rect,
+    /// The widget's appearance. For checkboxes and radio buttons, this is the
+    /// checked one.
+    pub on: Appearance,
+    /// The appearance of an unchecked checkbox or radio button.
+    pub off: Option<Appearance>,
+    /// The key of the widget's entry in the structure tree's parent tree, if
+    /// the document is tagged.
+    pub struct_parent: Option<i32>,
+}
+
+/// An encoded appearance of a widget.
+pub struct Appearance {
+    /// The appearance's dimensions.
+    size: Size,
+    /// The appearance's content stream.
+    content: Vec<u8>,
+    /// The resources used by the appearance.
+    resources: Vec<(PageResource, usize)>,
+}
+
+/// Encode the appearances of a form field for a widget at the given area.
+pub(crate) fn construct_widget(
+    ctx: &mut PdfContext,
+    field: &FormField,
+    rect: Rect,
+) -> Widget {
+    let on = construct_appearance(ctx, &field.appearance);
+    let off = field.off.as_ref().map(|frame| construct_appearance(ctx, frame));
+    Widget {
+        id: ctx.alloc.bump(),
+        field: field.clone(),
+        rect,
+        on,
+        off,
+        struct_parent: None,
+    }
+}
+
+/// Encode a frame as the appearance of a widget.
+fn construct_appearance(ctx: &mut PdfContext, frame: &Frame) -> Appearance {
+    let (_, page) = construct_page(ctx, frame, false);
+    Appearance {
+        size: page.size,
+        content: page.content,
+        resources: page.resources.into_iter().collect(),
+    }
+}
+
+/// Write the interactive form with the fields of all exported pages.
+///
+/// Widgets with the same name are combined into one field, so they must agree
+/// on its value and text style. Returns the reference to the form if there are
+/// any fields.
+#[tracing::instrument(skip_all)]
+pub(crate) fn write_form(ctx: &mut PdfContext) -> SourceResult<Option<Ref>> {
+    let mut groups: Vec<Vec<(Ref, Widget)>> = vec![];
+    let mut indices: HashMap<EcoString, usize> = HashMap::new();
+    for page in ctx.pages.iter_mut().flatten() {
+        for widget in std::mem::take(&mut page.widgets) {
+            let Some(&i) = indices.get(&widget.field.name) else {
+                indices.insert(widget.field.name.clone(), groups.len());
+                groups.push(vec![(page.id, widget)]);
+                continue;
+            };
+
+            let first = &groups[i][0].1.field;
+            if discriminant(&first.kind) != discriminant(&widget.field.kind) {
+                bail!(
+                    widget.field.span,
+                    "a different kind of form field is already named {}",
+                    first.name.repr();
+                    hint: "only fields of the same kind can share a name"
+                );
+            }
+
+            if let Some(what) = conflict(first, &widget.field) {
+                bail!(
+                    widget.field.span,
+                    "another form field named {} has a different {what}",
+                    first.name.repr();
+                    hint: "fields with the same name share their value and text style"
+                );
+            }
+
+            groups[i].push((page.id, widget));
+        }
+    }
+
+    if groups.is_empty() {
+        return Ok(None);
+    }
+
+    let mut field_refs = vec![];
+    let mut fonts = vec![FieldFont::Standard(STANDARD_FONTS[0][0][0])];
+    for group in groups {
+        let field_ref = ctx.alloc.bump();
+        field_refs.push(field_ref);
+        let font = field_font(ctx, &group[0].1.field);
+        if !fonts.contains(&font) {
+            fonts.push(font);
+        }
+        let state = write_field(ctx, field_ref, &group, font)?;
+        for (page_ref, widget) in &group {
+            write_widget(ctx, field_ref, *page_ref, widget, state.as_ref());
+        }
+    }
+
+    let mut font_refs = vec![];
+    for &font in &fonts {
+        let font_ref = match font {
+            FieldFont::Standard((_, base_font)) => {
+                let font_ref = ctx.alloc.bump();
+                ctx.pdf
+                    .indirect(font_ref)
+                    .dict()
+                    .pair(Name(b"Type"), Name(b"Font"))
+                    .pair(Name(b"Subtype"), Name(b"Type1"))
+                    .pair(Name(b"BaseFont"), Name(base_font.as_bytes()))
+                    .pair(Name(b"Encoding"), Name(b"WinAnsiEncoding"));
+                font_ref
+            }
+            FieldFont::Embedded(i) => ctx.font_refs[i],
+        };
+        font_refs.push((font.name(), font_ref));
+    }
+
+    let form_ref = ctx.alloc.bump();
+    let mut form = ctx.pdf.indirect(form_ref).dict();
+    form.insert(Name(b"Fields")).array().items(field_refs);
+    let mut resources = form.insert(Name(b"DR")).dict();
+    let mut resource_fonts = resources.insert(Name(b"Font")).dict();
+    for (name, font_ref) in &font_refs {
+        resource_fonts.pair(Name(name.as_bytes()), *font_ref);
+    }
+    resource_fonts.finish();
+    resources.finish();
+    form.pair(Name(b"DA"), Str(b"/Helv 0 Tf 0 g"));
+    form.finish();
+
+    Ok(Some(form_ref))
+}
+
+/// Write the field that a group of widgets with the same name belongs to.
+///
+/// For checkboxes and radio buttons, returns the field's state.
+fn write_field(
+    ctx: &mut PdfContext,
+    field_ref: Ref,
+    group: &[(Ref, Widget)],
+    font: FieldFont,
+) -> SourceResult<Option<EcoString>> {
+    let first = &group[0].1.field;
+    let [r, g, b, _] = first.text_fill.to_rgb().to_vec4();
+    let appearance =
+        eco_format!("/{} {} Tf {r} {g} {b} rg", font.name(), first.text_size.to_f32());
+
+    let mut state = None;
+    let mut field = ctx.pdf.indirect(field_ref).dict();
+    field.pair(Name(b"T"), TextStr(&first.name));
+    field.pair(Name(b"DA"), Str(appearance.as_bytes()));
+
+    match &first.kind {
+        FormFieldKind::Text { value, multiline } => {
+            field.pair(Name(b"FT"), Name(b"Tx"));
+            if *multiline {
+                field.pair(Name(b"Ff"), 1 << 12);
+            }
+            field.pair(Name(b"V"), TextStr(value));
+            field.pair(Name(b"DV"), TextStr(value));
+        }
+        FormFieldKind::Checkbox { checked } => {
+            let on = if *checked { "On" } else { "Off" };
+            field.pair(Name(b"FT"), Name(b"Btn"));
+            field.pair(Name(b"V"), Name(on.as_bytes()));
+            field.pair(Name(b"DV"), Name(on.as_bytes()));
+            state = Some(on.into());
+        }
+        FormFieldKind::Radio { .. } => {
+            let mut selected: Option<&EcoString> = None;
+            for (_, widget) in group {
+                let FormFieldKind::Radio { value, checked: true } = &widget.field.kind
+                else {
+                    continue;
+                };
+
+                if selected.is_some_and(|other| other != value) {
+                    bail!(
+                        widget.field.span,
+                        "another radio button named {} is already checked",
+                        first.name.repr()
+                    );
+                }
+
+                selected = Some(value);
+            }
+
+            let on = selected.cloned().unwrap_or_else(|| "Off".into());
+
+            // Radio buttons cannot be unchecked by clicking them again.
+            field.pair(Name(b"FT"), Name(b"Btn"));
+            field.pair(Name(b"Ff"), (1 << 14) | (1 << 15));
+            field.pair(Name(b"V"), Name(on.as_bytes()));
+            field.pair(Name(b"DV"), Name(on.as_bytes()));
+            state = Some(on);
+        }
+        FormFieldKind::Dropdown { options, value } => {
+            field.pair(Name(b"FT"), Name(b"Ch"));
+            field.pair(Name(b"Ff"), 1 << 17);
+            field
+                .insert(Name(b"Opt"))
+                .array()
+                .items(options.iter().map(|option| TextStr(option.as_str())));
+            field.pair(Name(b"V"), TextStr(value));
+            field.pair(Name(b"DV"), TextStr(value));
+        }
+    }
+
+    field
+        .insert(Name(b"Kids"))
+        .array()
+        .items(group.iter().map(|(_, widget)| widget.id));
+    field.finish();
+
+    Ok(state)
+}
+
+/// How two fields with the same name differ in a way that they cannot share
+/// one field in the form, if they do.
+fn conflict(first: &FormField, other: &FormField) -> Option<&'static str> {
+    match (&first.kind, &other.kind) {
+        (
+            FormFieldKind::Text { value: a, multiline: m },
+            FormFieldKind::Text { value: b, multiline: n },
+        ) => {
+            if a != b {
+                return Some("value");
+            } else if m != n {
+                return Some("number of lines");
+            }
+        }
+        (
+            FormFieldKind::Dropdown { options: a, value: v },
+            FormFieldKind::Dropdown { options: b, value: w },
+        ) => {
+            if a != b {
+                return Some("list of options");
+            } else if v != w {
+                return Some("value");
+            }
+        }
+        (
+            FormFieldKind::Checkbox { checked: a },
+            FormFieldKind::Checkbox { checked: b },
+        ) => {
+            return (a != b).then_some("checked state");
+        }
+        // Radio buttons of a group differ in their values, and the one that is
+        // checked is validated separately.
+        _ => return None,
+    }
+
+    let style =
+        |field: &FormField| (field.text_size, field.text_fill, field.text_font.clone());
+    (style(first) != style(other)).then_some("text style")
+}
+
+/// A font with which PDF readers show text typed into a field.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum FieldFont {
+    /// A standard font that PDF readers have built in, with its resource name
+    /// and base font.
+    Standard((&'static str, &'static str)),
+    /// A font of the document with its index in the font map.
+    Embedded(usize),
+}
+
+/// The resource names and base fonts of the standard fonts, as PDF readers
+/// name them in forms, by family (sans-serif, serif, or monospace), weight
+/// (regular or bold), and style (upright or italic).
+const STANDARD_FONTS: [[[(&str, &str); 2]; 2]; 3] = [
+    [
+        [("Helv", "Helvetica"), ("HeOb", "Helvetica-Oblique")],
+        [("HeBo", "Helvetica-Bold"), ("HeBO", "Helvetica-BoldOblique")],
+    ],
+    [
+        [("TiRo", "Times-Roman"), ("TiIt", "Times-Italic")],
+        [("TiBo", "Times-Bold"), ("TiBI", "Times-BoldItalic")],
+    ],
+    [
+        [("Cour", "Courier"), ("CoOb", "Courier-Oblique")],
+        [("CoBo", "Courier-Bold"), ("CoBO", "Courier-BoldOblique")],
+    ],
+];
+
+impl FieldFont {
+    /// The font's name in the form's resources.
+    fn name(self) -> EcoString {
+        match self {
+            Self::Standard((name, _)) => name.into(),
+            Self::Embedded(i) => eco_format!("F{i}"),
+        }
+    }
+}
+
+/// Choose the font with which PDF readers show text typed into a field.
+///
+/// The standard fonts only cover the Windows character set. They are used
+/// when the field's text fits into it, in the variant that resembles the
+/// field's font most. Otherwise, the field's font is used if the document
+/// embeds it. Since embedded fonts are subsetted, readers may still fall back
+/// to other fonts for characters the document does not use.
+fn field_font(ctx: &PdfContext, field: &FormField) -> FieldFont {
+    let text: Vec<&EcoString> = match &field.kind {
+        FormFieldKind::Text { value, .. } => vec![value],
+        FormFieldKind::Dropdown { options, .. } => options.iter().collect(),
+        FormFieldKind::Checkbox { .. } | FormFieldKind::Radio { .. } => vec![],
+    };
+
+    let font = field.text_font.as_ref();
+    let encodable = text.iter().all(|text| text.chars().all(win_ansi));
+    if let (false, Some(i)) = (encodable, font.and_then(|font| ctx.font_map.get(font))) {
+        return FieldFont::Embedded(i);
+    }
+
+    let Some(info) = font.map(Font::info) else {
+        return FieldFont::Standard(STANDARD_FONTS[0][0][0]);
+    };
+
+    let family = if info.flags.contains(FontFlags::MONOSPACE) {
+        2
+    } else if info.flags.contains(FontFlags::SERIF) {
+        1
+    } else {
+        0
+    };
+    let weight = usize::from(info.variant.weight >= FontWeight::SEMIBOLD);
+    let style = usize::from(info.variant.style != FontStyle::Normal);
+    FieldFont::Standard(STANDARD_FONTS[family][weight][style])
+}
+
+/// Whether a character is in the Windows character set.
+fn win_ansi(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='~' | '\u{A0}'..='\u{FF}')
+        || "€‚ƒ„…†‡ˆ‰Š‹ŒŽ‘’“”•–—˜™š›œžŸ".contains(c)
+}
+
+/// Write a widget annotation and its appearances.
+///
+/// The `state` is the one of the widget's checkbox or radio button field.
+fn write_widget(
+    ctx: &mut PdfContext,
+    field_ref: Ref,
+    page_ref: Ref,
+    widget: &Widget,
+    state: Option<&EcoString>,
+) {
+    let on_ref = write_appearance(ctx, &widget.on);
+    let off_ref = widget.off.as_ref().map(|off| write_appearance(ctx, off));
+
+    let mut annotation = ctx.pdf.indirect(widget.id).start::<Annotation>();
+    annotation.pair(Name(b"Subtype"), Name(b"Widget"));
+    annotation.rect(widget.rect);
+    annotation.flags(AnnotationFlags::PRINT);
+    annotation.pair(Name(b"P"), page_ref);
+    annotation.pair(Name(b"Parent"), field_ref);
+    if let Some(key) = widget.struct_parent {
+        annotation.pair(Name(b"StructParent"), key);
+    }
+
+    let mut appearances = annotation.insert(Name(b"AP")).dict();
+    let Some(off_ref) = off_ref else {
+        appearances.pair(Name(b"N"), on_ref);
+        return;
+    };
+
+    // The state in which a checkbox or radio button widget is checked. For
+    // radio buttons, it is the group's value when this button is selected.
+    let on = match &widget.field.kind {
+        FormFieldKind::Radio { value, .. } => value.as_str(),
+        _ => "On",
+    };
+
+    appearances
+        .insert(Name(b"N"))
+        .dict()
+        .pair(Name(on.as_bytes()), on_ref)
+        .pair(Name(b"Off"), off_ref);
+    appearances.finish();
+
+    let current = if state.is_some_and(|state| state == on) { on } else { "Off" };
+    annotation.pair(Name(b"AS"), Name(current.as_bytes()));
+}
+
+/// Write an appearance of a widget as a form XObject.
+fn write_appearance(ctx: &mut PdfContext, appearance: &Appearance) -> Ref {
+    let resources_ref = write_resources(ctx, &appearance.resources);
+    let id = ctx.alloc.bump();
+    let content = deflate_memoized(&appearance.content);
+    let mut form = ctx.pdf.form_xobject(id, &content);
+    form.filter(Filter::FlateDecode);
+    form.bbox(Rect::new(
+        0.0,
+        0.0,
+        appearance.size.x.to_f32(),
+        appearance.size.y.to_f32(),
+    ));
+    form.pair(Name(b"Resources"), resources_ref);
+    form.finish();
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use comemo::Prehashed;
+    use typst::foundations::Bytes;
+    use typst::introspection::Meta;
+    use typst::layout::{Abs, FrameItem, Point};
+    use typst::model::Document;
+    use typst::syntax::Span;
+    use typst::visualize::Color;
+
+    use super::*;
+    use crate::{pdf, PdfStandard};
+
+    /// A text field whose text is styled with a font from the test assets.
+    fn text_field(name: &str, value: &str, font: Option<&str>) -> FormField {
+        let font = font.map(|font| {
+            let data = std::fs::read(format!("../../assets/fonts/{font}")).unwrap();
+            Font::new(Bytes::from(data), 0).unwrap()
+        });
+        FormField {
+            name: name.into(),
+            kind: FormFieldKind::Text { value: value.into(), multiline: false },
+            appearance: Prehashed::new(Frame::soft(Size::splat(Abs::pt(10.0)))),
+            off: None,
+            text_size: Abs::pt(11.0),
+            text_fill: Color::BLACK,
+            text_font: font,
+            span: Span::detached(),
+        }
+    }
+
+    /// Export a page with the given fields.
+    fn export(fields: Vec<FormField>) -> SourceResult<Vec<u8>> {
+        let size = Size::splat(Abs::pt(10.0));
+        let mut frame = Frame::soft(Size::splat(Abs::pt(100.0)));
+        for field in fields {
+            frame.push(Point::zero(), FrameItem::Meta(Meta::FormField(field), size));
+        }
+        let document = Document { pages: vec![frame], ..Default::default() };
+        pdf(&document, None, None, None, PdfStandard::V1_7)
+    }
+
+    /// The names and default appearances of the written form's fields, and
+    /// the names of the fonts in its resources.
+    fn acro_form(pdf: &[u8]) -> (Vec<(String, String)>, Vec<String>) {
+        let doc = lopdf::Document::load_mem(pdf).unwrap();
+        let catalog = doc.catalog().unwrap();
+        let form = catalog.get_deref(b"AcroForm", &doc).unwrap().as_dict().unwrap();
+        let string = |object: &lopdf::Object| {
+            String::from_utf8_lossy(object.as_str().unwrap()).into_owned()
+        };
+
+        let fields = form
+            .get(b"Fields")
+            .and_then(lopdf::Object::as_array)
+            .unwrap()
+            .iter()
+            .map(|field| {
+                let field = doc.dereference(field).unwrap().1.as_dict().unwrap();
+                (string(field.get(b"T").unwrap()), string(field.get(b"DA").unwrap()))
+            })
+            .collect();
+
+        let resources = form.get_deref(b"DR", &doc).unwrap().as_dict().unwrap();
+        let fonts = resources.get_deref(b"Font", &doc).unwrap().as_dict().unwrap();
+        let mut names: Vec<String> = fonts
+            .iter()
+            .map(|(name, _)| String::from_utf8_lossy(name).into_owned())
+            .collect();
+        names.sort();
+
+        (fields, names)
+    }
+
+    #[test]
+    fn test_form_fields() {
+        let pdf = export(vec![
+            text_field("name", "Ada", None),
+            text_field("city", "Berlin", Some("IBMPlexSerif-Regular.ttf")),
+            text_field("code", "", Some("DejaVuSansMono-Bold.ttf")),
+            text_field("name", "Ada", None),
+        ])
+        .unwrap();
+
+        let (fields, fonts) = acro_form(&pdf);
+        assert_eq!(
+            fields,
+            [
+                ("name".into(), "/Helv 11 Tf 0 0 0 rg".into()),
+                ("city".into(), "/TiRo 11 Tf 0 0 0 rg".into()),
+                ("code".into(), "/CoBo 11 Tf 0 0 0 rg".into()),
+            ]
+        );
+        assert_eq!(fonts, ["CoBo", "Helv", "TiRo"]);
+    }
+
+    #[test]
+    fn test_form_fields_same_name() {
+        let message = |fields| export(fields).unwrap_err()[0].message.to_string();
+        assert_eq!(
+            message(vec![
+                text_field("name", "Ada", None),
+                text_field("name", "Bob", None)
+            ]),
+            "another form field named \"name\" has a different value"
+        );
+        assert_eq!(
+            message(vec![
+                text_field("name", "Ada", None),
+                text_field("name", "Ada", Some("IBMPlexSerif-Regular.ttf")),
+            ]),
+            "another form field named \"name\" has a different text style"
+        );
+    }
+
+    #[test]
+    fn test_win_ansi() {
+        assert!("Grüße – 5 €".chars().all(win_ansi));
+        assert!(!"Łódź".chars().all(win_ansi));
+        assert!(!"東京".chars().all(win_ansi));
+    }
+}
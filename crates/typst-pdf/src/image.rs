@@ -1,11 +1,11 @@
 use std::collections::HashMap;
-use std::io::Cursor;
 
-use image::{DynamicImage, GenericImageView, Rgba};
-use pdf_writer::{Chunk, Filter, Finish, Ref};
+use image::{GenericImageView, Rgba};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use pdf_writer::{Chunk, Dict, Filter, Finish, Name, Null, Obj, Rect, Ref, Str};
 use typst::util::Deferred;
 use typst::visualize::{
-    ColorSpace, Image, ImageKind, RasterFormat, RasterImage, SvgImage,
+    ColorSpace, Image, ImageKind, PdfImage, RasterFormat, RasterImage, SvgImage,
 };
 
 use crate::{deflate, PdfContext};
@@ -19,15 +19,26 @@ pub fn deferred_image(image: Image) -> Deferred<EncodedImage> {
         ImageKind::Raster(raster) => {
             let raster = raster.clone();
             let (width, height) = (image.width(), image.height());
-            let (data, filter, has_color) = encode_raster_image(&raster);
-            let icc = raster.icc().map(deflate);
+            let (data, filter, color_space, inverted) = encode_raster_image(&raster);
+            let icc =
+                raster.icc().filter(|icc| icc_matches(icc, color_space)).map(deflate);
 
             let alpha =
                 raster.dynamic().color().has_alpha().then(|| encode_alpha(&raster));
 
-            EncodedImage::Raster { data, filter, has_color, width, height, icc, alpha }
+            EncodedImage::Raster {
+                data,
+                filter,
+                color_space,
+                inverted,
+                width,
+                height,
+                icc,
+                alpha,
+            }
         }
         ImageKind::Svg(svg) => EncodedImage::Svg(encode_svg(svg)),
+        ImageKind::Pdf(pdf) => EncodedImage::Pdf(encode_pdf(pdf)),
     })
 }
 
@@ -40,7 +51,8 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
             EncodedImage::Raster {
                 data,
                 filter,
-                has_color,
+                color_space,
+                inverted,
                 width,
                 height,
                 icc,
@@ -49,11 +61,21 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
                 let image_ref = ctx.alloc.bump();
                 ctx.image_refs.push(image_ref);
 
+                let components = components(*color_space);
                 let mut image = ctx.pdf.image_xobject(image_ref, data);
                 image.filter(*filter);
                 image.width(*width as i32);
                 image.height(*height as i32);
                 image.bits_per_component(8);
+                if matches!(filter, Filter::FlateDecode) {
+                    write_predictor(&mut image, components, *width);
+                }
+                if *inverted {
+                    image
+                        .insert(Name(b"Decode"))
+                        .array()
+                        .items((0..components).flat_map(|_| [1.0_f32, 0.0]));
+                }
 
                 let mut icc_ref = None;
                 let space = image.color_space();
@@ -61,10 +83,8 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
                     let id = ctx.alloc.bump();
                     space.icc_based(id);
                     icc_ref = Some(id);
-                } else if *has_color {
-                    ctx.colors.write(ColorSpace::Srgb, space, &mut ctx.alloc);
                 } else {
-                    ctx.colors.write(ColorSpace::D65Gray, space, &mut ctx.alloc);
+                    ctx.colors.write(*color_space, space, &mut ctx.alloc);
                 }
 
                 // Add a second gray-scale image containing the alpha values if
@@ -80,6 +100,7 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
                     mask.height(*height as i32);
                     mask.color_space().device_gray();
                     mask.bits_per_component(8);
+                    write_predictor(&mut mask, 1, *width);
                 } else {
                     image.finish();
                 }
@@ -87,16 +108,16 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
                 if let (Some(icc), Some(icc_ref)) = (icc, icc_ref) {
                     let mut stream = ctx.pdf.icc_profile(icc_ref, icc);
                     stream.filter(Filter::FlateDecode);
-                    if *has_color {
-                        stream.n(3);
-                        stream.alternate().srgb();
-                    } else {
-                        stream.n(1);
-                        stream.alternate().d65_gray();
+                    stream.n(components);
+                    let alternate = stream.alternate();
+                    match color_space {
+                        ColorSpace::D65Gray => alternate.d65_gray(),
+                        ColorSpace::Cmyk => alternate.device_cmyk(),
+                        _ => alternate.srgb(),
                     }
                 }
             }
-            EncodedImage::Svg(chunk) => {
+            EncodedImage::Svg(chunk) | EncodedImage::Pdf(chunk) => {
                 let mut map = HashMap::new();
                 chunk.renumber_into(&mut ctx.pdf, |old| {
                     *map.entry(old).or_insert_with(|| ctx.alloc.bump())
@@ -107,50 +128,29 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
     }
 }
 
-/// Encode an image with a suitable filter and return the data, filter and
-/// whether the image has color.
+/// Encode an image with a suitable filter and return the data, filter, color
+/// space and whether the samples are inverted.
 ///
-/// Skips the alpha channel as that's encoded separately.
+/// JPEGs are embedded as they are if PDF readers can decode them. Everything
+/// else is deflated with PNG predictors. Skips the alpha channel as that's
+/// encoded separately.
 #[tracing::instrument(skip_all)]
-fn encode_raster_image(image: &RasterImage) -> (Vec<u8>, Filter, bool) {
-    let dynamic = image.dynamic();
-    match (image.format(), dynamic) {
-        // 8-bit gray JPEG.
-        (RasterFormat::Jpg, DynamicImage::ImageLuma8(_)) => {
-            let mut data = Cursor::new(vec![]);
-            dynamic.write_to(&mut data, image::ImageFormat::Jpeg).unwrap();
-            (data.into_inner(), Filter::DctDecode, false)
-        }
-
-        // 8-bit RGB JPEG (CMYK JPEGs get converted to RGB earlier).
-        (RasterFormat::Jpg, DynamicImage::ImageRgb8(_)) => {
-            let mut data = Cursor::new(vec![]);
-            dynamic.write_to(&mut data, image::ImageFormat::Jpeg).unwrap();
-            (data.into_inner(), Filter::DctDecode, true)
-        }
-
-        // TODO: Encode flate streams with PNG-predictor?
-
-        // 8-bit gray PNG.
-        (RasterFormat::Png, DynamicImage::ImageLuma8(luma)) => {
-            let data = deflate(luma.as_raw());
-            (data, Filter::FlateDecode, false)
+fn encode_raster_image(image: &RasterImage) -> (Vec<u8>, Filter, ColorSpace, bool) {
+    if image.format() == RasterFormat::Jpg {
+        if let Some(info) = jpeg_info(image.data()) {
+            return (image.data().to_vec(), Filter::DctDecode, info.space, info.inverted);
         }
+    }
 
-        // Anything else (including Rgb(a) PNGs).
-        (_, buf) => {
-            let (width, height) = buf.dimensions();
-            let mut pixels = Vec::with_capacity(3 * width as usize * height as usize);
-            for (_, _, Rgba([r, g, b, _])) in buf.pixels() {
-                pixels.push(r);
-                pixels.push(g);
-                pixels.push(b);
-            }
+    let dynamic = image.dynamic();
+    let (samples, color_space) = if dynamic.color().has_color() {
+        (dynamic.to_rgb8().into_raw(), ColorSpace::Srgb)
+    } else {
+        (dynamic.to_luma8().into_raw(), ColorSpace::D65Gray)
+    };
 
-            let data = deflate(&pixels);
-            (data, Filter::FlateDecode, true)
-        }
-    }
+    let filtered = apply_predictor(&samples, dynamic.width(), components(color_space));
+    (deflate(&filtered), Filter::FlateDecode, color_space, false)
 }
 
 /// Encode an image's alpha channel if present.
@@ -161,7 +161,168 @@ fn encode_alpha(raster: &RasterImage) -> (Vec<u8>, Filter) {
         .pixels()
         .map(|(_, _, Rgba([_, _, _, a]))| a)
         .collect();
-    (deflate(&pixels), Filter::FlateDecode)
+    let filtered = apply_predictor(&pixels, raster.width(), 1);
+    (deflate(&filtered), Filter::FlateDecode)
+}
+
+/// Whether a raster image ends up with device-dependent CMYK colors in the
+/// PDF, that is, whether it is a CMYK JPEG without a matching ICC profile.
+pub(crate) fn is_device_cmyk(raster: &RasterImage) -> bool {
+    raster.format() == RasterFormat::Jpg
+        && jpeg_info(raster.data()).is_some_and(|info| info.space == ColorSpace::Cmyk)
+        && !raster.icc().is_some_and(|icc| icc_matches(icc, ColorSpace::Cmyk))
+}
+
+/// The properties of a JPEG that matter for embedding it as is.
+struct JpegInfo {
+    /// The color space of the JPEG's components.
+    space: ColorSpace,
+    /// Whether the components are stored inverted. Adobe applications write
+    /// CMYK JPEGs like this and mark them with an APP14 segment.
+    inverted: bool,
+}
+
+/// Read the header of a JPEG.
+///
+/// Returns `None` if the JPEG uses a coding process or color space that PDF
+/// readers do not support.
+fn jpeg_info(data: &[u8]) -> Option<JpegInfo> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut adobe = false;
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+
+        // Markers may be preceded by any number of fill bytes.
+        let marker = data[i + 1];
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+
+        // The segment's length includes the two length bytes.
+        let len = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+        let segment = data.get(i + 4..i + 2 + len)?;
+        match marker {
+            // Adobe's application segment.
+            0xEE => adobe |= segment.starts_with(b"Adobe"),
+            // Baseline, extended sequential and progressive Huffman coding.
+            0xC0..=0xC2 => {
+                let precision = *segment.first()?;
+                let space = match *segment.get(5)? {
+                    1 => ColorSpace::D65Gray,
+                    3 => ColorSpace::Srgb,
+                    4 => ColorSpace::Cmyk,
+                    _ => return None,
+                };
+                let inverted = adobe && space == ColorSpace::Cmyk;
+                return (precision == 8).then_some(JpegInfo { space, inverted });
+            }
+            // Lossless, hierarchical and arithmetic coding.
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => return None,
+            _ => {}
+        }
+
+        i += 2 + len;
+    }
+
+    None
+}
+
+/// Whether an ICC profile describes colors in the given color space.
+fn icc_matches(icc: &[u8], color_space: ColorSpace) -> bool {
+    let signature: &[u8] = match color_space {
+        ColorSpace::D65Gray => b"GRAY",
+        ColorSpace::Cmyk => b"CMYK",
+        _ => b"RGB ",
+    };
+    icc.get(16..20) == Some(signature)
+}
+
+/// The number of components of a raster image's color space.
+fn components(color_space: ColorSpace) -> i32 {
+    match color_space {
+        ColorSpace::D65Gray => 1,
+        ColorSpace::Cmyk => 4,
+        _ => 3,
+    }
+}
+
+/// Filter 8-bit samples with PNG predictors, picking the predictor that
+/// promises the best compression for each row.
+fn apply_predictor(samples: &[u8], width: u32, components: i32) -> Vec<u8> {
+    let bpp = components as usize;
+    let stride = width as usize * bpp;
+    let rows = samples.len() / stride;
+    let zeros = vec![0; stride];
+
+    let mut filtered = Vec::with_capacity(samples.len() + rows);
+    let mut candidate = vec![0; stride];
+    let mut best = vec![0; stride];
+    for (y, row) in samples.chunks_exact(stride).enumerate() {
+        let above =
+            if y == 0 { &zeros[..] } else { &samples[(y - 1) * stride..y * stride] };
+
+        let mut best_kind = 0;
+        let mut best_cost = u64::MAX;
+        for kind in 0..5 {
+            for i in 0..stride {
+                let left = if i >= bpp { row[i - bpp] } else { 0 };
+                let up = above[i];
+                let up_left = if i >= bpp { above[i - bpp] } else { 0 };
+                let prediction = match kind {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    _ => paeth(left, up, up_left),
+                };
+                candidate[i] = row[i].wrapping_sub(prediction);
+            }
+
+            // Rows with small differences compress well.
+            let cost = candidate.iter().map(|&v| (v as i8).unsigned_abs() as u64).sum();
+            if cost < best_cost {
+                best_kind = kind;
+                best_cost = cost;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+
+        filtered.push(best_kind);
+        filtered.extend_from_slice(&best);
+    }
+
+    filtered
+}
+
+/// The Paeth predictor from the PNG specification.
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance = |v: u8| (estimate - v as i16).abs();
+    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+        left
+    } else if distance(up) <= distance(up_left) {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Write the parameters for decoding samples filtered with PNG predictors.
+fn write_predictor(stream: &mut Dict, components: i32, width: u32) {
+    stream
+        .insert(Name(b"DecodeParms"))
+        .dict()
+        .pair(Name(b"Predictor"), 15)
+        .pair(Name(b"Colors"), components)
+        .pair(Name(b"BitsPerComponent"), 8)
+        .pair(Name(b"Columns"), width as i32);
 }
 
 /// Encode an SVG into a chunk of PDF objects.
@@ -187,16 +348,125 @@ fn encode_svg(svg: &SvgImage) -> Chunk {
     chunk
 }
 
+/// Encode a page of a PDF into a chunk of PDF objects.
+///
+/// The main XObject will have ID 1. It is a form XObject that draws the
+/// page's content into the unit square, like an image.
+#[tracing::instrument(skip_all)]
+fn encode_pdf(pdf: &PdfImage) -> Chunk {
+    let mut chunk = Chunk::new();
+    let document = pdf.document();
+    let mut copier = Copier {
+        document,
+        map: HashMap::new(),
+        queue: vec![],
+        next: Ref::new(2),
+    };
+
+    let [x0, y0, x1, y1] = pdf.bbox().map(|v| v as f32);
+    let content = deflate(pdf.content());
+
+    let mut form = chunk.form_xobject(Ref::new(1), &content);
+    form.filter(Filter::FlateDecode);
+    form.bbox(Rect::new(x0, y0, x1, y1));
+    form.matrix(pdf.matrix().map(|v| v as f32));
+    if let Some(resources) = pdf.resources() {
+        copier.write_dict(&mut form.insert(Name(b"Resources")).dict(), resources);
+    }
+    form.finish();
+
+    // Copy everything the resources refer to, including indirect objects
+    // that are referenced from the copied objects in turn.
+    while let Some(id) = copier.queue.pop() {
+        let new = copier.map[&id];
+        match document.get_object(id) {
+            Ok(Object::Stream(stream)) => {
+                // The stream's data is copied with its filters as is.
+                let mut writer = chunk.stream(new, &stream.content);
+                for (key, value) in stream.dict.iter() {
+                    if key != b"Length" {
+                        copier.write(writer.insert(Name(key)), value);
+                    }
+                }
+            }
+            Ok(object) => copier.write(chunk.indirect(new), object),
+            Err(_) => chunk.indirect(new).primitive(Null),
+        }
+    }
+
+    chunk
+}
+
+/// Copies objects from a parsed PDF into a chunk, assigning new ids to the
+/// indirect ones.
+struct Copier<'a> {
+    /// The PDF to copy from.
+    document: &'a Document,
+    /// Maps the ids of the indirect objects in the PDF to their new ids.
+    map: HashMap<ObjectId, Ref>,
+    /// Indirect objects that were referenced but not yet copied.
+    queue: Vec<ObjectId>,
+    /// The next id to assign.
+    next: Ref,
+}
+
+impl Copier<'_> {
+    /// Write a direct object.
+    fn write(&mut self, obj: Obj, object: &Object) {
+        match object {
+            Object::Null => obj.primitive(Null),
+            Object::Boolean(v) => obj.primitive(*v),
+            // PDF writers may use 64-bit integers, which we can only write as
+            // reals if they don't fit.
+            Object::Integer(v) => match i32::try_from(*v) {
+                Ok(v) => obj.primitive(v),
+                Err(_) => obj.primitive(*v as f32),
+            },
+            Object::Real(v) => obj.primitive(*v),
+            Object::Name(v) => obj.primitive(Name(v)),
+            Object::String(v, _) => obj.primitive(Str(v)),
+            Object::Array(items) => {
+                let mut array = obj.array();
+                for item in items {
+                    self.write(array.push(), item);
+                }
+            }
+            Object::Dictionary(dict) => self.write_dict(&mut obj.dict(), dict),
+            // Streams are always indirect objects.
+            Object::Stream(_) => obj.primitive(Null),
+            Object::Reference(id) => obj.primitive(self.reference(*id)),
+        }
+    }
+
+    /// Write the entries of a dictionary.
+    fn write_dict(&mut self, writer: &mut Dict, dict: &Dictionary) {
+        for (key, value) in dict.iter() {
+            self.write(writer.insert(Name(key)), value);
+        }
+    }
+
+    /// The new id of an indirect object, queueing it for copying when it is
+    /// referenced for the first time.
+    fn reference(&mut self, id: ObjectId) -> Ref {
+        *self.map.entry(id).or_insert_with(|| {
+            self.queue.push(id);
+            self.next.bump()
+        })
+    }
+}
+
 /// A pre-encoded image.
 pub enum EncodedImage {
     /// A pre-encoded rasterized image.
     Raster {
-        /// The raw, pre-deflated image data.
+        /// The encoded image data.
         data: Vec<u8>,
         /// The filter to use for the image.
         filter: Filter,
-        /// Whether the image has color.
-        has_color: bool,
+        /// The color space of the image's samples.
+        color_space: ColorSpace,
+        /// Whether the samples are inverted.
+        inverted: bool,
         /// The image's width.
         width: u32,
         /// The image's height.
@@ -210,4 +480,139 @@ pub enum EncodedImage {
     ///
     /// The chunk is the SVG converted to PDF objects.
     Svg(Chunk),
+    /// A page of a PDF.
+    ///
+    /// The chunk is the page converted to a form XObject, along with the
+    /// objects its resources refer to.
+    Pdf(Chunk),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assemble a JPEG from the start-of-image marker and the given segments.
+    fn jpeg(segments: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        for &(marker, payload) in segments {
+            data.extend([0xFF, marker]);
+            data.extend((payload.len() as u16 + 2).to_be_bytes());
+            data.extend(payload);
+        }
+        data
+    }
+
+    /// The payload of a start-of-frame segment for a 16x16 image.
+    fn frame(precision: u8, components: u8) -> Vec<u8> {
+        let mut payload = vec![precision, 0, 16, 0, 16, components];
+        for id in 1..=components {
+            payload.extend([id, 0x11, 0]);
+        }
+        payload
+    }
+
+    /// An Adobe application segment.
+    const ADOBE: &[u8] = b"Adobe\x00\x64\x00\x00\x00\x00\x02";
+
+    #[test]
+    fn test_jpeg_info_baseline() {
+        let info = jpeg_info(&jpeg(&[(0xE0, b"JFIF\0"), (0xC0, &frame(8, 3))])).unwrap();
+        assert_eq!(info.space, ColorSpace::Srgb);
+        assert!(!info.inverted);
+    }
+
+    #[test]
+    fn test_jpeg_info_progressive() {
+        let info = jpeg_info(&jpeg(&[(0xC2, &frame(8, 1))])).unwrap();
+        assert_eq!(info.space, ColorSpace::D65Gray);
+        assert!(!info.inverted);
+    }
+
+    #[test]
+    fn test_jpeg_info_cmyk() {
+        let info = jpeg_info(&jpeg(&[(0xC0, &frame(8, 4))])).unwrap();
+        assert_eq!(info.space, ColorSpace::Cmyk);
+        assert!(!info.inverted);
+
+        let info = jpeg_info(&jpeg(&[(0xEE, ADOBE), (0xC0, &frame(8, 4))])).unwrap();
+        assert_eq!(info.space, ColorSpace::Cmyk);
+        assert!(info.inverted);
+
+        // Only CMYK components are inverted.
+        let info = jpeg_info(&jpeg(&[(0xEE, ADOBE), (0xC0, &frame(8, 3))])).unwrap();
+        assert!(!info.inverted);
+    }
+
+    #[test]
+    fn test_jpeg_info_fill_bytes() {
+        let mut data = jpeg(&[(0xC0, &frame(8, 3))]);
+        data.splice(2..2, [0xFF, 0xFF]);
+        assert!(jpeg_info(&data).is_some());
+    }
+
+    #[test]
+    fn test_jpeg_info_unsupported() {
+        // Extended sequential with 12-bit samples.
+        assert!(jpeg_info(&jpeg(&[(0xC1, &frame(12, 3))])).is_none());
+        // Lossless and arithmetic coding.
+        assert!(jpeg_info(&jpeg(&[(0xC3, &frame(8, 3))])).is_none());
+        assert!(jpeg_info(&jpeg(&[(0xC9, &frame(8, 3))])).is_none());
+        // Two components.
+        assert!(jpeg_info(&jpeg(&[(0xC0, &frame(8, 2))])).is_none());
+        // Not a JPEG.
+        assert!(jpeg_info(b"\x89PNG\r\n\x1a\n").is_none());
+    }
+
+    #[test]
+    fn test_jpeg_info_truncated() {
+        let data = jpeg(&[(0xE0, b"JFIF\0"), (0xC0, &frame(8, 3))]);
+        for len in 0..data.len() {
+            assert!(jpeg_info(&data[..len]).is_none(), "length {len}");
+        }
+        assert!(jpeg_info(&[0xFF, 0xD8]).is_none());
+    }
+
+    #[test]
+    fn test_paeth() {
+        assert_eq!(paeth(1, 2, 3), 1);
+        assert_eq!(paeth(100, 50, 100), 50);
+        assert_eq!(paeth(10, 20, 15), 15);
+        assert_eq!(paeth(0, 0, 0), 0);
+        assert_eq!(paeth(255, 255, 0), 255);
+    }
+
+    #[test]
+    fn test_apply_predictor_round_trip() {
+        let (width, components) = (5, 3);
+        let samples: Vec<u8> = (0..width * components * 4)
+            .map(|i| (i * i / 3 + i % 7 * 31) as u8)
+            .collect();
+        let filtered = apply_predictor(&samples, width, components as i32);
+
+        // Undo the filters like a PDF reader does.
+        let bpp = components as usize;
+        let stride = width as usize * bpp;
+        assert_eq!(filtered.len(), samples.len() + samples.len() / stride);
+        let mut decoded: Vec<u8> = vec![];
+        for (y, row) in filtered.chunks_exact(stride + 1).enumerate() {
+            let start = y * stride;
+            for i in 0..stride {
+                let left = if i >= bpp { decoded[start + i - bpp] } else { 0 };
+                let up = if y > 0 { decoded[start + i - stride] } else { 0 };
+                let up_left =
+                    if y > 0 && i >= bpp { decoded[start + i - stride - bpp] } else { 0 };
+                let prediction = match row[0] {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    4 => paeth(left, up, up_left),
+                    kind => panic!("invalid filter type {kind}"),
+                };
+                decoded.push(row[1 + i].wrapping_add(prediction));
+            }
+        }
+
+        assert_eq!(decoded, samples);
+    }
 }
@@ -1,13 +1,18 @@
 //! Exporting into PDF documents.
 
 mod color;
+mod destination;
+mod embed;
 mod extg;
 mod font;
+mod form;
 mod gradient;
 mod image;
 mod outline;
 mod page;
 mod pattern;
+mod pdfa;
+mod tags;
 
 use std::cmp::Eq;
 use std::collections::{BTreeMap, HashMap};
@@ -16,10 +21,11 @@ use std::sync::Arc;
 
 use base64::Engine;
 use ecow::{eco_format, EcoString};
-use pdf_writer::types::Direction;
-use pdf_writer::{Finish, Name, Pdf, Ref, TextStr};
+use pdf_writer::types::{Direction, OutputIntentSubtype};
+use pdf_writer::{Finish, Name, Pdf, Ref, Str, TextStr};
+use typst::diag::SourceResult;
 use typst::foundations::Datetime;
-use typst::layout::{Abs, Dir, Em, Transform};
+use typst::layout::{Abs, Dir, Em, PageRanges, Transform};
 use typst::model::Document;
 use typst::text::{Font, Lang};
 use typst::util::Deferred;
@@ -32,11 +38,26 @@ use crate::gradient::PdfGradient;
 use crate::image::EncodedImage;
 use crate::page::Page;
 use crate::pattern::PdfPattern;
+use crate::tags::Tags;
 
 /// Export a document into a PDF file.
 ///
 /// Returns the raw bytes making up the PDF file.
 ///
+/// The file is tagged with a logical structure that is derived from the
+/// document's headings, paragraphs, lists, tables, figures, and so on. This
+/// lets screen readers and reflow tools make sense of the content.
+///
+/// Files attached with `pdf.embed` are embedded into the file and listed in
+/// the document catalog. Fails if two attachments share a name.
+///
+/// Form fields become an interactive form whose widgets show the fields'
+/// laid-out appearances. Fails if fields of different kinds share a name.
+///
+/// Labelled elements become named destinations, so that other documents can
+/// link to them as `file.pdf#label`. Links of this form also work in the other
+/// direction and jump to a named destination in another PDF file.
+///
 /// The `ident` parameter shall be a string that uniquely and stably identifies
 /// the document. It should not change between compilations of the same
 /// document. Its hash will be used to create a PDF document identifier (the
@@ -47,13 +68,27 @@ use crate::pattern::PdfPattern;
 /// The `timestamp`, if given, is expected to be the creation date of the
 /// document as a UTC datetime. It will only be used if `set document(date: ..)`
 /// is `auto`.
+///
+/// The `page_ranges`, if given, restrict the exported pages to the given
+/// ranges. Outline entries and links that point to excluded pages are
+/// omitted.
+///
+/// The `standard` determines which PDF standard the file conforms to. Fails
+/// with errors pointing to the offending content if the exported pages cannot
+/// be represented in it.
 #[tracing::instrument(skip_all)]
 pub fn pdf(
     document: &Document,
     ident: Option<&str>,
     timestamp: Option<Datetime>,
-) -> Vec<u8> {
-    let mut ctx = PdfContext::new(document);
+    page_ranges: Option<PageRanges>,
+    standard: PdfStandard,
+) -> SourceResult<Vec<u8>> {
+    let mut ctx = PdfContext::new(document, page_ranges, standard);
+    if standard == PdfStandard::A2b {
+        pdfa::validate(&ctx)?;
+    }
+    let embedded = embed::write_embedded_files(&mut ctx)?;
     page::construct_pages(&mut ctx, &document.pages);
     font::write_fonts(&mut ctx);
     image::write_images(&mut ctx);
@@ -61,18 +96,39 @@ pub fn pdf(
     extg::write_external_graphics_states(&mut ctx);
     pattern::write_patterns(&mut ctx);
     page::write_page_tree(&mut ctx);
-    write_catalog(&mut ctx, ident, timestamp);
-    ctx.pdf.finish()
+    let form = form::write_form(&mut ctx)?;
+    let dests = destination::write_named_destinations(&mut ctx);
+    write_catalog(&mut ctx, ident, timestamp, &dests, &embedded, form);
+    Ok(ctx.pdf.finish())
+}
+
+/// A standard that an exported PDF file conforms to.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PdfStandard {
+    /// PDF 1.7.
+    #[default]
+    V1_7,
+    /// PDF/A-2b, the basic conformance level of the second part of the PDF/A
+    /// standard for long-term archival.
+    ///
+    /// In addition to what plain PDF 1.7 export writes, this embeds an sRGB
+    /// output intent, marks link annotations as printable, writes a `CIDSet`
+    /// for each font, and declares the conformance in the XMP metadata.
+    A2b,
 }
 
 /// Context for exporting a whole PDF document.
 struct PdfContext<'a> {
     /// The document that we're currently exporting.
     document: &'a Document,
+    /// The page ranges to export, if any. Pages outside of them are skipped.
+    exported_pages: Option<PageRanges>,
+    /// The standard that the PDF file conforms to.
+    standard: PdfStandard,
     /// The writer we are writing the PDF into.
     pdf: Pdf,
-    /// Content of exported pages.
-    pages: Vec<Page>,
+    /// Content of exported pages. `None` for pages that were not exported.
+    pages: Vec<Option<Page>>,
     /// For each font a mapping from used glyphs to their text representation.
     /// May contain multiple chars in case of ligatures or similar things. The
     /// same glyph can have a different text representation within one document,
@@ -88,7 +144,7 @@ struct PdfContext<'a> {
     alloc: Ref,
     /// The ID of the page tree.
     page_tree_ref: Ref,
-    /// The IDs of written pages.
+    /// The IDs of written pages. Only contains exported pages.
     page_refs: Vec<Ref>,
     /// The IDs of written fonts.
     font_refs: Vec<Ref>,
@@ -102,6 +158,8 @@ struct PdfContext<'a> {
     ext_gs_refs: Vec<Ref>,
     /// Handles color space writing.
     colors: ColorSpaces,
+    /// The logical structure of the document.
+    tags: Tags,
 
     /// Deduplicates fonts used across the document.
     font_map: Remapper<Font>,
@@ -118,11 +176,25 @@ struct PdfContext<'a> {
 }
 
 impl<'a> PdfContext<'a> {
-    fn new(document: &'a Document) -> Self {
+    fn new(
+        document: &'a Document,
+        exported_pages: Option<PageRanges>,
+        standard: PdfStandard,
+    ) -> Self {
         let mut alloc = Ref::new(1);
         let page_tree_ref = alloc.bump();
+
+        // PDF/A's output intent refers to the sRGB profile, so it must be
+        // written even if no content uses it.
+        let mut colors = ColorSpaces::default();
+        if standard == PdfStandard::A2b {
+            colors.srgb(&mut alloc);
+        }
+
         Self {
             document,
+            exported_pages,
+            standard,
             pdf: Pdf::new(),
             pages: vec![],
             glyph_sets: HashMap::new(),
@@ -135,7 +207,8 @@ impl<'a> PdfContext<'a> {
             gradient_refs: vec![],
             pattern_refs: vec![],
             ext_gs_refs: vec![],
-            colors: ColorSpaces::default(),
+            colors,
+            tags: Tags::new(),
             font_map: Remapper::new(),
             image_map: Remapper::new(),
             image_deferred_map: HashMap::default(),
@@ -148,7 +221,14 @@ impl<'a> PdfContext<'a> {
 
 /// Write the document catalog.
 #[tracing::instrument(skip_all)]
-fn write_catalog(ctx: &mut PdfContext, ident: Option<&str>, timestamp: Option<Datetime>) {
+fn write_catalog(
+    ctx: &mut PdfContext,
+    ident: Option<&str>,
+    timestamp: Option<Datetime>,
+    dests: &BTreeMap<EcoString, Ref>,
+    embedded: &BTreeMap<EcoString, Ref>,
+    form: Option<Ref>,
+) {
     let lang = ctx
         .languages
         .iter()
@@ -167,6 +247,9 @@ fn write_catalog(ctx: &mut PdfContext, ident: Option<&str>, timestamp: Option<Da
     // Write the page labels.
     let page_labels = page::write_page_labels(ctx);
 
+    // Write the structure tree.
+    let struct_tree_root = tags::write_structure(ctx, lang);
+
     // Write the document information.
     let mut info = ctx.pdf.document_info(ctx.alloc.bump());
     let mut xmp = XmpWriter::new();
@@ -222,7 +305,7 @@ fn write_catalog(ctx: &mut PdfContext, ident: Option<&str>, timestamp: Option<Da
     }
 
     info.finish();
-    xmp.num_pages(ctx.document.pages.len() as u32);
+    xmp.num_pages(ctx.page_refs.len() as u32);
     xmp.format("application/pdf");
     xmp.language(ctx.languages.keys().map(|lang| LangId(lang.as_str())));
 
@@ -245,6 +328,10 @@ fn write_catalog(ctx: &mut PdfContext, ident: Option<&str>, timestamp: Option<Da
 
     xmp.rendition_class(RenditionClass::Proof);
     xmp.pdf_version("1.7");
+    if ctx.standard == PdfStandard::A2b {
+        xmp.pdfa_part(2);
+        xmp.pdfa_conformance("B");
+    }
 
     let xmp_buf = xmp.finish(None);
     let meta_ref = ctx.alloc.bump();
@@ -258,6 +345,8 @@ fn write_catalog(ctx: &mut PdfContext, ident: Option<&str>, timestamp: Option<Da
     catalog.pages(ctx.page_tree_ref);
     catalog.viewer_preferences().direction(dir);
     catalog.metadata(meta_ref);
+    catalog.pair(Name(b"StructTreeRoot"), struct_tree_root);
+    catalog.insert(Name(b"MarkInfo")).dict().pair(Name(b"Marked"), true);
 
     // Insert the page labels.
     if !page_labels.is_empty() {
@@ -275,6 +364,52 @@ fn write_catalog(ctx: &mut PdfContext, ident: Option<&str>, timestamp: Option<Da
     if let Some(lang) = lang {
         catalog.lang(TextStr(lang.as_str()));
     }
+
+    // Insert the named destinations and the embedded files into name trees.
+    // The embedded files are listed in the attachment panel of PDF readers.
+    if !dests.is_empty() || !embedded.is_empty() {
+        let mut names = catalog.insert(Name(b"Names")).dict();
+        if !dests.is_empty() {
+            let mut tree = names.insert(Name(b"Dests")).dict();
+            let mut entries = tree.insert(Name(b"Names")).array();
+            for (name, dest_ref) in dests {
+                entries.item(Str(name.as_bytes()));
+                entries.item(*dest_ref);
+            }
+        }
+        if !embedded.is_empty() {
+            let mut tree = names.insert(Name(b"EmbeddedFiles")).dict();
+            let mut entries = tree.insert(Name(b"Names")).array();
+            for (name, spec_ref) in embedded {
+                entries.item(TextStr(name));
+                entries.item(*spec_ref);
+            }
+        }
+    }
+
+    // The embedded files are also associated files of the document.
+    if !embedded.is_empty() {
+        catalog.insert(Name(b"AF")).array().items(embedded.values().copied());
+    }
+
+    if let Some(form) = form {
+        catalog.pair(Name(b"AcroForm"), form);
+    }
+
+    // PDF/A requires an output intent so that all device-dependent colors
+    // have a defined meaning. Typst's colors are all sRGB-based, so we use
+    // the sRGB profile that also backs the `srgb` color space.
+    if ctx.standard == PdfStandard::A2b {
+        let srgb = ctx.colors.srgb(&mut ctx.alloc);
+        catalog
+            .output_intents()
+            .push()
+            .subtype(OutputIntentSubtype::PDFA)
+            .output_condition_identifier(TextStr("sRGB"))
+            .registry_name(TextStr("http://www.color.org"))
+            .info(TextStr("sRGB IEC61966-2.1"))
+            .dest_output_profile(srgb);
+    }
 }
 
 /// Compress data with the DEFLATE algorithm.
@@ -368,6 +503,10 @@ where
         })
     }
 
+    fn get(&self, item: &T) -> Option<usize> {
+        self.to_pdf.get(item).copied()
+    }
+
     fn pdf_indices<'a>(
         &'a self,
         refs: &'a [Ref],
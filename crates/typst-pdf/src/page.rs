@@ -3,15 +3,15 @@ use std::num::NonZeroUsize;
 
 use ecow::{eco_format, EcoString};
 use pdf_writer::types::{
-    ActionType, AnnotationType, ColorSpaceOperand, LineCapStyle, LineJoinStyle,
-    NumberingStyle,
+    ActionType, AnnotationFlags, AnnotationType, ColorSpaceOperand, LineCapStyle,
+    LineJoinStyle, NumberingStyle,
 };
-use pdf_writer::writers::PageLabel;
+use pdf_writer::writers::{Annotation, PageLabel, Resources};
 use pdf_writer::{Content, Filter, Finish, Name, Rect, Ref, Str, TextStr};
-use typst::introspection::Meta;
+use typst::introspection::{Meta, Tag};
 use typst::layout::{
-    Abs, Em, Frame, FrameItem, GroupItem, PdfPageLabel, PdfPageLabelStyle, Point, Ratio,
-    Size, Transform,
+    Abs, Em, Frame, FrameItem, GroupItem, PdfPageLabel, PdfPageLabelStyle, Point,
+    Position, Ratio, Size, Transform,
 };
 use typst::model::Destination;
 use typst::text::{Font, TextItem};
@@ -21,23 +21,44 @@ use typst::visualize::{
 };
 
 use crate::color::PaintEncode;
+use crate::destination::xyz;
 use crate::extg::ExtGState;
+use crate::form::{construct_widget, Widget};
 use crate::image::deferred_image;
-use crate::{deflate_memoized, AbsExt, EmExt, PdfContext};
+use crate::tags::{locatable_tag, Kind};
+use crate::{deflate_memoized, AbsExt, EmExt, PdfContext, PdfStandard};
 
 /// Construct page objects.
 #[tracing::instrument(skip_all)]
 pub(crate) fn construct_pages(ctx: &mut PdfContext, frames: &[Frame]) {
-    for frame in frames {
-        let (page_ref, page) = construct_page(ctx, frame);
+    for (i, frame) in frames.iter().enumerate() {
+        if ctx
+            .exported_pages
+            .as_ref()
+            .is_some_and(|ranges| !ranges.includes_page_index(i))
+        {
+            // Don't export this page.
+            ctx.pages.push(None);
+            continue;
+        }
+
+        let (page_ref, page) = construct_page(ctx, frame, true);
         ctx.page_refs.push(page_ref);
-        ctx.pages.push(page);
+        ctx.pages.push(Some(page));
     }
 }
 
 /// Construct a page object.
+///
+/// If `tagged` is true, the content is made part of the document's logical
+/// structure. This is only the case for actual pages, not for the frames of
+/// patterns.
 #[tracing::instrument(skip_all)]
-pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> (Ref, Page) {
+pub(crate) fn construct_page(
+    ctx: &mut PdfContext,
+    frame: &Frame,
+    tagged: bool,
+) -> (Ref, Page) {
     let page_ref = ctx.alloc.bump();
 
     let mut ctx = PageContext {
@@ -50,6 +71,11 @@ pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> (Ref, Page)
         saves: vec![],
         bottom: 0.0,
         links: vec![],
+        widgets: vec![],
+        tagged,
+        chain: vec![],
+        link: None,
+        marks: vec![],
         resources: HashMap::default(),
     };
 
@@ -69,13 +95,16 @@ pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> (Ref, Page)
     // Encode the page into the content stream.
     write_frame(&mut ctx, frame);
 
+    let struct_parents = tagged.then(|| ctx.parent.tags.page(ctx.marks));
     let page = Page {
         size,
         content: ctx.content.finish(),
         id: ctx.page_ref,
         uses_opacities: ctx.uses_opacities,
         links: ctx.links,
+        widgets: ctx.widgets,
         label: ctx.label,
+        struct_parents,
         resources: ctx.resources,
     };
 
@@ -86,7 +115,9 @@ pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> (Ref, Page)
 #[tracing::instrument(skip_all)]
 pub(crate) fn write_page_tree(ctx: &mut PdfContext) {
     for i in 0..ctx.pages.len() {
-        write_page(ctx, i);
+        if ctx.pages[i].is_some() {
+            write_page(ctx, i);
+        }
     }
 
     let mut pages = ctx.pdf.pages(ctx.page_tree_ref);
@@ -144,9 +175,34 @@ pub(crate) fn write_page_tree(ctx: &mut PdfContext) {
 /// Write a page tree node.
 #[tracing::instrument(skip_all)]
 fn write_page(ctx: &mut PdfContext, i: usize) {
-    let page = &ctx.pages[i];
+    let page = ctx.pages[i].as_ref().unwrap();
     let content_id = ctx.alloc.bump();
 
+    // Links to pages that were not exported are dropped.
+    let mut annotations = vec![];
+    for (dest, rect, node) in &page.links {
+        let action = match dest {
+            Destination::Url(uri) => match remote_destination(uri) {
+                Some((file, name)) => Action::GoToRemote(file, name),
+                None => Action::Uri(uri),
+            },
+            Destination::Position(pos) => Action::GoTo(*pos),
+            Destination::Location(loc) => {
+                Action::GoTo(ctx.document.introspector.position(*loc))
+            }
+        };
+
+        if let Action::GoTo(pos) = &action {
+            if !matches!(ctx.pages.get(pos.page.get() - 1), Some(Some(_))) {
+                continue;
+            }
+        }
+
+        let id = ctx.alloc.bump();
+        let struct_parent = node.map(|node| ctx.tags.annotate(node, page.id, id));
+        annotations.push((id, *rect, action, struct_parent));
+    }
+
     let mut page_writer = ctx.pdf.page(page.id);
     page_writer.parent(ctx.page_tree_ref);
 
@@ -155,6 +211,12 @@ fn write_page(ctx: &mut PdfContext, i: usize) {
     page_writer.media_box(Rect::new(0.0, 0.0, w, h));
     page_writer.contents(content_id);
 
+    // Tab through the annotations in the order of the structure tree.
+    if let Some(key) = page.struct_parents {
+        page_writer.pair(Name(b"StructParents"), key);
+        page_writer.pair(Name(b"Tabs"), Name(b"S"));
+    }
+
     if page.uses_opacities {
         page_writer
             .group()
@@ -165,50 +227,116 @@ fn write_page(ctx: &mut PdfContext, i: usize) {
             .srgb();
     }
 
-    let mut annotations = page_writer.annotations();
-    for (dest, rect) in &page.links {
-        let mut annotation = annotations.push();
-        annotation.subtype(AnnotationType::Link).rect(*rect);
+    page_writer.insert(Name(b"Annots")).array().items(
+        annotations
+            .iter()
+            .map(|&(id, ..)| id)
+            .chain(page.widgets.iter().map(|widget| widget.id)),
+    );
+    page_writer.finish();
+
+    for (id, rect, action, struct_parent) in annotations {
+        let mut annotation = ctx.pdf.indirect(id).start::<Annotation>();
+        annotation.subtype(AnnotationType::Link).rect(rect);
         annotation.border(0.0, 0.0, 0.0, None);
+        if ctx.standard == PdfStandard::A2b {
+            annotation.flags(AnnotationFlags::PRINT);
+        }
+        if let Some(key) = struct_parent {
+            annotation.pair(Name(b"StructParent"), key);
+        }
 
-        let pos = match dest {
-            Destination::Url(uri) => {
+        match action {
+            Action::Uri(uri) => {
                 annotation
                     .action()
                     .action_type(ActionType::Uri)
                     .uri(Str(uri.as_bytes()));
-                continue;
             }
-            Destination::Position(pos) => *pos,
-            Destination::Location(loc) => ctx.document.introspector.position(*loc),
-        };
-
-        let index = pos.page.get() - 1;
-        let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
-        if let Some(page) = ctx.pages.get(index) {
-            annotation
-                .action()
-                .action_type(ActionType::GoTo)
-                .destination()
-                .page(ctx.page_refs[index])
-                .xyz(pos.point.x.to_f32(), (page.size.y - y).to_f32(), None);
+            Action::GoToRemote(file, name) => {
+                annotation
+                    .action()
+                    .action_type(ActionType::RemoteGoTo)
+                    .pair(Name(b"F"), Str(file.as_bytes()))
+                    .pair(Name(b"D"), Str(name.as_bytes()));
+            }
+            Action::GoTo(pos) => {
+                let target = ctx.pages[pos.page.get() - 1].as_ref().unwrap();
+                let (x, y) = xyz(pos.point, target.size.y);
+                annotation
+                    .action()
+                    .action_type(ActionType::GoTo)
+                    .destination()
+                    .page(target.id)
+                    .xyz(x, y, None);
+            }
         }
     }
 
-    annotations.finish();
-    page_writer.finish();
-
     let data = deflate_memoized(&page.content);
     ctx.pdf.stream(content_id, &data).filter(Filter::FlateDecode);
 }
 
+/// What a link annotation does when clicked.
+enum Action<'a> {
+    /// Open a URL.
+    Uri(&'a EcoString),
+    /// Go to a named destination in another PDF file.
+    GoToRemote(EcoString, EcoString),
+    /// Go to a position in the document.
+    GoTo(Position),
+}
+
+/// Split a relative link like `file.pdf#label` into the path of the PDF file
+/// and the name of the destination in it.
+///
+/// Both parts are percent-decoded, so that `file.pdf#my%20label` points to
+/// the destination `my label`. Links with a URL scheme are left to the PDF
+/// reader, which typically opens them in a browser.
+fn remote_destination(uri: &str) -> Option<(EcoString, EcoString)> {
+    let (file, name) = uri.split_once('#')?;
+    let is_pdf = std::path::Path::new(file)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+    (is_pdf && !file.contains(':') && !name.is_empty())
+        .then(|| (percent_decode(file), percent_decode(name)))
+}
+
+/// Decode the percent-escapes in a part of a URI, like `%20` for a space.
+///
+/// Malformed escapes are kept as they are.
+fn percent_decode(text: &str) -> EcoString {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into()
+}
+
 /// Write the page labels.
 #[tracing::instrument(skip_all)]
 pub(crate) fn write_page_labels(ctx: &mut PdfContext) -> Vec<(NonZeroUsize, Ref)> {
     let mut result = vec![];
     let mut prev: Option<&PdfPageLabel> = None;
 
-    for (i, page) in ctx.pages.iter().enumerate() {
+    // Labels are keyed by the index in the exported document, so we skip
+    // pages that were not exported.
+    for (i, page) in ctx.pages.iter().flatten().enumerate() {
         let nr = NonZeroUsize::new(1 + i).unwrap();
         let Some(label) = &page.label else { continue };
 
@@ -261,10 +389,16 @@ pub struct Page {
     pub content: Vec<u8>,
     /// Whether the page uses opacities.
     pub uses_opacities: bool,
-    /// Links in the PDF coordinate system.
-    pub links: Vec<(Destination, Rect)>,
+    /// Links in the PDF coordinate system, with the structure elements of
+    /// tagged ones.
+    pub links: Vec<(Destination, Rect, Option<usize>)>,
+    /// The widgets of form fields on the page.
+    pub widgets: Vec<Widget>,
     /// The page's PDF label.
     pub label: Option<PdfPageLabel>,
+    /// The key of the page's entry in the structure's parent tree, if it is
+    /// tagged.
+    pub struct_parents: Option<i32>,
     /// The page's used resources
     pub resources: HashMap<PageResource, usize>,
 }
@@ -324,6 +458,58 @@ impl PageResource {
     }
 }
 
+/// Write the resource dictionary of a content stream other than a page, like
+/// a pattern or the appearance of a form field.
+pub(crate) fn write_resources(
+    ctx: &mut PdfContext,
+    resources: &[(PageResource, usize)],
+) -> Ref {
+    let id = ctx.alloc.bump();
+    let mut resources_map = ctx.pdf.indirect(id).start::<Resources>();
+
+    resources_map.x_objects().pairs(
+        resources
+            .iter()
+            .filter(|(res, _)| res.is_x_object())
+            .map(|(res, ref_)| (res.name(), ctx.image_refs[*ref_])),
+    );
+
+    resources_map.fonts().pairs(
+        resources
+            .iter()
+            .filter(|(res, _)| res.is_font())
+            .map(|(res, ref_)| (res.name(), ctx.font_refs[*ref_])),
+    );
+
+    ctx.colors
+        .write_color_spaces(resources_map.color_spaces(), &mut ctx.alloc);
+
+    resources_map
+        .patterns()
+        .pairs(
+            resources
+                .iter()
+                .filter(|(res, _)| res.is_pattern())
+                .map(|(res, ref_)| (res.name(), ctx.pattern_refs[*ref_])),
+        )
+        .pairs(
+            resources
+                .iter()
+                .filter(|(res, _)| res.is_gradient())
+                .map(|(res, ref_)| (res.name(), ctx.gradient_refs[*ref_])),
+        );
+
+    resources_map.ext_g_states().pairs(
+        resources
+            .iter()
+            .filter(|(res, _)| res.is_ext_g_state())
+            .map(|(res, ref_)| (res.name(), ctx.ext_gs_refs[*ref_])),
+    );
+
+    resources_map.finish();
+    id
+}
+
 /// An exporter for the contents of a single PDF page.
 pub struct PageContext<'a, 'b> {
     pub(crate) parent: &'a mut PdfContext<'b>,
@@ -334,7 +520,17 @@ pub struct PageContext<'a, 'b> {
     saves: Vec<State>,
     bottom: f32,
     uses_opacities: bool,
-    links: Vec<(Destination, Rect)>,
+    links: Vec<(Destination, Rect, Option<usize>)>,
+    widgets: Vec<Widget>,
+    /// Whether the content is made part of the document's logical structure.
+    tagged: bool,
+    /// The elements that the content being written is part of, from the
+    /// outermost.
+    chain: Vec<Tag>,
+    /// The link that the content being written is in.
+    link: Option<Destination>,
+    /// For each marked-content sequence, the structure element it belongs to.
+    marks: Vec<usize>,
     /// Keep track of the resources being used in the page.
     pub resources: HashMap<PageResource, usize>,
 }
@@ -555,28 +751,150 @@ impl PageContext<'_, '_> {
     pub fn reset_stroke_color_space(&mut self) {
         self.state.stroke_space = None;
     }
+
+    /// Continue with content that is preceded by the given metadata.
+    ///
+    /// The content is also part of the elements and the link that the
+    /// enclosing frame is in, which are given by `outer`.
+    fn enter(&mut self, outer: &(Vec<Tag>, Option<Destination>), run: MetaRun) {
+        self.chain = outer.0.iter().copied().chain(run.tags).collect();
+        self.link = run.link.or_else(|| outer.1.clone());
+        for (pos, dest, size) in run.links {
+            let node = self
+                .tagged
+                .then(|| self.parent.tags.annotation_target(&self.chain, &dest));
+            write_link(self, pos, dest, size, node);
+        }
+    }
+
+    /// Begin a marked-content sequence that ties content to its structure
+    /// element or marks it as an artifact.
+    fn begin_tag(&mut self, kind: Kind) {
+        if !self.tagged {
+            return;
+        }
+
+        let tags = &mut self.parent.tags;
+        match tags.target(&self.chain, self.link.as_ref(), kind) {
+            Some(node) => {
+                let mcid = self.marks.len() as i32;
+                tags.mark(node, self.page_ref, mcid);
+                self.marks.push(node);
+
+                let role = tags.role_name(node);
+                let mut marked = self
+                    .content
+                    .begin_marked_content_with_properties(Name(role.as_bytes()));
+                marked.properties().pair(Name(b"MCID"), mcid);
+            }
+            None => {
+                self.content.begin_marked_content(Name(b"Artifact"));
+            }
+        }
+    }
+
+    /// End the marked-content sequence begun by [`Self::begin_tag`].
+    fn end_tag(&mut self) {
+        if self.tagged {
+            self.content.end_marked_content();
+        }
+    }
+}
+
+/// Metadata that precedes content in a frame.
+#[derive(Default)]
+struct MetaRun {
+    /// The elements that the content is part of, from the outermost.
+    tags: Vec<Tag>,
+    /// The innermost link that the content is in.
+    link: Option<Destination>,
+    /// The link areas to write.
+    links: Vec<(Point, Destination, Size)>,
 }
 
 /// Encode a frame into the content stream.
+///
+/// The elements and links that content is part of are given by the run of
+/// metadata items preceding it, within those of the enclosing frame. Content
+/// without any applies to the run of the enclosing frame.
 fn write_frame(ctx: &mut PageContext, frame: &Frame) {
+    let outer = (ctx.chain.clone(), ctx.link.clone());
+    let mut run: Option<MetaRun> = None;
+
     for &(pos, ref item) in frame.items() {
         let x = pos.x.to_f32();
         let y = pos.y.to_f32();
 
-        match item {
-            FrameItem::Group(group) => write_group(ctx, pos, group),
-            FrameItem::Text(text) => write_text(ctx, pos, text),
-            FrameItem::Shape(shape, _) => write_shape(ctx, pos, shape),
-            FrameItem::Image(image, size, _) => write_image(ctx, x, y, image, *size),
-            FrameItem::Meta(meta, size) => match meta {
-                Meta::Link(dest) => write_link(ctx, pos, dest, *size),
-                Meta::Elem(_) => {}
+        if let FrameItem::Meta(meta, size) = item {
+            match meta {
+                Meta::Link(dest) => {
+                    let run = run.get_or_insert_with(MetaRun::default);
+                    run.link = Some(dest.clone());
+                    run.links.push((pos, dest.clone(), *size));
+                }
+                Meta::Elem(elem) => {
+                    let run = run.get_or_insert_with(MetaRun::default);
+                    run.tags.push(locatable_tag(elem));
+                }
+                Meta::Tag(tag) => {
+                    let run = run.get_or_insert_with(MetaRun::default);
+                    run.tags.push(*tag);
+                }
                 Meta::Hide => {}
                 Meta::PageNumbering(_) => {}
                 Meta::PdfPageLabel(label) => ctx.label = Some(label.clone()),
-            },
+                Meta::FormField(field) => {
+                    if let Some(run) = run.take() {
+                        ctx.enter(&outer, run);
+                    }
+
+                    // The rest of the frame is the field's static appearance,
+                    // which the widget replaces.
+                    let rect = page_rect(ctx, pos, *size);
+                    let mut widget = construct_widget(ctx.parent, field, rect);
+                    if ctx.tagged {
+                        let tags = &mut ctx.parent.tags;
+                        let node = tags.widget_target(&ctx.chain);
+                        let key = tags.annotate(node, ctx.page_ref, widget.id);
+                        widget.struct_parent = Some(key);
+                    }
+                    ctx.widgets.push(widget);
+                    break;
+                }
+            }
+            continue;
+        }
+
+        if let Some(run) = run.take() {
+            ctx.enter(&outer, run);
+        }
+
+        match item {
+            FrameItem::Group(group) => write_group(ctx, pos, group),
+            FrameItem::Text(text) => {
+                ctx.begin_tag(Kind::Text(text.lang));
+                write_text(ctx, pos, text);
+                ctx.end_tag();
+            }
+            FrameItem::Shape(shape, _) => {
+                ctx.begin_tag(Kind::Shape);
+                write_shape(ctx, pos, shape);
+                ctx.end_tag();
+            }
+            FrameItem::Image(image, size, _) => {
+                ctx.begin_tag(Kind::Image(image.alt()));
+                write_image(ctx, x, y, image, *size);
+                ctx.end_tag();
+            }
+            FrameItem::Meta(..) => unreachable!(),
         }
     }
+
+    if let Some(run) = run {
+        ctx.enter(&outer, run);
+    }
+
+    (ctx.chain, ctx.link) = outer;
 }
 
 /// Encode a group into the content stream.
@@ -758,7 +1076,9 @@ fn write_image(ctx: &mut PageContext, x: f32, y: f32, image: &Image, size: Size)
     ctx.content.save_state();
     ctx.content.transform([w, 0.0, 0.0, -h, x, y + h]);
 
-    if let Some(alt) = image.alt() {
+    // In tagged content, the alternative description is part of the
+    // structure element instead.
+    if let Some(alt) = image.alt().filter(|_| !ctx.tagged) {
         let mut image_span =
             ctx.content.begin_marked_content_with_properties(Name(b"Span"));
         let mut image_alt = image_span.properties();
@@ -778,13 +1098,26 @@ fn write_image(ctx: &mut PageContext, x: f32, y: f32, image: &Image, size: Size)
 }
 
 /// Save a link for later writing in the annotations dictionary.
-fn write_link(ctx: &mut PageContext, pos: Point, dest: &Destination, size: Size) {
+fn write_link(
+    ctx: &mut PageContext,
+    pos: Point,
+    dest: Destination,
+    size: Size,
+    node: Option<usize>,
+) {
+    let rect = page_rect(ctx, pos, size);
+    ctx.links.push((dest, rect, node));
+}
+
+/// Compute the bounding box of an area of the current frame in the PDF
+/// coordinate system.
+fn page_rect(ctx: &PageContext, pos: Point, size: Size) -> Rect {
     let mut min_x = Abs::inf();
     let mut min_y = Abs::inf();
     let mut max_x = -Abs::inf();
     let mut max_y = -Abs::inf();
 
-    // Compute the bounding box of the transformed link.
+    // Compute the bounding box of the transformed area.
     for point in [
         pos,
         pos + Point::with_x(size.x),
@@ -802,9 +1135,7 @@ fn write_link(ctx: &mut PageContext, pos: Point, dest: &Destination, size: Size)
     let x2 = max_x.to_f32();
     let y1 = max_y.to_f32();
     let y2 = min_y.to_f32();
-    let rect = Rect::new(x1, y1, x2, y2);
-
-    ctx.links.push((dest.clone(), rect));
+    Rect::new(x1, y1, x2, y2)
 }
 
 fn to_pdf_line_cap(cap: LineCap) -> LineCapStyle {
@@ -832,3 +1163,48 @@ fn to_pdf_numbering_style(style: PdfPageLabelStyle) -> NumberingStyle {
         PdfPageLabelStyle::UpperAlpha => NumberingStyle::UpperAlpha,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_destination() {
+        assert_eq!(
+            remote_destination("other.pdf#intro"),
+            Some(("other.pdf".into(), "intro".into()))
+        );
+        assert_eq!(
+            remote_destination("docs/Other.PDF#sec:a"),
+            Some(("docs/Other.PDF".into(), "sec:a".into()))
+        );
+    }
+
+    #[test]
+    fn test_remote_destination_percent_decoded() {
+        assert_eq!(
+            remote_destination("my%20file.pdf#my%20label"),
+            Some(("my file.pdf".into(), "my label".into()))
+        );
+        assert_eq!(
+            remote_destination("a.pdf#%C3%BCber"),
+            Some(("a.pdf".into(), "über".into()))
+        );
+    }
+
+    #[test]
+    fn test_remote_destination_not_remote() {
+        assert_eq!(remote_destination("https://typst.app/a.pdf#intro"), None);
+        assert_eq!(remote_destination("notes.html#intro"), None);
+        assert_eq!(remote_destination("other.pdf#"), None);
+        assert_eq!(remote_destination("other.pdf"), None);
+    }
+
+    #[test]
+    fn test_percent_decode_malformed() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%2"), "%zz%2");
+        assert_eq!(percent_decode("%+1"), "%+1");
+        assert_eq!(percent_decode("%41b"), "Ab");
+    }
+}
@@ -6,18 +6,36 @@ use typst::util::Numeric;
 use typst::visualize::{Pattern, RelativeTo};
 
 use crate::color::PaintEncode;
-use crate::page::{construct_page, PageContext, PageResource, ResourceKind, Transforms};
+use crate::page::{
+    construct_page, write_resources, PageContext, PageResource, ResourceKind, Transforms,
+};
 use crate::{deflate_memoized, transform_to_array, PdfContext};
 
 /// Writes the actual patterns (tiling patterns) to the PDF.
 /// This is performed once after writing all pages.
 pub(crate) fn write_patterns(ctx: &mut PdfContext) {
-    for PdfPattern { transform, pattern, content, resources } in ctx.pattern_map.items() {
+    // Patterns may be nested in each other, so all of them need a reference
+    // before the resource dictionaries are written.
+    let resources: Vec<_> = ctx
+        .pattern_map
+        .items()
+        .map(|pattern| pattern.resources.clone())
+        .collect();
+    for _ in &resources {
         let tiling = ctx.alloc.bump();
         ctx.pattern_refs.push(tiling);
+    }
+
+    let resource_refs: Vec<_> = resources
+        .iter()
+        .map(|resources| write_resources(ctx, resources))
+        .collect();
 
+    for (i, PdfPattern { transform, pattern, content, .. }) in
+        ctx.pattern_map.items().enumerate()
+    {
         let content = deflate_memoized(content);
-        let mut tiling_pattern = ctx.pdf.tiling_pattern(tiling, &content);
+        let mut tiling_pattern = ctx.pdf.tiling_pattern(ctx.pattern_refs[i], &content);
         tiling_pattern
             .tiling_type(TilingType::ConstantSpacing)
             .paint_type(PaintType::Colored)
@@ -28,50 +46,9 @@ pub(crate) fn write_patterns(ctx: &mut PdfContext) {
                 pattern.size().y.to_pt() as _,
             ))
             .x_step((pattern.size().x + pattern.spacing().x).to_pt() as _)
-            .y_step((pattern.size().y + pattern.spacing().y).to_pt() as _);
-
-        let mut resources_map = tiling_pattern.resources();
-
-        resources_map.x_objects().pairs(
-            resources
-                .iter()
-                .filter(|(res, _)| res.is_x_object())
-                .map(|(res, ref_)| (res.name(), ctx.image_refs[*ref_])),
-        );
-
-        resources_map.fonts().pairs(
-            resources
-                .iter()
-                .filter(|(res, _)| res.is_font())
-                .map(|(res, ref_)| (res.name(), ctx.font_refs[*ref_])),
-        );
-
-        ctx.colors
-            .write_color_spaces(resources_map.color_spaces(), &mut ctx.alloc);
-
-        resources_map
-            .patterns()
-            .pairs(
-                resources
-                    .iter()
-                    .filter(|(res, _)| res.is_pattern())
-                    .map(|(res, ref_)| (res.name(), ctx.pattern_refs[*ref_])),
-            )
-            .pairs(
-                resources
-                    .iter()
-                    .filter(|(res, _)| res.is_gradient())
-                    .map(|(res, ref_)| (res.name(), ctx.gradient_refs[*ref_])),
-            );
-
-        resources_map.ext_g_states().pairs(
-            resources
-                .iter()
-                .filter(|(res, _)| res.is_ext_g_state())
-                .map(|(res, ref_)| (res.name(), ctx.ext_gs_refs[*ref_])),
-        );
-
-        resources_map.finish();
+            .y_step((pattern.size().y + pattern.spacing().y).to_pt() as _)
+            .pair(Name(b"Resources"), resource_refs[i]);
+
         tiling_pattern
             .matrix(transform_to_array(
                 transform.post_concat(Transform::scale(Ratio::one(), -Ratio::one())),
@@ -115,7 +92,7 @@ fn register_pattern(
     };
 
     // Render the body.
-    let (_, content) = construct_page(ctx.parent, pattern.frame());
+    let (_, content) = construct_page(ctx.parent, pattern.frame(), false);
 
     let pdf_pattern = PdfPattern {
         transform,
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+
+use ecow::EcoVec;
+use ttf_parser::{Permissions, Tag};
+use typst::diag::{error, SourceDiagnostic, SourceResult};
+use typst::foundations::{NativeElement, Repr};
+use typst::introspection::Meta;
+use typst::layout::{Frame, FrameItem};
+use typst::model::pdf::EmbedElem;
+use typst::syntax::Span;
+use typst::text::{Font, TextItem};
+use typst::visualize::{ColorSpace, Image, ImageKind, Paint};
+
+use crate::image::is_device_cmyk;
+use crate::PdfContext;
+
+/// Check that the exported pages can be represented in PDF/A-2b.
+///
+/// Most requirements of the standard are met by how we write the file. This
+/// catches the content for which that is impossible.
+#[tracing::instrument(skip_all)]
+pub(crate) fn validate(ctx: &PdfContext) -> SourceResult<()> {
+    let mut validator = Validator { fonts: HashSet::new(), errors: EcoVec::new() };
+
+    for (i, frame) in ctx.document.pages.iter().enumerate() {
+        if ctx
+            .exported_pages
+            .as_ref()
+            .map_or(true, |ranges| ranges.includes_page_index(i))
+        {
+            validator.frame(frame);
+        }
+    }
+
+    // PDF/A-2 only allows embedding files that conform to PDF/A themselves,
+    // which we cannot verify.
+    for elem in ctx.document.introspector.query(&EmbedElem::elem().select()) {
+        validator.push(error!(
+            elem.span(), "PDF/A-2b export does not support embedded files";
+            hint: "export without a PDF standard to embed files"
+        ));
+    }
+
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors)
+    }
+}
+
+/// Collects the reasons why content cannot be represented in PDF/A.
+struct Validator {
+    /// The fonts that were already checked.
+    fonts: HashSet<Font>,
+    /// The errors found so far.
+    errors: EcoVec<SourceDiagnostic>,
+}
+
+impl Validator {
+    /// Check a frame and its subframes.
+    fn frame(&mut self, frame: &Frame) {
+        for (_, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => self.frame(&group.frame),
+                FrameItem::Text(text) => self.text(text),
+                FrameItem::Shape(shape, span) => {
+                    if let Some(fill) = &shape.fill {
+                        self.paint(fill, *span);
+                    }
+                    if let Some(stroke) = &shape.stroke {
+                        self.paint(&stroke.paint, *span);
+                    }
+                }
+                FrameItem::Image(image, _, span) => self.image(image, *span),
+                // Fields are filled in with a standard font, which isn't
+                // embedded.
+                FrameItem::Meta(Meta::FormField(field), _) => {
+                    self.push(error!(
+                        field.span, "PDF/A-2b export does not support form fields";
+                        hint: "export without a PDF standard to create a fillable form"
+                    ));
+                }
+                FrameItem::Meta(..) => {}
+            }
+        }
+    }
+
+    /// Check a text run.
+    fn text(&mut self, text: &TextItem) {
+        let span = text.glyphs.first().map_or(Span::detached(), |glyph| glyph.span.0);
+        self.paint(&text.fill, span);
+
+        if self.fonts.insert(text.font.clone()) {
+            self.font(&text.font, span);
+        }
+
+        // PDF/A forbids references to the `.notdef` glyph, which is what
+        // text no font could display is shaped into.
+        if let Some(glyph) = text.glyphs.iter().find(|glyph| glyph.id == 0) {
+            let segment = &text.text[glyph.range()];
+            self.push(error!(
+                glyph.span.0,
+                "the text {} could not be displayed with any font",
+                segment.repr();
+                hint: "PDF/A-2b does not allow missing glyphs"
+            ));
+        }
+    }
+
+    /// Check that a font can be embedded.
+    fn font(&mut self, font: &Font, span: Span) {
+        let family = &font.info().family;
+        let ttf = font.ttf();
+
+        if ttf.permissions() == Some(Permissions::Restricted) {
+            self.push(error!(
+                span, "the license of the font {family} forbids embedding it";
+                hint: "PDF/A-2b requires all fonts to be embedded"
+            ));
+        }
+
+        if ttf.raw_face().table(Tag::from_bytes(b"CFF2")).is_some() {
+            self.push(error!(
+                span, "the font {family} has CFF2 outlines";
+                hint: "PDF/A-2b only supports fonts with TrueType or CFF outlines"
+            ));
+        }
+    }
+
+    /// Check that a paint does not use CMYK colors.
+    ///
+    /// PDF/A only allows device CMYK colors with a CMYK output intent, but
+    /// ours is sRGB.
+    fn paint(&mut self, paint: &Paint, span: Span) {
+        let uses_cmyk = match paint {
+            Paint::Solid(color) => color.space() == ColorSpace::Cmyk,
+            Paint::Gradient(gradient) => {
+                gradient.space() == ColorSpace::Cmyk
+                    || gradient
+                        .stops_ref()
+                        .iter()
+                        .any(|(color, _)| color.space() == ColorSpace::Cmyk)
+            }
+            Paint::Pattern(pattern) => {
+                self.frame(pattern.frame());
+                false
+            }
+        };
+
+        if uses_cmyk {
+            self.push(error!(
+                span, "PDF/A-2b export does not support CMYK colors";
+                hint: "convert the color to RGB with `rgb(..)`"
+            ));
+        }
+    }
+
+    /// Check that an image can be embedded.
+    ///
+    /// JPEGs without an ICC profile are embedded with device-dependent CMYK
+    /// colors. Pages of PDF files are copied as they are, with whatever fonts,
+    /// colors, and filters they use, which we cannot verify.
+    ///
+    /// Archived documents must stay usable for readers who rely on assistive
+    /// technology, so images also need an alternative description. This
+    /// includes the images in figures.
+    fn image(&mut self, image: &Image, span: Span) {
+        if image.alt().is_none() {
+            self.push(error!(
+                span, "image has no alternative description";
+                hint: "describe the image with the `alt` parameter"
+            ));
+        }
+
+        match image.kind() {
+            ImageKind::Raster(raster) if is_device_cmyk(raster) => {
+                self.push(error!(
+                    span, "PDF/A-2b export does not support uncalibrated CMYK images";
+                    hint: "convert the image to RGB or embed a color profile"
+                ));
+            }
+            ImageKind::Pdf(_) => {
+                self.push(error!(
+                    span, "PDF/A-2b export does not support PDF images";
+                    hint: "convert the image to SVG or PNG"
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    /// Record an error, unless it was already recorded.
+    fn push(&mut self, error: SourceDiagnostic) {
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use comemo::Prehashed;
+    use typst::foundations::Bytes;
+    use typst::layout::{Abs, Point, Size};
+    use typst::model::pdf::{FormField, FormFieldKind};
+    use typst::model::Document;
+    use typst::visualize::{Color, Geometry, ImageFormat, VectorFormat};
+
+    use super::*;
+    use crate::{pdf, PdfStandard};
+
+    /// Export a page with the given items.
+    fn export(items: Vec<FrameItem>, standard: PdfStandard) -> SourceResult<Vec<u8>> {
+        let mut frame = Frame::soft(Size::splat(Abs::pt(100.0)));
+        for item in items {
+            frame.push(Point::zero(), item);
+        }
+        let document = Document { pages: vec![frame], ..Default::default() };
+        pdf(&document, None, None, None, standard)
+    }
+
+    /// A square filled with a color.
+    fn square(color: Color) -> FrameItem {
+        let shape = Geometry::Rect(Size::splat(Abs::pt(10.0))).filled(color.into());
+        FrameItem::Shape(shape, Span::detached())
+    }
+
+    /// Whether the bytes of an exported file contain a sequence.
+    fn contains(pdf: &[u8], needle: &[u8]) -> bool {
+        pdf.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_pdfa_validate_rgb() {
+        assert!(export(vec![square(Color::BLACK)], PdfStandard::A2b).is_ok());
+    }
+
+    #[test]
+    fn test_pdfa_validate_cmyk() {
+        let cmyk = square(Color::BLACK.to_space(ColorSpace::Cmyk));
+        let errors =
+            export(vec![cmyk.clone(), cmyk.clone()], PdfStandard::A2b).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message.as_str(),
+            "PDF/A-2b export does not support CMYK colors"
+        );
+        assert!(export(vec![cmyk], PdfStandard::V1_7).is_ok());
+    }
+
+    #[test]
+    fn test_pdfa_validate_form_field() {
+        let size = Size::splat(Abs::pt(10.0));
+        let field = FormField {
+            name: "name".into(),
+            kind: FormFieldKind::Text { value: "".into(), multiline: false },
+            appearance: Prehashed::new(Frame::soft(size)),
+            off: None,
+            text_size: Abs::pt(11.0),
+            text_fill: Color::BLACK,
+            text_font: None,
+            span: Span::detached(),
+        };
+        let errors =
+            export(vec![FrameItem::Meta(Meta::FormField(field), size)], PdfStandard::A2b)
+                .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message.as_str(),
+            "PDF/A-2b export does not support form fields"
+        );
+    }
+
+    #[test]
+    fn test_pdfa_validate_alt() {
+        let svg = |alt: Option<&str>| {
+            let data = Bytes::from_static(
+                b"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"1\" height=\"1\"/>",
+            );
+            let format = ImageFormat::Vector(VectorFormat::Svg);
+            let image = Image::new(data, format, alt.map(Into::into)).unwrap();
+            FrameItem::Image(image, Size::splat(Abs::pt(10.0)), Span::detached())
+        };
+
+        let errors = export(vec![svg(None)], PdfStandard::A2b).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message.as_str(), "image has no alternative description");
+        assert!(export(vec![svg(Some("A dot"))], PdfStandard::A2b).is_ok());
+        assert!(export(vec![svg(None)], PdfStandard::V1_7).is_ok());
+    }
+
+    #[test]
+    fn test_pdfa_output_intent() {
+        let archival = export(vec![], PdfStandard::A2b).unwrap();
+        assert!(contains(&archival, b"/OutputIntents"));
+        assert!(contains(&archival, b"/GTS_PDFA1"));
+        assert!(contains(&archival, b"/DestOutputProfile"));
+
+        let plain = export(vec![], PdfStandard::V1_7).unwrap();
+        assert!(!contains(&plain, b"/OutputIntents"));
+    }
+}
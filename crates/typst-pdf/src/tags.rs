@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+
+use ecow::EcoString;
+use pdf_writer::{Finish, Name, Pdf, Ref, TextStr};
+use typst::foundations::{Content, NativeElement, StyleChain};
+use typst::introspection::{Location, Tag, TagKind};
+use typst::math::EquationElem;
+use typst::model::{
+    Destination, EnumElem, EnumItem, FigureElem, FootnoteElem, FootnoteEntry,
+    HeadingElem, ListElem, ListItem, ParElem, TableElem, TermItem, TermsElem,
+};
+use typst::text::Lang;
+
+use crate::PdfContext;
+
+/// The logical structure of the document.
+///
+/// The structure is derived from the elements whose metadata precedes the
+/// content in the frames and built up while the pages are written. Its
+/// reading order is the order in which the content is first encountered.
+pub(crate) struct Tags {
+    /// The structure elements. The first one is the document element, which
+    /// contains all others.
+    nodes: Vec<Node>,
+    /// Maps the locations of elements to the structure elements they became.
+    locations: HashMap<Location, usize>,
+    /// Maps a table's structure element and a row index to the row's
+    /// structure element.
+    rows: HashMap<(usize, usize), usize>,
+    /// The link that content was most recently tagged with: its destination,
+    /// the structure element it is in and its own structure element.
+    link: Option<(Destination, usize, usize)>,
+    /// The entries of the parent tree. Their indices are the keys that pages
+    /// and annotations refer to.
+    parents: Vec<Parent>,
+}
+
+/// A structure element.
+struct Node {
+    /// The element's structure type.
+    role: Role,
+    /// The element's parent. The document element is its own parent.
+    parent: usize,
+    /// The element's children, in reading order.
+    kids: Vec<Kid>,
+    /// A replacement text for the element, for figures.
+    alt: Option<EcoString>,
+    /// The language of the element's text.
+    lang: Option<Lang>,
+}
+
+/// A child of a structure element.
+enum Kid {
+    /// Another structure element.
+    Node(usize),
+    /// A marked-content sequence on a page.
+    Content { page: Ref, mcid: i32 },
+    /// A link annotation on a page.
+    Annotation { page: Ref, annotation: Ref },
+}
+
+/// An entry of the parent tree.
+enum Parent {
+    /// For each marked-content sequence on a page, the structure element it
+    /// belongs to.
+    Page(Vec<usize>),
+    /// The structure element of an annotation.
+    Annotation(usize),
+}
+
+/// A standard structure type.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Role {
+    Document,
+    Heading(usize),
+    Paragraph,
+    List,
+    ListItem,
+    ListBody,
+    Table,
+    TableRow,
+    TableHeader,
+    TableData,
+    Figure,
+    Formula,
+    Link,
+    Reference,
+    Note,
+    Form,
+}
+
+impl Role {
+    /// The name of the structure type.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Document => "Document",
+            Self::Heading(1) => "H1",
+            Self::Heading(2) => "H2",
+            Self::Heading(3) => "H3",
+            Self::Heading(4) => "H4",
+            Self::Heading(5) => "H5",
+            Self::Heading(_) => "H6",
+            Self::Paragraph => "P",
+            Self::List => "L",
+            Self::ListItem => "LI",
+            Self::ListBody => "LBody",
+            Self::Table => "Table",
+            Self::TableRow => "TR",
+            Self::TableHeader => "TH",
+            Self::TableData => "TD",
+            Self::Figure => "Figure",
+            Self::Formula => "Formula",
+            Self::Link => "Link",
+            Self::Reference => "Reference",
+            Self::Note => "Note",
+            Self::Form => "Form",
+        }
+    }
+
+    /// Whether content directly in an element of this type is decoration,
+    /// like the markers of a list or the lines of a table.
+    fn is_container(self) -> bool {
+        matches!(self, Self::List | Self::Table | Self::TableRow)
+    }
+}
+
+/// A kind of content that can be tagged.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Kind<'a> {
+    /// A text run in the given language.
+    Text(Lang),
+    /// An image with the given alternative description.
+    Image(Option<&'a str>),
+    /// A geometrical shape.
+    Shape,
+}
+
+impl Tags {
+    /// Create an empty structure that only has the document element.
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::new(Role::Document, 0)],
+            locations: HashMap::new(),
+            rows: HashMap::new(),
+            link: None,
+            parents: vec![],
+        }
+    }
+
+    /// Determine the structure element that content belongs to, given the
+    /// elements it is part of, from the outermost, and the link it is in.
+    ///
+    /// Returns `None` if the content is an artifact, that is, decoration that
+    /// is not part of the document's logical structure.
+    pub fn target(
+        &mut self,
+        chain: &[Tag],
+        link: Option<&Destination>,
+        kind: Kind,
+    ) -> Option<usize> {
+        let mut node = self.resolve(chain);
+        let role = self.nodes[node].role;
+        match kind {
+            Kind::Text(_) | Kind::Image(_) if role.is_container() => return None,
+            Kind::Shape if !matches!(role, Role::Figure | Role::Formula) => return None,
+            _ => {}
+        }
+
+        node = match link {
+            Some(dest) => self.link(node, dest),
+            None => {
+                self.link = None;
+                node
+            }
+        };
+
+        match kind {
+            Kind::Text(lang) => {
+                self.nodes[node].lang.get_or_insert(lang);
+            }
+            Kind::Image(alt) => {
+                if self.nodes[node].role != Role::Figure {
+                    node = self.push(node, Role::Figure);
+                }
+                if self.nodes[node].alt.is_none() {
+                    self.nodes[node].alt = alt.map(Into::into);
+                }
+            }
+            Kind::Shape => {}
+        }
+
+        Some(node)
+    }
+
+    /// Determine the structure element of a link annotation, given the
+    /// elements the link is part of.
+    pub fn annotation_target(&mut self, chain: &[Tag], dest: &Destination) -> usize {
+        let node = self.resolve(chain);
+        self.link(node, dest)
+    }
+
+    /// Create the structure element of a form widget, given the elements the
+    /// field is part of.
+    pub fn widget_target(&mut self, chain: &[Tag]) -> usize {
+        let node = self.resolve(chain);
+        self.link = None;
+        self.push(node, Role::Form)
+    }
+
+    /// The name of an element's structure type, for marked content.
+    pub fn role_name(&self, node: usize) -> &'static str {
+        self.nodes[node].role.name()
+    }
+
+    /// Add a marked-content sequence to a structure element.
+    pub fn mark(&mut self, node: usize, page: Ref, mcid: i32) {
+        self.nodes[node].kids.push(Kid::Content { page, mcid });
+    }
+
+    /// Add a link annotation to a structure element and return the key of its
+    /// entry in the parent tree.
+    pub fn annotate(&mut self, node: usize, page: Ref, annotation: Ref) -> i32 {
+        self.nodes[node].kids.push(Kid::Annotation { page, annotation });
+        self.parents.push(Parent::Annotation(node));
+        self.parents.len() as i32 - 1
+    }
+
+    /// Register the structure elements of a page's marked content, indexed by
+    /// their MCID, and return the key of the page's entry in the parent tree.
+    pub fn page(&mut self, marks: Vec<usize>) -> i32 {
+        self.parents.push(Parent::Page(marks));
+        self.parents.len() as i32 - 1
+    }
+
+    /// Find or create the structure elements for a chain of elements and
+    /// return the innermost one.
+    fn resolve(&mut self, chain: &[Tag]) -> usize {
+        let mut parent = 0;
+        for tag in chain {
+            if let Some(&node) = self.locations.get(&tag.location) {
+                parent = node;
+                continue;
+            }
+
+            let role = self.nodes[parent].role;
+            let func = tag.func;
+            let node = match tag.kind {
+                TagKind::Heading(level) => self.push(parent, Role::Heading(level.get())),
+                TagKind::Cell { y, header, .. } => {
+                    let row = match self.rows.get(&(parent, y)) {
+                        Some(&row) => row,
+                        None => {
+                            let row = self.push(parent, Role::TableRow);
+                            self.rows.insert((parent, y), row);
+                            row
+                        }
+                    };
+                    let role = if header { Role::TableHeader } else { Role::TableData };
+                    self.push(row, role)
+                }
+                TagKind::Plain if func == ParElem::elem() => {
+                    // The text of headings is laid out as a paragraph, but
+                    // headings are paragraphs of their own in PDF.
+                    if matches!(role, Role::Heading(_)) {
+                        parent
+                    } else {
+                        self.push(parent, Role::Paragraph)
+                    }
+                }
+                TagKind::Plain
+                    if func == ListElem::elem()
+                        || func == EnumElem::elem()
+                        || func == TermsElem::elem() =>
+                {
+                    self.push(parent, Role::List)
+                }
+                TagKind::Plain
+                    if func == ListItem::elem()
+                        || func == EnumItem::elem()
+                        || func == TermItem::elem() =>
+                {
+                    let item = self.push(parent, Role::ListItem);
+                    self.push(item, Role::ListBody)
+                }
+                TagKind::Plain if func == TableElem::elem() => {
+                    self.push(parent, Role::Table)
+                }
+                TagKind::Plain if func == FigureElem::elem() => {
+                    self.push(parent, Role::Figure)
+                }
+                TagKind::Plain if func == EquationElem::elem() => {
+                    self.push(parent, Role::Formula)
+                }
+                TagKind::Plain if func == FootnoteElem::elem() => {
+                    self.push(parent, Role::Reference)
+                }
+                TagKind::Plain if func == FootnoteEntry::elem() => {
+                    self.push(parent, Role::Note)
+                }
+                // Other elements don't show up in the structure, but their
+                // content does.
+                TagKind::Plain => continue,
+            };
+
+            self.locations.insert(tag.location, node);
+            parent = node;
+        }
+
+        parent
+    }
+
+    /// Find or create the structure element for a link in another element.
+    ///
+    /// Consecutive content with the same destination shares one element.
+    fn link(&mut self, parent: usize, dest: &Destination) -> usize {
+        if let Some((prev, prev_parent, node)) = &self.link {
+            if prev == dest && *prev_parent == parent {
+                return *node;
+            }
+        }
+
+        let node = self.push(parent, Role::Link);
+        self.link = Some((dest.clone(), parent, node));
+        node
+    }
+
+    /// Add a new structure element to a parent.
+    fn push(&mut self, parent: usize, role: Role) -> usize {
+        let node = self.nodes.len();
+        self.nodes.push(Node::new(role, parent));
+        self.nodes[parent].kids.push(Kid::Node(node));
+        node
+    }
+
+    /// Write the structure tree and return the reference of its root.
+    fn write(&self, pdf: &mut Pdf, alloc: &mut Ref, lang: Option<Lang>) -> Ref {
+        let root_ref = alloc.bump();
+        let refs: Vec<Ref> = self.nodes.iter().map(|_| alloc.bump()).collect();
+
+        for (node, &id) in self.nodes.iter().zip(&refs) {
+            let mut elem = pdf.indirect(id).dict();
+            elem.pair(Name(b"Type"), Name(b"StructElem"));
+            elem.pair(Name(b"S"), Name(node.role.name().as_bytes()));
+            elem.pair(
+                Name(b"P"),
+                if node.role == Role::Document { root_ref } else { refs[node.parent] },
+            );
+
+            if let Some(alt) = &node.alt {
+                elem.pair(Name(b"Alt"), TextStr(alt));
+            }
+
+            if let Some(node_lang) = node.lang.filter(|&l| Some(l) != lang) {
+                elem.pair(Name(b"Lang"), TextStr(node_lang.as_str()));
+            }
+
+            // Header cells apply to the cells below them.
+            if node.role == Role::TableHeader {
+                elem.insert(Name(b"A"))
+                    .dict()
+                    .pair(Name(b"O"), Name(b"Table"))
+                    .pair(Name(b"Scope"), Name(b"Column"));
+            }
+
+            let mut kids = elem.insert(Name(b"K")).array();
+            for kid in &node.kids {
+                match *kid {
+                    Kid::Node(child) => {
+                        kids.item(refs[child]);
+                    }
+                    Kid::Content { page, mcid } => {
+                        kids.push()
+                            .dict()
+                            .pair(Name(b"Type"), Name(b"MCR"))
+                            .pair(Name(b"Pg"), page)
+                            .pair(Name(b"MCID"), mcid);
+                    }
+                    Kid::Annotation { page, annotation } => {
+                        kids.push()
+                            .dict()
+                            .pair(Name(b"Type"), Name(b"OBJR"))
+                            .pair(Name(b"Pg"), page)
+                            .pair(Name(b"Obj"), annotation);
+                    }
+                }
+            }
+        }
+
+        let mut root = pdf.indirect(root_ref).dict();
+        root.pair(Name(b"Type"), Name(b"StructTreeRoot"));
+        root.pair(Name(b"K"), refs[0]);
+        root.pair(Name(b"ParentTreeNextKey"), self.parents.len() as i32);
+
+        let mut tree = root.insert(Name(b"ParentTree")).dict();
+        let mut nums = tree.insert(Name(b"Nums")).array();
+        for (key, parent) in self.parents.iter().enumerate() {
+            nums.item(key as i32);
+            match parent {
+                Parent::Page(marks) => {
+                    nums.push().array().items(marks.iter().map(|&node| refs[node]));
+                }
+                Parent::Annotation(node) => {
+                    nums.item(refs[*node]);
+                }
+            }
+        }
+
+        nums.finish();
+        tree.finish();
+        root.finish();
+
+        root_ref
+    }
+}
+
+impl Node {
+    fn new(role: Role, parent: usize) -> Self {
+        Self { role, parent, kids: vec![], alt: None, lang: None }
+    }
+}
+
+/// The tag of a locatable element, like a heading or a figure.
+pub(crate) fn locatable_tag(elem: &Content) -> Tag {
+    let tag = Tag::new(elem.func(), elem.location().unwrap());
+    match elem.to::<HeadingElem>() {
+        Some(heading) => {
+            tag.with_kind(TagKind::Heading(heading.level(StyleChain::default())))
+        }
+        None => tag,
+    }
+}
+
+/// Write the structure tree and return the reference of its root.
+///
+/// Languages that differ from the document's language `lang` are written on
+/// the structure elements.
+#[tracing::instrument(skip_all)]
+pub(crate) fn write_structure(ctx: &mut PdfContext, lang: Option<Lang>) -> Ref {
+    ctx.tags.write(&mut ctx.pdf, &mut ctx.alloc, lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::introspection::Locator;
+    use typst::model::TableCellElem;
+    use typst::syntax::Span;
+
+    use super::*;
+
+    fn roles(tags: &Tags) -> Vec<&'static str> {
+        tags.nodes.iter().map(|node| node.role.name()).collect()
+    }
+
+    #[test]
+    fn test_tags_table_header_rows() {
+        let mut locator = Locator::new();
+        let table = locator.tag(TableElem::elem(), Span::detached());
+
+        let mut tags = Tags::new();
+        let cells = [(0, 0, true), (1, 0, true), (0, 1, false), (1, 1, false)];
+        for (i, (x, y, header)) in cells.into_iter().enumerate() {
+            let cell = table.part(TableCellElem::elem(), i).with_kind(TagKind::Cell {
+                x,
+                y,
+                header,
+            });
+            let node =
+                tags.target(&[table, cell], None, Kind::Text(Lang::ENGLISH)).unwrap();
+            tags.mark(node, Ref::new(1), i as i32);
+        }
+
+        // The table's lines are artifacts.
+        assert_eq!(tags.target(&[table], None, Kind::Shape), None);
+        assert_eq!(
+            roles(&tags),
+            ["Document", "Table", "TR", "TH", "TH", "TR", "TD", "TD"]
+        );
+    }
+
+    #[test]
+    fn test_tags_paragraph_lines() {
+        let mut locator = Locator::new();
+        let first = locator.tag(ParElem::elem(), Span::detached());
+        let second = locator.tag(ParElem::elem(), Span::detached());
+
+        // Lines of the same paragraph share a structure element.
+        let mut tags = Tags::new();
+        let text = Kind::Text(Lang::ENGLISH);
+        let a = tags.target(&[first], None, text);
+        let b = tags.target(&[first], None, text);
+        let c = tags.target(&[second], None, text);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(roles(&tags), ["Document", "P", "P"]);
+    }
+
+    #[test]
+    fn test_tags_list_items() {
+        let mut locator = Locator::new();
+        let list = locator.tag(ListElem::elem(), Span::detached());
+        let par = locator.tag(ParElem::elem(), Span::detached());
+
+        let mut tags = Tags::new();
+        let text = Kind::Text(Lang::ENGLISH);
+        for i in 0..2 {
+            let item = list.part(ListItem::elem(), i);
+            tags.target(&[list, item, par.part(ParElem::elem(), i)], None, text);
+        }
+
+        // The markers are artifacts.
+        assert_eq!(tags.target(&[list], None, text), None);
+        assert_eq!(
+            roles(&tags),
+            ["Document", "L", "LI", "LBody", "P", "LI", "LBody", "P"]
+        );
+    }
+
+    #[test]
+    fn test_tags_write_structure() {
+        let mut locator = Locator::new();
+        let par = locator.tag(ParElem::elem(), Span::detached());
+
+        let mut tags = Tags::new();
+        let node = tags.target(&[par], None, Kind::Text(Lang::GERMAN)).unwrap();
+        tags.mark(node, Ref::new(1), 0);
+        tags.page(vec![node]);
+        let widget = tags.widget_target(&[par]);
+        let key = tags.annotate(widget, Ref::new(1), Ref::new(2));
+        assert_eq!(key, 1);
+
+        let mut pdf = Pdf::new();
+        let mut alloc = Ref::new(3);
+        tags.write(&mut pdf, &mut alloc, Some(Lang::ENGLISH));
+        let bytes = pdf.finish();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("/Type /StructTreeRoot"));
+        assert!(text.contains("/ParentTreeNextKey 2"));
+        assert!(text.contains("/S /P"));
+        assert!(text.contains("/S /Form"));
+        assert!(text.contains("/Type /OBJR"));
+        assert!(text.contains("/Lang (de)"));
+    }
+}
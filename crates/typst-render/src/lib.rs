@@ -164,8 +164,10 @@ fn render_frame(canvas: &mut sk::Pixmap, state: State, frame: &Frame) {
             FrameItem::Meta(meta, _) => match meta {
                 Meta::Link(_) => {}
                 Meta::Elem(_) => {}
+                Meta::Tag(_) => {}
                 Meta::PageNumbering(_) => {}
                 Meta::PdfPageLabel(_) => {}
+                Meta::FormField(_) => {}
                 Meta::Hide => {}
             },
         }
@@ -747,6 +749,15 @@ fn scaled_texture(image: &Image, w: u32, h: u32) -> Option<Arc<sk::Pixmap>> {
                 tree.render(ts, &mut pixmap.as_mut())
             });
         },
+        // Only a preview of the page's shapes can be rendered.
+        ImageKind::Pdf(pdf) => {
+            let preview = pdf.preview();
+            let ts = sk::Transform::from_scale(
+                w as f32 / preview.width().to_f32(),
+                h as f32 / preview.height().to_f32(),
+            );
+            render_frame(&mut pixmap, State::new(preview.size(), ts, ts.sx), preview);
+        }
     }
     Some(Arc::new(pixmap))
 }
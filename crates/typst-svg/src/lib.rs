@@ -14,8 +14,9 @@ use typst::layout::{
 use typst::text::{Font, TextItem};
 use typst::util::hash128;
 use typst::visualize::{
-    Color, FixedStroke, Geometry, Gradient, Image, ImageFormat, LineCap, LineJoin, Paint,
-    Path, PathItem, Pattern, RasterFormat, RatioOrAngle, RelativeTo, Shape, VectorFormat,
+    Color, FixedStroke, Geometry, Gradient, Image, ImageFormat, ImageKind, LineCap,
+    LineJoin, Paint, Path, PathItem, Pattern, RasterFormat, RatioOrAngle, RelativeTo,
+    Shape, VectorFormat,
 };
 use xmlwriter::XmlWriter;
 
@@ -289,7 +290,9 @@ impl SVGRenderer {
                 FrameItem::Shape(shape, _) => {
                     self.render_shape(state.pre_translate(*pos), shape)
                 }
-                FrameItem::Image(image, size, _) => self.render_image(image, size),
+                FrameItem::Image(image, size, _) => {
+                    self.render_image(state.pre_translate(*pos), image, size)
+                }
                 FrameItem::Meta(_, _) => unreachable!(),
             };
 
@@ -681,7 +684,19 @@ impl SVGRenderer {
     }
 
     /// Render an image element.
-    fn render_image(&mut self, image: &Image, size: &Axes<Abs>) {
+    fn render_image(&mut self, state: State, image: &Image, size: &Axes<Abs>) {
+        // Browsers don't display PDFs in images, so we draw a preview of the
+        // page's shapes instead.
+        if let ImageKind::Pdf(pdf) = image.kind() {
+            let preview = pdf.preview();
+            let ts = Transform::scale(
+                Ratio::new(size.x / preview.width()),
+                Ratio::new(size.y / preview.height()),
+            );
+            self.render_frame(state.pre_concat(ts), ts, preview);
+            return;
+        }
+
         let url = convert_image_to_base64_url(image);
         self.xml.start_element("image");
         self.xml.write_attribute("xlink:href", &url);
@@ -1221,6 +1236,7 @@ fn convert_image_to_base64_url(image: &Image) -> EcoString {
         },
         ImageFormat::Vector(f) => match f {
             VectorFormat::Svg => "svg+xml",
+            VectorFormat::Pdf => unreachable!("PDF pages are rendered as frames"),
         },
     };
 
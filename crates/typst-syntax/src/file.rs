@@ -191,6 +191,71 @@ impl Debug for VirtualPath {
     }
 }
 
+/// A parsed package manifest.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct PackageManifest {
+    /// Details about the package itself.
+    pub package: PackageInfo,
+    /// Details about the template, if the package is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateInfo>,
+}
+
+/// The `package` key in the manifest.
+///
+/// More fields are specified, but they are not relevant to the compiler.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct PackageInfo {
+    /// The name of the package within its namespace.
+    pub name: EcoString,
+    /// The package's version.
+    pub version: PackageVersion,
+    /// The path of the entrypoint into the package.
+    pub entrypoint: EcoString,
+    /// The minimum required compiler version for the package.
+    pub compiler: Option<PackageVersion>,
+}
+
+/// The `template` key in the manifest.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    /// The path of the starting point within the package.
+    pub path: EcoString,
+    /// The path of the entrypoint relative to the starting point's `path`.
+    pub entrypoint: EcoString,
+}
+
+impl PackageManifest {
+    /// Ensure that this manifest is indeed for the specified package.
+    pub fn validate(&self, spec: &PackageSpec) -> Result<(), EcoString> {
+        if self.package.name != spec.name {
+            return Err(eco_format!(
+                "package manifest contains mismatched name `{}`",
+                self.package.name
+            ));
+        }
+
+        if self.package.version != spec.version {
+            return Err(eco_format!(
+                "package manifest contains mismatched version {}",
+                self.package.version
+            ));
+        }
+
+        if let Some(compiler) = self.package.compiler {
+            let current = PackageVersion::compiler();
+            if current < compiler {
+                return Err(eco_format!(
+                    "package requires typst {compiler} or newer \
+                     (current version is {current})"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Identifies a package.
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub struct PackageSpec {
@@ -254,6 +319,63 @@ impl Display for PackageSpec {
     }
 }
 
+/// Identifies a package, but not a specific version of it.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct VersionlessPackageSpec {
+    /// The namespace the package lives in.
+    pub namespace: EcoString,
+    /// The name of the package within its namespace.
+    pub name: EcoString,
+}
+
+impl VersionlessPackageSpec {
+    /// Fill in the `version` to get a complete [`PackageSpec`].
+    pub fn at(self, version: PackageVersion) -> PackageSpec {
+        PackageSpec { namespace: self.namespace, name: self.name, version }
+    }
+}
+
+impl FromStr for VersionlessPackageSpec {
+    type Err = EcoString;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut s = unscanny::Scanner::new(s);
+        if !s.eat_if('@') {
+            Err("package specification must start with '@'")?;
+        }
+
+        let namespace = s.eat_until('/');
+        if namespace.is_empty() {
+            Err("package specification is missing namespace")?;
+        } else if !is_ident(namespace) {
+            Err(eco_format!("`{namespace}` is not a valid package namespace"))?;
+        }
+
+        s.eat_if('/');
+
+        let name = s.after();
+        if name.is_empty() {
+            Err("package specification is missing name")?;
+        } else if !is_ident(name) {
+            Err(eco_format!("`{name}` is not a valid package name"))?;
+        }
+
+        Ok(Self { namespace: namespace.into(), name: name.into() })
+    }
+}
+
+impl Debug for VersionlessPackageSpec {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for VersionlessPackageSpec {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "@{}/{}", self.namespace, self.name)
+    }
+}
+
 /// A package's version.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct PackageVersion {
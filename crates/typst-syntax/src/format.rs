@@ -0,0 +1,580 @@
+use ecow::EcoString;
+
+use crate::{parse, SyntaxKind, SyntaxNode};
+
+/// Configures how source code is formatted.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FormatConfig {
+    /// The number of spaces per indentation level.
+    pub indent: usize,
+    /// The line width the formatter tries to stay within.
+    pub max_width: usize,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self { indent: 2, max_width: 80 }
+    }
+}
+
+/// Format the code in Typst source code.
+///
+/// Code expressions, argument lists, collections and set and show rules are
+/// re-emitted with normalized spacing. Lists are broken over multiple lines
+/// when they don't fit into the configured width. Markup and math are not
+/// formatted: they are kept as they are, including their indentation, apart
+/// from trailing whitespace.
+///
+/// Fails if the source contains syntax errors. The formatted source is parsed
+/// again and the result is only returned if its syntax tree matches the
+/// original one.
+pub fn format(text: &str, config: &FormatConfig) -> Result<String, EcoString> {
+    let root = parse(text);
+    if root.erroneous() {
+        return Err("cannot format source with syntax errors".into());
+    }
+
+    let mut output = String::new();
+    Formatter { config }.markup(&root, Pos::default(), &mut output);
+
+    let reparsed = parse(&output);
+    if reparsed.erroneous() || !equivalent(&root, &reparsed) {
+        return Err("formatting would change the meaning of the source".into());
+    }
+
+    Ok(output)
+}
+
+/// Walks a syntax tree and produces its formatted text.
+struct Formatter<'a> {
+    config: &'a FormatConfig,
+}
+
+/// Where in the output the text that is currently produced starts.
+#[derive(Debug, Default, Copy, Clone)]
+struct Pos {
+    /// The indentation of the current line.
+    indent: usize,
+    /// The column at which the text starts.
+    col: usize,
+}
+
+impl Pos {
+    /// The position after `text` was written at this position.
+    fn after(self, text: &str) -> Self {
+        match text.rfind('\n') {
+            Some(i) => {
+                let line = &text[i + 1..];
+                let indent = line.len() - line.trim_start_matches(' ').len();
+                Self { indent, col: width(line) }
+            }
+            None => Self { col: self.col + width(text), ..self },
+        }
+    }
+
+    /// The position at the start of a new line with the given indentation.
+    fn line(indent: usize) -> Self {
+        Self { indent, col: indent }
+    }
+}
+
+/// An element of a list or code block.
+enum Entry<'a> {
+    /// An expression.
+    Item(&'a SyntaxNode),
+    /// A comment and whether it was on the same line as the previous entry.
+    Comment(&'a SyntaxNode, bool),
+    /// An empty line between two entries.
+    Blank,
+}
+
+impl Formatter<'_> {
+    /// Format markup, appending it to `out`, which starts at `base`.
+    ///
+    /// Markup is preserved verbatim, except for trailing whitespace and
+    /// embedded code expressions.
+    fn markup(&self, node: &SyntaxNode, base: Pos, out: &mut String) {
+        let mut embedded = false;
+        for child in node.children() {
+            if embedded {
+                embedded = false;
+                let pos = base.after(out);
+                out.push_str(&self.expr(child, pos));
+                continue;
+            }
+
+            match child.kind() {
+                SyntaxKind::Hash => {
+                    embedded = true;
+                    out.push('#');
+                }
+                SyntaxKind::Space | SyntaxKind::Parbreak => {
+                    out.push_str(&trim_trailing(child.text()));
+                }
+                SyntaxKind::Equation | SyntaxKind::Raw => {
+                    out.push_str(&child.clone().into_text());
+                }
+                _ if child.children().len() > 0 => self.markup(child, base, out),
+                _ => out.push_str(child.text()),
+            }
+        }
+    }
+
+    /// Format a code expression that starts at `pos`.
+    fn expr(&self, node: &SyntaxNode, pos: Pos) -> String {
+        match node.kind() {
+            _ if node.children().len() == 0 => node.text().to_string(),
+            SyntaxKind::CodeBlock => self.code_block(node, pos),
+            SyntaxKind::ContentBlock => {
+                let mut out = String::new();
+                self.markup(node, pos, &mut out);
+                out
+            }
+            SyntaxKind::Args
+            | SyntaxKind::Array
+            | SyntaxKind::Dict
+            | SyntaxKind::Params
+            | SyntaxKind::Destructuring => self.list(node, pos),
+            SyntaxKind::Equation | SyntaxKind::Raw => verbatim(node),
+            _ if has_comments(node) => verbatim(node),
+            SyntaxKind::ImportItems => {
+                let items: Vec<_> = significant(node)
+                    .filter(|child| child.kind() != SyntaxKind::Comma)
+                    .map(|child| self.expr(child, pos))
+                    .collect();
+                items.join(", ")
+            }
+            SyntaxKind::Parenthesized
+            | SyntaxKind::FieldAccess
+            | SyntaxKind::FuncCall
+            | SyntaxKind::Spread => self.join(node, pos, false),
+            SyntaxKind::Unary => {
+                let spaced = node.children().any(|c| c.kind() == SyntaxKind::Not);
+                self.join(node, pos, spaced)
+            }
+            _ => self.join(node, pos, true),
+        }
+    }
+
+    /// Format the children of a node one after another.
+    ///
+    /// If `spaced` is true, the children are separated by single spaces,
+    /// except before colons, arguments and parameters.
+    fn join(&self, node: &SyntaxNode, pos: Pos, spaced: bool) -> String {
+        let mut out = String::new();
+        for (i, child) in significant(node).enumerate() {
+            if spaced
+                && i > 0
+                && !matches!(
+                    child.kind(),
+                    SyntaxKind::Colon | SyntaxKind::Args | SyntaxKind::Params
+                )
+            {
+                out.push(' ');
+            }
+            out.push_str(&self.expr(child, pos.after(&out)));
+        }
+        out
+    }
+
+    /// Format a code block with one statement per line.
+    fn code_block(&self, node: &SyntaxNode, pos: Pos) -> String {
+        let children = node.children().flat_map(|child| match child.kind() {
+            SyntaxKind::Code => child.children().as_slice(),
+            _ => std::slice::from_ref(child),
+        });
+        let entries = entries(children.filter(|child| {
+            !matches!(
+                child.kind(),
+                SyntaxKind::LeftBrace | SyntaxKind::RightBrace | SyntaxKind::Semicolon
+            )
+        }));
+
+        // Blocks that were written on a single line stay there if possible.
+        if let [Entry::Item(item)] = entries.as_slice() {
+            if !verbatim(node).contains('\n') {
+                let flat = format!("{{ {} }}", self.expr(item, pos.after("{ ")));
+                if !flat.contains('\n') && self.fits(pos, &flat) {
+                    return flat;
+                }
+            }
+        }
+
+        if entries.is_empty() {
+            return "{}".into();
+        }
+
+        let lines = self.lines(&entries, pos.indent + self.config.indent);
+        self.broken("{", &lines, "}", pos.indent)
+    }
+
+    /// Format a parenthesized list, followed by trailing content blocks in the
+    /// case of arguments.
+    fn list(&self, node: &SyntaxNode, pos: Pos) -> String {
+        let kind = node.kind();
+        let children: Vec<_> = node.children().collect();
+        let Some(open) = children.iter().position(|c| c.kind() == SyntaxKind::LeftParen)
+        else {
+            // Arguments consisting of just content blocks and parameters
+            // consisting of just a single identifier.
+            return self.join(node, pos, false);
+        };
+        let close = children
+            .iter()
+            .rposition(|c| c.kind() == SyntaxKind::RightParen)
+            .unwrap_or(children.len() - 1);
+
+        let inside = &children[open + 1..close];
+        let entries = entries(inside.iter().copied().filter(|child| {
+            !matches!(child.kind(), SyntaxKind::Comma | SyntaxKind::Colon)
+        }));
+
+        let items: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Item(item) => Some(*item),
+                _ => None,
+            })
+            .collect();
+        let trailing_comma = inside
+            .iter()
+            .rev()
+            .find(|child| !child.kind().is_trivia())
+            .is_some_and(|child| child.kind() == SyntaxKind::Comma);
+
+        let mut out = if entries.is_empty() {
+            if kind == SyntaxKind::Dict {
+                "(:)".into()
+            } else {
+                "()".into()
+            }
+        } else {
+            self.parens(kind, &entries, &items, trailing_comma, pos)
+        };
+
+        for child in &children[close + 1..] {
+            if !child.kind().is_trivia() {
+                out.push_str(&self.expr(child, pos.after(&out)));
+            }
+        }
+
+        out
+    }
+
+    /// Format a non-empty parenthesized list, either on a single line or with
+    /// one item per line.
+    fn parens(
+        &self,
+        kind: SyntaxKind,
+        entries: &[Entry],
+        items: &[&SyntaxNode],
+        trailing_comma: bool,
+        pos: Pos,
+    ) -> String {
+        let inner = pos.indent + self.config.indent;
+        let texts: Vec<_> =
+            items.iter().map(|item| self.expr(item, Pos::line(inner))).collect();
+
+        // A single item keeps its trailing comma, because it makes the
+        // difference between, for example, an array and parentheses.
+        let comma = if items.len() == 1
+            && trailing_comma
+            && !matches!(kind, SyntaxKind::Args | SyntaxKind::Params)
+        {
+            ","
+        } else {
+            ""
+        };
+
+        let commented = items.len() < entries.len();
+        if !commented && texts.iter().all(|text| !text.contains('\n')) {
+            let flat = format!("({}{comma})", texts.join(", "));
+            if self.fits(pos, &flat) {
+                return flat;
+            }
+        }
+
+        // If only the last item spans multiple lines, such as a trailing
+        // closure with a code block body, it may continue on the same line.
+        if let Some((last, init)) = items.split_last() {
+            let overflows = matches!(
+                last.kind(),
+                SyntaxKind::CodeBlock
+                    | SyntaxKind::ContentBlock
+                    | SyntaxKind::Closure
+                    | SyntaxKind::Array
+                    | SyntaxKind::Dict
+            );
+            if overflows
+                && !commented
+                && texts[..init.len()].iter().all(|text| !text.contains('\n'))
+            {
+                let mut flat = String::from("(");
+                for text in &texts[..init.len()] {
+                    flat.push_str(text);
+                    flat.push_str(", ");
+                }
+                let text = self.expr(last, pos.after(&flat));
+                let first = text.lines().next().unwrap_or_default();
+                if text.contains('\n') && self.fits(pos.after(&flat), first) {
+                    flat.push_str(&text);
+                    flat.push_str(comma);
+                    flat.push(')');
+                    return flat;
+                }
+            }
+        }
+
+        let mut texts = texts.into_iter();
+        let entries: Vec<_> = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Item(_) => Line::Item(texts.next().unwrap() + ","),
+                Entry::Comment(comment, same) => {
+                    Line::Comment(comment.text().to_string(), *same)
+                }
+                Entry::Blank => Line::Blank,
+            })
+            .collect();
+        self.broken("(", &join_lines(entries), ")", pos.indent)
+    }
+
+    /// Format the entries of a code block into lines.
+    fn lines(&self, entries: &[Entry], indent: usize) -> Vec<String> {
+        let lines = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Item(item) => Line::Item(self.expr(item, Pos::line(indent))),
+                Entry::Comment(comment, same) => {
+                    Line::Comment(comment.text().to_string(), *same)
+                }
+                Entry::Blank => Line::Blank,
+            })
+            .collect();
+        join_lines(lines)
+    }
+
+    /// Put lines between delimiters, indenting them by one level.
+    fn broken(&self, open: &str, lines: &[String], close: &str, indent: usize) -> String {
+        let mut out = String::from(open);
+        for line in lines {
+            out.push('\n');
+            if !line.is_empty() {
+                out.push_str(&" ".repeat(indent + self.config.indent));
+                out.push_str(line);
+            }
+        }
+        out.push('\n');
+        out.push_str(&" ".repeat(indent));
+        out.push_str(close);
+        out
+    }
+
+    /// Whether single-line text fits into the line width at the position.
+    fn fits(&self, pos: Pos, text: &str) -> bool {
+        pos.col + width(text) <= self.config.max_width
+    }
+}
+
+/// A line of a broken list or code block.
+enum Line {
+    Item(String),
+    Comment(String, bool),
+    Blank,
+}
+
+/// Attach comments that were on the same line as the previous item to it.
+fn join_lines(lines: Vec<Line>) -> Vec<String> {
+    let mut out: Vec<String> = vec![];
+    for line in lines {
+        match line {
+            Line::Comment(comment, true) if !out.is_empty() => {
+                let last = out.last_mut().unwrap();
+                last.push(' ');
+                last.push_str(&comment);
+            }
+            Line::Item(text) | Line::Comment(text, _) => out.push(text),
+            Line::Blank => out.push(String::new()),
+        }
+    }
+    out
+}
+
+/// Group the children of a list or code block into entries.
+///
+/// Spaces are dropped, but empty lines between entries are kept (at most one
+/// in a row).
+fn entries<'a>(children: impl Iterator<Item = &'a SyntaxNode>) -> Vec<Entry<'a>> {
+    let mut entries = vec![];
+    let mut newlines = 0;
+    for child in children {
+        match child.kind() {
+            SyntaxKind::Space => {
+                newlines += child.text().chars().filter(|&c| c == '\n').count();
+                continue;
+            }
+            SyntaxKind::LineComment | SyntaxKind::BlockComment => {
+                if newlines >= 2 && !entries.is_empty() {
+                    entries.push(Entry::Blank);
+                }
+                let same = newlines == 0 && !entries.is_empty();
+                entries.push(Entry::Comment(child, same));
+            }
+            _ => {
+                if newlines >= 2 && !entries.is_empty() {
+                    entries.push(Entry::Blank);
+                }
+                entries.push(Entry::Item(child));
+            }
+        }
+        newlines = 0;
+    }
+    entries
+}
+
+/// The children of a code node that aren't spaces.
+fn significant(node: &SyntaxNode) -> impl Iterator<Item = &SyntaxNode> {
+    node.children().filter(|child| child.kind() != SyntaxKind::Space)
+}
+
+/// Whether any direct child of the node is a comment.
+fn has_comments(node: &SyntaxNode) -> bool {
+    node.children().any(|child| {
+        matches!(child.kind(), SyntaxKind::LineComment | SyntaxKind::BlockComment)
+    })
+}
+
+/// The original text of a node.
+fn verbatim(node: &SyntaxNode) -> String {
+    node.clone().into_text().into()
+}
+
+/// Remove spaces and tabs at the end of each line but the last.
+fn trim_trailing(text: &str) -> String {
+    let mut lines: Vec<_> = text.split('\n').collect();
+    let last = lines.pop().unwrap_or_default();
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(line.trim_end_matches([' ', '\t', '\r']));
+        out.push('\n');
+    }
+    out.push_str(last);
+    out
+}
+
+/// The width of single-line text.
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Whether two syntax trees are the same up to the whitespace and separators
+/// that the formatter may change.
+fn equivalent(a: &SyntaxNode, b: &SyntaxNode) -> bool {
+    if a.kind() != b.kind() {
+        return false;
+    }
+
+    if a.children().len() == 0 && b.children().len() == 0 {
+        return matches!(a.kind(), SyntaxKind::Space | SyntaxKind::Parbreak)
+            || a.text() == b.text();
+    }
+
+    let parent = a.kind();
+    let relevant = |child: &&SyntaxNode| match child.kind() {
+        SyntaxKind::Comma => false,
+        SyntaxKind::Semicolon => parent != SyntaxKind::Code,
+        SyntaxKind::Space => parent == SyntaxKind::Markup,
+        _ => true,
+    };
+
+    let mut left = a.children().filter(relevant);
+    let mut right = b.children().filter(relevant);
+    loop {
+        match (left.next(), right.next()) {
+            (Some(x), Some(y)) if equivalent(x, y) => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn test(text: &str, expected: &str) {
+        let config = FormatConfig { indent: 2, max_width: 40 };
+        let formatted = format(text, &config).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted, &config).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_code() {
+        test("#let x=1+ 2", "#let x = 1 + 2");
+        test("#set text( red,size:12pt )", "#set text(red, size: 12pt)");
+        test("#show heading:it=>emph(it)", "#show heading: it => emph(it)");
+        test("#(1 ,)  and #( : ) and #( a : 1 )", "#(1,)  and #(:) and #(a: 1)");
+        test("#if x{1}else{ 2 }", "#if x { 1 } else { 2 }");
+        test("#f(x)[ *a* ]", "#f(x)[ *a* ]");
+        test("#{ let x = 1; x }", "#{\n  let x = 1\n  x\n}");
+        test("#let f(x,y)=x", "#let f(x, y) = x");
+        test("#(a not  in b)", "#(a not in b)");
+    }
+
+    #[test]
+    fn test_format_breaking() {
+        test(
+            "#set page(paper: \"a4\", margin: 2cm, numbering: \"1\")",
+            "#set page(\n  paper: \"a4\",\n  margin: 2cm,\n  numbering: \"1\",\n)",
+        );
+        test(
+            "#{\n  let x = (aaaaaaaaaa, bbbbbbbbbb, cccccccccc)\n}",
+            "#{\n  let x = (\n    aaaaaaaaaa,\n    bbbbbbbbbb,\n    cccccccccc,\n  )\n}",
+        );
+        test(
+            "#show: doc => {\n set text(red)\n doc\n}",
+            "#show: doc => {\n  set text(red)\n  doc\n}",
+        );
+        test("#map(x => {\nx\n})", "#map(x => {\n  x\n})");
+    }
+
+    #[test]
+    fn test_format_comments() {
+        test(
+            "#{ // a\n  let x = 1 // b\n\n\n  // c\n  x; y\n}",
+            "#{\n  // a\n  let x = 1 // b\n\n  // c\n  x\n  y\n}",
+        );
+        test("#f(a, // b\nc)", "#f(\n  a, // b\n  c,\n)");
+    }
+
+    #[test]
+    fn test_format_overflow() {
+        test(
+            "#f(x, (aaaaaaaaaa, bbbbbbbbbb, cccccccccc))",
+            "#f(x, (\n  aaaaaaaaaa,\n  bbbbbbbbbb,\n  cccccccccc,\n))",
+        );
+
+        // Nested last items hug each other's parentheses, and the array with
+        // a single item keeps its trailing comma.
+        test(
+            "#f(((aaaaaaaaaa: 1, bbbbbbbbbb: 2, cccccccccc: 3),))",
+            "#f(((\n  aaaaaaaaaa: 1,\n  bbbbbbbbbb: 2,\n  cccccccccc: 3,\n),))",
+        );
+    }
+
+    #[test]
+    fn test_format_markup() {
+        test(
+            "= Heading  \nSome *text*   \n\n  $ x  +  y $",
+            "= Heading\nSome *text*\n\n  $ x  +  y $",
+        );
+        test("- a #f( x )\n  - b", "- a #f(x)\n  - b");
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert!(format("#f(", &FormatConfig::default()).is_err());
+    }
+}
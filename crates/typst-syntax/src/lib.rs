@@ -3,6 +3,7 @@
 pub mod ast;
 
 mod file;
+mod format;
 mod highlight;
 mod kind;
 mod lexer;
@@ -12,7 +13,11 @@ mod reparser;
 mod source;
 mod span;
 
-pub use self::file::{FileId, PackageSpec, PackageVersion, VirtualPath};
+pub use self::file::{
+    FileId, PackageInfo, PackageManifest, PackageSpec, PackageVersion, TemplateInfo,
+    VersionlessPackageSpec, VirtualPath,
+};
+pub use self::format::{format, FormatConfig};
 pub use self::highlight::{highlight, highlight_html, Tag};
 pub use self::kind::SyntaxKind;
 pub use self::lexer::{
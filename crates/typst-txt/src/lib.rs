@@ -0,0 +1,152 @@
+//! Exporting of laid-out documents as plain text.
+
+use typst::layout::{Abs, Frame, FrameItem, PageRanges, Point, Transform};
+use typst::model::Document;
+
+/// Separates the text of consecutive pages. Like in the output of other
+/// text extraction tools, this is a form feed.
+pub const PAGE_SEPARATOR: char = '\u{c}';
+
+/// How far apart the baselines of two consecutive lines have to be, relative
+/// to the font size, for a paragraph break to be inserted between them.
+///
+/// With Typst's default leading and paragraph spacing, consecutive lines of a
+/// paragraph are about 1.35em apart and paragraphs about 1.9em.
+const PARAGRAPH_BREAK: f64 = 1.6;
+
+/// How large a horizontal gap between two runs on the same line has to be,
+/// relative to the font size, for a space to be inserted between them.
+const WORD_GAP: f64 = 0.15;
+
+/// Export a document into plain text.
+///
+/// The text is reconstructed from the positions of the laid-out text runs:
+/// Runs that share a baseline form a line, larger vertical gaps between lines
+/// become blank lines, and pages are separated by [`PAGE_SEPARATOR`]. Runs are
+/// read in the order in which they were laid out, so that multi-column
+/// layouts keep their columns intact.
+///
+/// Only pages within the given ranges are exported. If `None`, all pages are.
+#[tracing::instrument(skip_all)]
+pub fn txt(document: &Document, page_ranges: Option<PageRanges>) -> String {
+    let mut output = String::new();
+    let pages = document.pages.iter().enumerate().filter(|&(i, _)| {
+        page_ranges
+            .as_ref()
+            .map_or(true, |ranges| ranges.includes_page_index(i))
+    });
+
+    for (i, (_, page)) in pages.enumerate() {
+        if i > 0 {
+            output.push(PAGE_SEPARATOR);
+        }
+        output.push_str(&txt_frame(page));
+    }
+
+    output
+}
+
+/// Export a single frame into plain text.
+///
+/// The text ends with a newline unless the frame contains no text at all.
+#[tracing::instrument(skip_all)]
+pub fn txt_frame(frame: &Frame) -> String {
+    let mut runs = vec![];
+    collect_runs(frame, Transform::identity(), &mut runs);
+
+    let mut output = String::new();
+    let mut prev: Option<Run> = None;
+    for run in runs {
+        if let Some(prev) = &prev {
+            let size = prev.size.max(run.size);
+            let tolerance = prev.size.min(run.size) * 0.5;
+            let advance = run.start.y - prev.start.y;
+            let same_line =
+                advance.abs() < tolerance && run.start.x > prev.end.x - tolerance;
+
+            if same_line {
+                if run.start.x - prev.end.x > size * WORD_GAP
+                    && !output.ends_with(char::is_whitespace)
+                    && !run.text.starts_with(char::is_whitespace)
+                {
+                    output.push(' ');
+                }
+            } else {
+                trim_end(&mut output);
+                output.push('\n');
+
+                // Moving upwards starts a new column or a float, which is
+                // separated like a new paragraph.
+                if advance < -tolerance || advance > size * PARAGRAPH_BREAK {
+                    output.push('\n');
+                }
+            }
+        }
+
+        output.push_str(run.text);
+        prev = Some(run);
+    }
+
+    trim_end(&mut output);
+    if !output.is_empty() {
+        output.push('\n');
+    }
+
+    output
+}
+
+/// A laid-out run of text.
+struct Run<'a> {
+    /// The text of the run.
+    text: &'a str,
+    /// The start of the run's baseline on the page.
+    start: Point,
+    /// The end of the run's baseline on the page.
+    end: Point,
+    /// The font size of the run.
+    size: Abs,
+}
+
+/// Collect the text runs of a frame and its subframes in layout order.
+fn collect_runs<'a>(frame: &'a Frame, ts: Transform, runs: &mut Vec<Run<'a>>) {
+    for (pos, item) in frame.items() {
+        let ts = ts.pre_concat(Transform::translate(pos.x, pos.y));
+        match item {
+            FrameItem::Group(group) => {
+                collect_runs(&group.frame, ts.pre_concat(group.transform), runs);
+            }
+            FrameItem::Text(text) => runs.push(Run {
+                text: &text.text,
+                start: Point::zero().transform(ts),
+                end: Point::with_x(text.width()).transform(ts),
+                size: text.size,
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Remove trailing spaces, but not line breaks, from the output.
+fn trim_end(output: &mut String) {
+    let len = output.trim_end_matches([' ', '\t']).len();
+    output.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use typst::layout::Size;
+
+    use super::*;
+
+    #[test]
+    fn test_txt_page_ranges() {
+        let pages = vec![Frame::soft(Size::zero()); 3];
+        let document = Document { pages, ..Default::default() };
+        assert_eq!(txt(&document, None), "\u{c}\u{c}");
+
+        let ranges = PageRanges::new(vec![NonZeroUsize::new(2)..=None]);
+        assert_eq!(txt(&document, Some(ranges)), "\u{c}");
+    }
+}
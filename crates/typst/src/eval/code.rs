@@ -2,7 +2,7 @@ use ecow::{eco_vec, EcoVec};
 
 use crate::diag::{bail, error, At, SourceDiagnostic, SourceResult};
 use crate::eval::{ops, Eval, Vm};
-use crate::foundations::{Array, Content, Dict, Str, Value};
+use crate::foundations::{sys, Array, Content, Dict, Str, Value};
 use crate::syntax::ast::{self, AstNode};
 
 impl Eval for ast::Code<'_> {
@@ -142,7 +142,20 @@ impl Eval for ast::Ident<'_> {
 
     #[tracing::instrument(name = "Ident::eval", skip_all)]
     fn eval(self, vm: &mut Vm) -> SourceResult<Self::Output> {
-        vm.scopes.get(&self).cloned().at(self.span())
+        let value = vm.scopes.get(&self).at(self.span())?;
+
+        // The world may replace the library's inputs. Closures capture the
+        // library's module, so we compare modules instead of names alone.
+        if self.as_str() == "sys" {
+            let library = vm.scopes.base.and_then(|base| base.global.scope().get("sys"));
+            if library == Some(value) {
+                if let Some(inputs) = vm.world().inputs() {
+                    return Ok(Value::Module(sys::module(inputs)));
+                }
+            }
+        }
+
+        Ok(value.clone())
     }
 }
 
@@ -15,7 +15,7 @@ use crate::foundations::{
     elem, func, scope, ty, Dict, Element, FromValue, Guard, IntoValue, Label,
     NativeElement, Recipe, Repr, Selector, Str, Style, Styles, Value,
 };
-use crate::introspection::{Location, Meta, MetaElem};
+use crate::introspection::{Location, Meta, MetaElem, Tag};
 use crate::layout::{Align, AlignElem, Axes, Length, MoveElem, PadElem, Rel, Sides};
 use crate::model::{Destination, EmphElem, StrongElem};
 use crate::syntax::Span;
@@ -492,6 +492,16 @@ impl Content {
         self.styled(MetaElem::set_data(smallvec![Meta::Elem(backlink)]))
     }
 
+    /// Mark the content as belonging to an element of the document's logical
+    /// structure, so that exporters can find the element's parts in the
+    /// frames.
+    ///
+    /// Should be used with a tag whose location is a [`Location::variant`] of a
+    /// [tagged](crate::introspection::Locator::tag) parent element.
+    pub fn attached(self, tag: Tag) -> Self {
+        self.styled(MetaElem::set_data(smallvec![Meta::Tag(tag)]))
+    }
+
     /// Set alignments for this content.
     pub fn aligned(self, align: Align) -> Self {
         self.styled(AlignElem::set_alignment(align))
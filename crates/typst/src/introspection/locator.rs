@@ -3,8 +3,11 @@ use std::collections::HashMap;
 
 use comemo::{Track, Tracked, Validate};
 
-use crate::introspection::{Location, Meta};
+use crate::foundations::Element;
+use crate::introspection::{Location, Meta, Tag};
 use crate::layout::{Frame, FrameItem};
+use crate::syntax::Span;
+use crate::util::hash128;
 
 /// Provides locations for elements in the document.
 ///
@@ -72,25 +75,39 @@ impl<'a> Locator<'a> {
         Location { hash, disambiguator, variant: 0 }
     }
 
+    /// Create a tag for an element of the document's logical structure.
+    ///
+    /// Unlike locatable elements, the element is not visible to introspection.
+    /// Instead, its parts are marked with [`Meta::Tag`] during layout, from
+    /// which exporters can recover the structure. Identifying the element by
+    /// its span instead of its contents keeps this cheap.
+    pub fn tag(&mut self, func: Element, span: Span) -> Tag {
+        Tag::new(func, self.locate(hash128(&(func, span))))
+    }
+
     /// Advance past a frame.
     pub fn visit_frame(&mut self, frame: &Frame) {
         for (_, item) in frame.items() {
             match item {
                 FrameItem::Group(group) => self.visit_frame(&group.frame),
                 FrameItem::Meta(Meta::Elem(elem), _) => {
-                    let hashes = self.hashes.get_mut();
-                    let loc = elem.location().unwrap();
-                    let entry = hashes.entry(loc.hash).or_default();
-
-                    // Next disambiguator needs to be at least one larger than
-                    // the maximum we've seen so far.
-                    *entry = (*entry).max(loc.disambiguator + 1);
+                    self.visit_location(elem.location().unwrap());
                 }
+                FrameItem::Meta(Meta::Tag(tag), _) => self.visit_location(tag.location),
                 _ => {}
             }
         }
     }
 
+    /// Advance past a location that was produced in a frame.
+    fn visit_location(&mut self, loc: Location) {
+        let entry = self.hashes.get_mut().entry(loc.hash).or_default();
+
+        // Next disambiguator needs to be at least one larger than
+        // the maximum we've seen so far.
+        *entry = (*entry).max(loc.disambiguator + 1);
+    }
+
     /// Advance past a number of frames.
     pub fn visit_frames<'b>(&mut self, frames: impl IntoIterator<Item = &'b Frame>) {
         for frame in frames {
@@ -10,6 +10,7 @@ mod metadata;
 #[path = "query.rs"]
 mod query_;
 mod state;
+mod tag;
 
 pub use self::counter::*;
 pub use self::introspector::*;
@@ -19,6 +20,7 @@ pub use self::locator::*;
 pub use self::metadata::*;
 pub use self::query_::*;
 pub use self::state::*;
+pub use self::tag::*;
 
 use std::fmt::{self, Debug, Formatter};
 
@@ -30,6 +32,7 @@ use crate::foundations::{
     Unlabellable,
 };
 use crate::layout::PdfPageLabel;
+use crate::model::pdf::FormField;
 use crate::model::{Destination, Numbering};
 
 /// Interactions between document parts.
@@ -79,10 +82,19 @@ pub enum Meta {
     /// An identifiable element that produces something within the area this
     /// metadata is attached to.
     Elem(Content),
+    /// An element of the document's logical structure that produces something
+    /// within the area this metadata is attached to.
+    ///
+    /// Unlike with `Elem`, the element is not visible to introspection. It
+    /// only serves exporters that tag the structure, like PDF export.
+    Tag(Tag),
     /// The numbering of the current page.
     PageNumbering(Option<Numbering>),
     /// A PDF page label of the current page.
     PdfPageLabel(PdfPageLabel),
+    /// An interactive form field whose static appearance follows in the same
+    /// frame.
+    FormField(FormField),
     /// Indicates that content should be hidden. This variant doesn't appear
     /// in the final frames as it is removed alongside the content that should
     /// be hidden.
@@ -98,8 +110,10 @@ impl Debug for Meta {
         match self {
             Self::Link(dest) => write!(f, "Link({dest:?})"),
             Self::Elem(content) => write!(f, "Elem({:?})", content.func()),
+            Self::Tag(tag) => write!(f, "Tag({:?})", tag.func),
             Self::PageNumbering(value) => write!(f, "PageNumbering({value:?})"),
             Self::PdfPageLabel(label) => write!(f, "PdfPageLabel({label:?})"),
+            Self::FormField(field) => write!(f, "FormField({:?})", field.name),
             Self::Hide => f.pad("Hide"),
         }
     }
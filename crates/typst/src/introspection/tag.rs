@@ -0,0 +1,49 @@
+use std::num::NonZeroUsize;
+
+use crate::foundations::Element;
+use crate::introspection::Location;
+
+/// Marks the parts of an element of the document's logical structure in the
+/// frames.
+///
+/// A tag only holds what exporters need to rebuild the structure, so that
+/// marking every frame of a paragraph, list, or table stays cheap.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Tag {
+    /// The element's function.
+    pub func: Element,
+    /// Identifies the element among all tagged and locatable elements.
+    pub location: Location,
+    /// Details of the element that matter for its structure.
+    pub kind: TagKind,
+}
+
+/// Details of a tagged element that matter for its structure.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TagKind {
+    /// An element that needs no details.
+    Plain,
+    /// A heading with its level.
+    Heading(NonZeroUsize),
+    /// A table cell with its column and row and whether it is a header cell.
+    Cell { x: usize, y: usize, header: bool },
+}
+
+impl Tag {
+    /// Create a tag for an element that needs no details.
+    pub fn new(func: Element, location: Location) -> Self {
+        Self { func, location, kind: TagKind::Plain }
+    }
+
+    /// Create a tag for the `i`-th part of the tagged element, like an item of
+    /// a list or a cell of a table.
+    pub fn part(&self, func: Element, i: usize) -> Self {
+        Self::new(func, self.location.variant(i + 1))
+    }
+
+    /// Add details to the tag.
+    pub fn with_kind(mut self, kind: TagKind) -> Self {
+        self.kind = kind;
+        self
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::{self, Debug, Formatter};
 
+use crate::introspection::{Meta, Tag};
 use crate::layout::Frame;
 
 /// A partial layout result.
@@ -50,6 +51,15 @@ impl Fragment {
     pub fn iter_mut(&mut self) -> std::slice::IterMut<Frame> {
         self.0.iter_mut()
     }
+
+    /// Mark the frames as parts of a [tagged](crate::introspection::Locator::tag)
+    /// element of the document's logical structure.
+    pub fn tagged(mut self, tag: Tag) -> Self {
+        for frame in &mut self.0 {
+            frame.meta_iter([Meta::Tag(tag)]);
+        }
+        self
+    }
 }
 
 impl Debug for Fragment {
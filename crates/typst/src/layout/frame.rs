@@ -298,7 +298,11 @@ impl Frame {
         }
         if hide {
             Arc::make_mut(&mut self.items).retain(|(_, item)| {
-                matches!(item, FrameItem::Group(_) | FrameItem::Meta(Meta::Elem(_), _))
+                matches!(
+                    item,
+                    FrameItem::Group(_)
+                        | FrameItem::Meta(Meta::Elem(_) | Meta::Tag(_), _)
+                )
             });
         }
     }
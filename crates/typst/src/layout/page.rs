@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
 use std::ptr;
 use std::str::FromStr;
 
@@ -750,6 +751,48 @@ impl Parity {
     }
 }
 
+/// A list of page ranges to be exported.
+///
+/// Both ends of a range are inclusive and one-based. An open end (`None`)
+/// extends the range to the first or last page, respectively.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PageRanges(Vec<PageRange>);
+
+/// A range of pages to export.
+///
+/// The range is one-indexed. For example, `1..=3` indicates the first, second
+/// and third pages should be exported.
+pub type PageRange = RangeInclusive<Option<NonZeroUsize>>;
+
+impl PageRanges {
+    /// Create new page ranges.
+    pub fn new(ranges: Vec<PageRange>) -> Self {
+        Self(ranges)
+    }
+
+    /// Check if a page, given its number, should be included when exporting
+    /// the document while restricting the exported pages to these page ranges.
+    /// This is the one-indexed version of [`Self::includes_page_index`].
+    pub fn includes_page(&self, page: NonZeroUsize) -> bool {
+        self.0.iter().any(|range| match (range.start(), range.end()) {
+            (Some(start), Some(end)) => (start..=end).contains(&&page),
+            (Some(start), None) => (start..).contains(&&page),
+            (None, Some(end)) => (..=end).contains(&&page),
+            (None, None) => true,
+        })
+    }
+
+    /// Check if a page, given its index, should be included when exporting
+    /// the document while restricting the exported pages to these page ranges.
+    /// This is the zero-indexed version of [`Self::includes_page`].
+    pub fn includes_page_index(&self, page: usize) -> bool {
+        let Some(page) = NonZeroUsize::try_from(page + 1).ok() else {
+            return false;
+        };
+        self.includes_page(page)
+    }
+}
+
 /// Specification of a paper.
 #[derive(Debug, Copy, Clone, Hash)]
 pub struct Paper {
@@ -963,3 +1006,42 @@ papers! {
     (PRESENTATION_16_9:    297.0, 167.0625, "presentation-16-9")
     (PRESENTATION_4_3:     280.0,    210.0, "presentation-4-3")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(n: usize) -> Option<NonZeroUsize> {
+        NonZeroUsize::new(n)
+    }
+
+    fn includes(ranges: &PageRanges, n: usize) -> bool {
+        ranges.includes_page(NonZeroUsize::new(n).unwrap())
+    }
+
+    #[test]
+    fn test_page_ranges_includes_page() {
+        let ranges = PageRanges::new(vec![page(2)..=page(3), page(6)..=page(6)]);
+        let included: Vec<usize> = (1..=8).filter(|&n| includes(&ranges, n)).collect();
+        assert_eq!(included, [2, 3, 6]);
+    }
+
+    #[test]
+    fn test_page_ranges_includes_page_open() {
+        let ranges = PageRanges::new(vec![None..=page(2), page(7)..=None]);
+        let included: Vec<usize> = (1..=9).filter(|&n| includes(&ranges, n)).collect();
+        assert_eq!(included, [1, 2, 7, 8, 9]);
+        assert!(includes(&PageRanges::new(vec![None..=None]), 100));
+        assert!(!includes(&PageRanges::new(vec![]), 1));
+    }
+
+    #[test]
+    fn test_page_ranges_includes_page_index() {
+        let ranges = PageRanges::new(vec![page(2)..=page(3)]);
+        assert!(!ranges.includes_page_index(0));
+        assert!(ranges.includes_page_index(1));
+        assert!(ranges.includes_page_index(2));
+        assert!(!ranges.includes_page_index(3));
+        assert!(!ranges.includes_page_index(usize::MAX));
+    }
+}
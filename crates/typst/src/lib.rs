@@ -66,7 +66,7 @@ use crate::diag::{warning, FileResult, SourceDiagnostic, SourceResult};
 use crate::engine::{Engine, Route};
 use crate::eval::Tracer;
 use crate::foundations::{
-    Array, Bytes, Content, Datetime, Module, Scope, StyleChain, Styles,
+    Array, Bytes, Content, Datetime, Dict, Module, Scope, StyleChain, Styles,
 };
 use crate::introspection::{Introspector, Locator};
 use crate::layout::{Align, Dir, LayoutRoot};
@@ -187,7 +187,8 @@ fn deduplicate(mut diags: EcoVec<SourceDiagnostic>) -> EcoVec<SourceDiagnostic>
 pub trait World {
     /// The standard library.
     ///
-    /// Can be created through `Library::build()`.
+    /// Can be created through `Library::build()` or, if further configuration
+    /// is needed, through `Library::builder()`.
     fn library(&self) -> &Prehashed<Library>;
 
     /// Metadata about all known fonts.
@@ -223,6 +224,17 @@ pub trait World {
     fn packages(&self) -> &[(PackageSpec, Option<EcoString>)] {
         &[]
     }
+
+    /// Values that replace the ones configured in the library for
+    /// `sys.inputs`.
+    ///
+    /// This function is optional to implement. Changing the library
+    /// invalidates all evaluated modules, while changing these values only
+    /// invalidates the modules that refer to `sys`. This makes it cheaper to
+    /// compile the same document many times with different inputs.
+    fn inputs(&self) -> Option<Dict> {
+        None
+    }
 }
 
 /// Helper methods on [`World`] implementations.
@@ -252,11 +264,14 @@ pub struct Library {
 }
 
 impl Library {
-    /// Construct the standard library.
+    /// Construct the standard library with default configuration.
     pub fn build() -> Self {
-        let math = math::module();
-        let global = global(math.clone());
-        Self { global, math, styles: Styles::new() }
+        Self::builder().build()
+    }
+
+    /// Create a new builder for a library.
+    pub fn builder() -> LibraryBuilder {
+        LibraryBuilder::default()
     }
 }
 
@@ -266,11 +281,35 @@ impl Default for Library {
     }
 }
 
+/// Configurable builder for the standard library.
+///
+/// This struct is created by [`Library::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct LibraryBuilder {
+    inputs: Option<Dict>,
+}
+
+impl LibraryBuilder {
+    /// Configure the inputs visible through `sys.inputs`.
+    pub fn with_inputs(mut self, inputs: Dict) -> Self {
+        self.inputs = Some(inputs);
+        self
+    }
+
+    /// Consumes the builder and returns a `Library`.
+    pub fn build(self) -> Library {
+        let math = math::module();
+        let inputs = self.inputs.unwrap_or_default();
+        let global = global(math.clone(), inputs);
+        Library { global, math, styles: Styles::new() }
+    }
+}
+
 /// Construct the module with global definitions.
 #[tracing::instrument(skip_all)]
-fn global(math: Module) -> Module {
+fn global(math: Module, inputs: Dict) -> Module {
     let mut global = Scope::deduplicating();
-    self::foundations::define(&mut global);
+    self::foundations::define(&mut global, inputs);
     self::model::define(&mut global);
     self::text::define(&mut global);
     global.reset_category();
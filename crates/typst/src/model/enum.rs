@@ -226,6 +226,7 @@ impl Layout for EnumElem {
                 .unwrap_or_else(|| *BlockElem::below_in(styles).amount())
         };
 
+        let tag = engine.locator.tag(Self::elem(), self.span());
         let mut cells = vec![];
         let mut number = self.start(styles);
         let mut parents = self.parents(styles);
@@ -237,7 +238,7 @@ impl Layout for EnumElem {
         // relation to the item it refers to.
         let number_align = self.number_align(styles);
 
-        for item in self.children() {
+        for (i, item) in self.children().iter().enumerate() {
             number = item.number(styles).unwrap_or(number);
 
             let resolved = if full {
@@ -259,10 +260,12 @@ impl Layout for EnumElem {
             let resolved =
                 resolved.aligned(number_align).styled(TextElem::set_overhang(false));
 
+            let body = item.body().clone().attached(tag.part(EnumItem::elem(), i));
+
             cells.push(Content::empty());
             cells.push(resolved);
             cells.push(Content::empty());
-            cells.push(item.body().clone().styled(Self::set_parents(Parent(number))));
+            cells.push(body.styled(Self::set_parents(Parent(number))));
             number = number.saturating_add(1);
         }
 
@@ -280,7 +283,7 @@ impl Layout for EnumElem {
             self.span(),
         );
 
-        Ok(layouter.layout(engine)?.fragment)
+        Ok(layouter.layout(engine)?.fragment.tagged(tag))
     }
 }
 
@@ -7,7 +7,7 @@ use crate::foundations::{
     cast, elem, scope, Content, Finalize, Label, NativeElement, Show, Smart, StyleChain,
     Synthesize,
 };
-use crate::introspection::{Count, Counter, CounterUpdate, Locatable, Location};
+use crate::introspection::{Count, Counter, CounterUpdate, Locatable, Location, Tag};
 use crate::layout::{Abs, Em, HElem, Length, Ratio};
 use crate::model::{Destination, Numbering, NumberingPattern, ParElem};
 use crate::text::{SuperElem, TextElem, TextSize};
@@ -288,12 +288,14 @@ impl Show for FootnoteEntry {
             .pack()
             .linked(Destination::Location(loc))
             .backlinked(loc.variant(1));
+        // The entry is tagged with another variant of the note's location.
         Ok(Content::sequence([
             HElem::new(self.indent(styles).into()).pack(),
             sup,
             HElem::new(number_gap.into()).with_weak(true).pack(),
             note.body_content().unwrap().clone(),
-        ]))
+        ])
+        .attached(Tag::new(Self::elem(), loc.variant(2))))
     }
 }
 
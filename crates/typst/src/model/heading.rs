@@ -6,7 +6,7 @@ use crate::foundations::{
     cast, elem, Content, Finalize, NativeElement, Show, Smart, StyleChain, Styles,
     Synthesize,
 };
-use crate::introspection::{Count, Counter, CounterUpdate, Locatable};
+use crate::introspection::{Count, Counter, CounterUpdate, Introspector, Locatable};
 use crate::layout::{BlockElem, Em, HElem, VElem};
 use crate::model::{Numbering, Outlinable, Refable, Supplement};
 use crate::text::{FontWeight, Lang, LocalName, Region, SpaceElem, TextElem, TextSize};
@@ -279,3 +279,119 @@ impl LocalName for HeadingElem {
         }
     }
 }
+
+/// A heading in the tree of bookmarked headings, which viewers show for
+/// navigation, like the outline panel of a PDF reader.
+#[derive(Debug, Clone)]
+pub struct HeadingNode {
+    /// The laid-out heading element.
+    pub element: Content,
+    /// The heading's level.
+    pub level: NonZeroUsize,
+    /// Whether the heading is bookmarked.
+    pub bookmarked: bool,
+    /// The bookmarked headings nested below this one.
+    pub children: Vec<HeadingNode>,
+}
+
+impl HeadingNode {
+    /// Build the tree of bookmarked headings in a document.
+    ///
+    /// Headings for which `include` returns `false` are left out entirely.
+    pub fn tree(
+        introspector: &Introspector,
+        mut include: impl FnMut(&Content) -> bool,
+    ) -> Vec<Self> {
+        let mut tree: Vec<HeadingNode> = vec![];
+
+        // Stores the level of the topmost skipped ancestor of the next bookmarked
+        // heading. A skipped heading is a heading with 'bookmarked: false', that
+        // is, it is not added to the PDF outline, and so is not in the tree.
+        // Therefore, its next descendant must be added at its level, which is
+        // enforced in the manner shown below.
+        let mut last_skipped_level = None;
+        for heading in introspector.query(&HeadingElem::elem().select()).iter() {
+            if !include(&**heading) {
+                continue;
+            }
+
+            let leaf = HeadingNode::leaf((**heading).clone());
+
+            if leaf.bookmarked {
+                let mut children = &mut tree;
+
+                // Descend the tree through the latest bookmarked heading of each
+                // level until either:
+                // - you reach a node whose children would be brothers of this
+                // heading (=> add the current heading as a child of this node);
+                // - you reach a node with no children (=> this heading probably
+                // skipped a few nesting levels in Typst, or one or more ancestors
+                // of this heading weren't bookmarked, so add it as a child of this
+                // node, which is its deepest bookmarked ancestor);
+                // - or, if the latest heading(s) was(/were) skipped
+                // ('bookmarked: false'), then stop if you reach a node whose
+                // children would be brothers of the latest skipped heading
+                // of lowest level (=> those skipped headings would be ancestors
+                // of the current heading, so add it as a 'brother' of the least
+                // deep skipped ancestor among them, as those ancestors weren't
+                // added to the bookmark tree, and the current heading should not
+                // be mistakenly added as a descendant of a brother of that
+                // ancestor.)
+                //
+                // That is, if you had a bookmarked heading of level N, a skipped
+                // heading of level N, a skipped heading of level N + 1, and then
+                // a bookmarked heading of level N + 2, that last one is bookmarked
+                // as a level N heading (taking the place of its topmost skipped
+                // ancestor), so that it is not mistakenly added as a descendant of
+                // the previous level N heading.
+                //
+                // In other words, a heading can be added to the bookmark tree
+                // at most as deep as its topmost skipped direct ancestor (if it
+                // exists), or at most as deep as its actual nesting level in Typst
+                // (not exceeding whichever is the most restrictive depth limit
+                // of those two).
+                while children.last().map_or(false, |last| {
+                    last_skipped_level.map_or(true, |l| last.level < l)
+                        && last.level < leaf.level
+                }) {
+                    children = &mut children.last_mut().unwrap().children;
+                }
+
+                // Since this heading was bookmarked, the next heading, if it is a
+                // child of this one, won't have a skipped direct ancestor (indeed,
+                // this heading would be its most direct ancestor, and wasn't
+                // skipped). Therefore, it can be added as a child of this one, if
+                // needed, following the usual rules listed above.
+                last_skipped_level = None;
+                children.push(leaf);
+            } else if last_skipped_level.map_or(true, |l| leaf.level < l) {
+                // Only the topmost / lowest-level skipped heading matters when you
+                // have consecutive skipped headings (since none of them are being
+                // added to the bookmark tree), hence the condition above.
+                // This ensures the next bookmarked heading will be placed
+                // at most as deep as its topmost skipped ancestors. Deeper
+                // ancestors do not matter as the nesting structure they create
+                // won't be visible in the PDF outline.
+                last_skipped_level = Some(leaf.level);
+            }
+        }
+
+        tree
+    }
+
+    /// Create a node without children for a heading element.
+    ///
+    /// The heading counts as bookmarked if its `bookmarked` field is `true` or
+    /// if it is `auto` and the heading is outlined.
+    fn leaf(element: Content) -> Self {
+        HeadingNode {
+            level: element.expect_field_by_name::<NonZeroUsize>("level"),
+            // 'bookmarked' set to 'auto' falls back to the value of 'outlined'.
+            bookmarked: element
+                .expect_field_by_name::<Smart<bool>>("bookmarked")
+                .unwrap_or_else(|| element.expect_field_by_name::<bool>("outlined")),
+            element,
+            children: Vec::new(),
+        }
+    }
+}
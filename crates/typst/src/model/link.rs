@@ -51,6 +51,11 @@ pub struct LinkElem {
     ///     `y` coordinates of type [length]($length). Pages are counted from
     ///     one, and the coordinates are relative to the page's top left corner.
     ///
+    /// - To link to a labelled element in another PDF file, `dest` can be a
+    ///   relative path to the file followed by `#` and the label's name, like
+    ///   `{"report.pdf#results"}`. In PDF export, labelled elements become
+    ///   named destinations that such links can point to.
+    ///
     /// ```example
     /// = Introduction <intro>
     /// #link("mailto:hello@typst.app") \
@@ -151,12 +151,15 @@ impl Layout for ListElem {
             // avoid '#set align' interference with the list
             .aligned(HAlign::Start + VAlign::Top);
 
+        let tag = engine.locator.tag(Self::elem(), self.span());
         let mut cells = vec![];
-        for item in self.children() {
+        for (i, item) in self.children().iter().enumerate() {
+            let body = item.body().clone().attached(tag.part(ListItem::elem(), i));
+
             cells.push(Content::empty());
             cells.push(marker.clone());
             cells.push(Content::empty());
-            cells.push(item.body().clone().styled(Self::set_depth(Depth)));
+            cells.push(body.styled(Self::set_depth(Depth)));
         }
 
         let layouter = GridLayouter::new(
@@ -173,7 +176,7 @@ impl Layout for ListElem {
             self.span(),
         );
 
-        Ok(layouter.layout(engine)?.fragment)
+        Ok(layouter.layout(engine)?.fragment.tagged(tag))
     }
 }
 
@@ -1,5 +1,7 @@
 //! Structuring elements that define the document model.
 
+pub mod pdf;
+
 mod bibliography;
 mod cite;
 mod document;
@@ -72,4 +74,5 @@ pub fn define(global: &mut Scope) {
     global.define_elem::<EmphElem>();
     global.define_elem::<StrongElem>();
     global.define_func::<numbering>();
+    global.define_module(pdf::module());
 }
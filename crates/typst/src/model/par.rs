@@ -133,14 +133,16 @@ impl ParElem {
         region: Size,
         expand: bool,
     ) -> SourceResult<Fragment> {
-        crate::layout::layout_inline(
+        let tag = engine.locator.tag(Self::elem(), self.span());
+        let fragment = crate::layout::layout_inline(
             self.children(),
             engine,
             styles,
             consecutive,
             region,
             expand,
-        )
+        )?;
+        Ok(fragment.tagged(tag))
     }
 }
 
@@ -0,0 +1,112 @@
+use ecow::EcoString;
+
+use crate::diag::{At, SourceResult};
+use crate::engine::Engine;
+use crate::foundations::{
+    elem, Behave, Behaviour, Bytes, Cast, Content, Show, Smart, StyleChain, Synthesize,
+};
+use crate::introspection::Locatable;
+use crate::syntax::Spanned;
+use crate::World;
+
+/// A file that is embedded into the exported PDF.
+///
+/// This can be used to distribute files that belong to the document, like raw
+/// data or source code, within the PDF itself. PDF readers list the embedded
+/// files in an attachment panel. Some standards, like ZUGFeRD and Factur-X for
+/// invoices, use this mechanism to attach machine-readable data that mirrors
+/// the visual content of the document.
+///
+/// The embedded files of a document can be queried like other locatable
+/// elements. They have no effect on other export formats.
+///
+/// # Example
+/// ```typ
+/// #pdf.embed(
+///   "experiment.csv",
+///   relationship: "supplement",
+///   mime-type: "text/csv",
+///   description: "Raw oxygen readings from the Arctic experiment",
+/// )
+/// ```
+#[elem(Behave, Synthesize, Show, Locatable)]
+pub struct EmbedElem {
+    /// Path to the file to embed. Its last component becomes the name of the
+    /// attachment.
+    ///
+    /// The file is only read from this path if no `data` is given.
+    #[required]
+    #[parse(
+        let Spanned { v: path, span } =
+            args.expect::<Spanned<EcoString>>("path to the file to embed")?;
+        let id = span.resolve_path(&path).at(span)?;
+        path
+    )]
+    #[borrowed]
+    pub path: EcoString,
+
+    /// The raw file data. If omitted, the data is read from `path`.
+    #[required]
+    #[parse(
+        match args.find::<Bytes>()? {
+            Some(data) => data,
+            None => engine.world.file(id).at(span)?,
+        }
+    )]
+    pub data: Bytes,
+
+    /// How the file relates to the document.
+    pub relationship: Smart<EmbedRelationship>,
+
+    /// The file's MIME type, like `{"text/csv"}`.
+    pub mime_type: Option<EcoString>,
+
+    /// A description of the file, which PDF readers show in their attachment
+    /// panel.
+    pub description: Option<EcoString>,
+}
+
+impl EmbedElem {
+    /// The name of the attachment, which is the last component of its path.
+    pub fn name(&self) -> &str {
+        let path = self.path().as_str();
+        path.rsplit(['/', '\\']).next().unwrap_or(path)
+    }
+}
+
+impl Synthesize for EmbedElem {
+    fn synthesize(&mut self, _: &mut Engine, styles: StyleChain) -> SourceResult<()> {
+        self.push_relationship(self.relationship(styles));
+        self.push_mime_type(self.mime_type(styles));
+        self.push_description(self.description(styles));
+        Ok(())
+    }
+}
+
+impl Show for EmbedElem {
+    fn show(&self, _: &mut Engine, _: StyleChain) -> SourceResult<Content> {
+        Ok(Content::empty())
+    }
+}
+
+impl Behave for EmbedElem {
+    fn behaviour(&self) -> Behaviour {
+        Behaviour::Invisible
+    }
+}
+
+/// How an embedded file relates to the document.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Cast)]
+pub enum EmbedRelationship {
+    /// The file is the original source of the document's content, like the
+    /// Typst file it was compiled from.
+    Source,
+    /// The file contains data that the document's content was derived from,
+    /// like the values of a table or chart.
+    Data,
+    /// The file is an alternative representation of the document's content,
+    /// like a machine-readable invoice.
+    Alternative,
+    /// The file supplements the document's content, like an appendix.
+    Supplement,
+}
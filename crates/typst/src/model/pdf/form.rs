@@ -0,0 +1,550 @@
+use comemo::Prehashed;
+use ecow::EcoString;
+
+use crate::diag::{bail, SourceResult};
+use crate::engine::Engine;
+use crate::foundations::{
+    elem, Content, Guard, NativeElement, Resolve, Show, Smart, StyleChain,
+};
+use crate::introspection::Meta;
+use crate::layout::{
+    Abs, Axes, BoxElem, Corners, Em, Fragment, Frame, FrameItem, FrameKind, Layout,
+    Length, Point, Regions, Rel, Sides, Size,
+};
+use crate::syntax::Span;
+use crate::text::{families, variant, Font, TextElem};
+use crate::visualize::{
+    ellipse, Color, FixedStroke, Geometry, LineCap, LineJoin, Paint, Path, Stroke,
+};
+
+/// A text field in a fillable PDF form.
+///
+/// Readers of the exported PDF can type into the field. It is laid out like a
+/// [box]($box) that shows the field's current value in the current text
+/// style. Other export formats show this static appearance. Readers show typed
+/// text in a built-in font that resembles the current one, or in the current
+/// font itself if the field's value needs characters that built-in fonts lack.
+///
+/// # Example
+/// ```example
+/// Name: #pdf.text-field("name") \
+/// City: #pdf.text-field(
+///   "city",
+///   value: "Berlin",
+///   width: 4cm,
+/// )
+/// ```
+#[elem(Show, Layout)]
+pub struct TextFieldElem {
+    /// The name of the field. It identifies the field's value when the form's
+    /// data is extracted. Text fields with the same name share their value,
+    /// so they must have the same value and text style.
+    #[required]
+    pub name: EcoString,
+
+    /// The text the field is filled with initially.
+    pub value: EcoString,
+
+    /// Whether the field accepts multiple lines of text.
+    ///
+    /// ```example
+    /// #pdf.text-field(
+    ///   "comments",
+    ///   multiline: true,
+    ///   height: 3em,
+    /// )
+    /// ```
+    #[default(false)]
+    pub multiline: bool,
+
+    /// The width of the field.
+    #[default(Length::from(Em::new(10.0)).into())]
+    pub width: Rel<Length>,
+
+    /// The height of the field. If `{auto}`, the field is as high as its
+    /// value, but at least one line.
+    pub height: Smart<Rel<Length>>,
+
+    /// The field's background color.
+    pub fill: Option<Paint>,
+
+    /// The field's border. Defaults to a one point thick black line.
+    #[resolve]
+    #[fold]
+    #[default(Some(Stroke::default()))]
+    pub stroke: Option<Stroke>,
+
+    /// How much to pad the field's value.
+    #[resolve]
+    #[fold]
+    #[default(Sides::splat(Abs::pt(2.0).into()))]
+    pub inset: Sides<Option<Rel<Length>>>,
+}
+
+impl Show for TextFieldElem {
+    #[tracing::instrument(name = "TextFieldElem::show", skip_all)]
+    fn show(&self, _: &mut Engine, _: StyleChain) -> SourceResult<Content> {
+        Ok(inline(self.clone().pack().guarded(Guard::Base(Self::elem()))))
+    }
+}
+
+impl Layout for TextFieldElem {
+    #[tracing::instrument(name = "TextFieldElem::layout", skip_all)]
+    fn layout(
+        &self,
+        engine: &mut Engine,
+        styles: StyleChain,
+        regions: Regions,
+    ) -> SourceResult<Fragment> {
+        let value = self.value(styles);
+        let appearance = layout_text_appearance(
+            engine,
+            styles,
+            regions,
+            Axes::new(Smart::Custom(self.width(styles)), self.height(styles)),
+            TextElem::packed(value.clone()),
+            self.fill(styles),
+            self.stroke(styles),
+            self.inset(styles),
+            self.span(),
+        )?;
+
+        let kind = FormFieldKind::Text { value, multiline: self.multiline(styles) };
+        Ok(finish(
+            engine,
+            styles,
+            self.name().clone(),
+            kind,
+            appearance,
+            None,
+            self.span(),
+        ))
+    }
+}
+
+/// A checkbox in a fillable PDF form.
+///
+/// Readers of the exported PDF can check and uncheck the box. The check mark
+/// is drawn in the current text color.
+///
+/// # Example
+/// ```example
+/// #pdf.checkbox("newsletter", checked: true)
+/// Subscribe to the newsletter
+/// ```
+#[elem(Show, Layout)]
+pub struct CheckboxElem {
+    /// The name of the field. It identifies the field's value when the form's
+    /// data is extracted.
+    #[required]
+    pub name: EcoString,
+
+    /// Whether the box is checked initially.
+    #[default(false)]
+    pub checked: bool,
+
+    /// The width and height of the box.
+    #[resolve]
+    #[default(Em::new(0.8).into())]
+    pub size: Length,
+
+    /// The box's background color.
+    pub fill: Option<Paint>,
+
+    /// The box's border. Defaults to a one point thick black line.
+    #[resolve]
+    #[fold]
+    #[default(Some(Stroke::default()))]
+    pub stroke: Option<Stroke>,
+}
+
+impl Show for CheckboxElem {
+    #[tracing::instrument(name = "CheckboxElem::show", skip_all)]
+    fn show(&self, _: &mut Engine, _: StyleChain) -> SourceResult<Content> {
+        Ok(inline(self.clone().pack().guarded(Guard::Base(Self::elem()))))
+    }
+}
+
+impl Layout for CheckboxElem {
+    #[tracing::instrument(name = "CheckboxElem::layout", skip_all)]
+    fn layout(
+        &self,
+        engine: &mut Engine,
+        styles: StyleChain,
+        _: Regions,
+    ) -> SourceResult<Fragment> {
+        let size = Size::splat(self.size(styles));
+        let stroke = self.stroke(styles).map(Stroke::unwrap_or_default);
+
+        let mut off = Frame::hard(size);
+        off.fill_and_stroke(
+            self.fill(styles),
+            Sides::splat(stroke),
+            Sides::splat(Rel::zero()),
+            Corners::splat(Rel::zero()),
+            self.span(),
+        );
+
+        let mut mark = Path::new();
+        mark.move_to(Point::new(size.x * 0.2, size.y * 0.5));
+        mark.line_to(Point::new(size.x * 0.42, size.y * 0.72));
+        mark.line_to(Point::new(size.x * 0.8, size.y * 0.28));
+
+        let mut on = off.clone();
+        let stroke = FixedStroke {
+            paint: TextElem::fill_in(styles),
+            thickness: size.x / 8.0,
+            line_cap: LineCap::Round,
+            line_join: LineJoin::Round,
+            ..FixedStroke::default()
+        };
+        on.push(
+            Point::zero(),
+            FrameItem::Shape(Geometry::Path(mark).stroked(stroke), self.span()),
+        );
+
+        let kind = FormFieldKind::Checkbox { checked: self.checked(styles) };
+        Ok(finish(engine, styles, self.name().clone(), kind, on, Some(off), self.span()))
+    }
+}
+
+/// A radio button in a fillable PDF form.
+///
+/// Radio buttons with the same name form a group in which readers of the
+/// exported PDF can select one button. The selected button determines the
+/// group's value. The dot of a selected button is drawn in the current text
+/// color.
+///
+/// # Example
+/// ```example
+/// #pdf.radio("size", "small") Small
+/// #pdf.radio("size", "medium", checked: true) Medium
+/// #pdf.radio("size", "large") Large
+/// ```
+#[elem(title = "Radio Button", Show, Layout)]
+pub struct RadioElem {
+    /// The name of the group the button belongs to.
+    #[required]
+    pub name: EcoString,
+
+    /// The value the group takes on when this button is selected.
+    #[required]
+    pub value: EcoString,
+
+    /// Whether the button is selected initially. At most one button of a
+    /// group may be selected.
+    #[default(false)]
+    pub checked: bool,
+
+    /// The diameter of the button.
+    #[resolve]
+    #[default(Em::new(0.8).into())]
+    pub size: Length,
+
+    /// The button's background color.
+    pub fill: Option<Paint>,
+
+    /// The button's border. Defaults to a one point thick black line.
+    #[resolve]
+    #[fold]
+    #[default(Some(Stroke::default()))]
+    pub stroke: Option<Stroke>,
+}
+
+impl Show for RadioElem {
+    #[tracing::instrument(name = "RadioElem::show", skip_all)]
+    fn show(&self, _: &mut Engine, _: StyleChain) -> SourceResult<Content> {
+        Ok(inline(self.clone().pack().guarded(Guard::Base(Self::elem()))))
+    }
+}
+
+impl Layout for RadioElem {
+    #[tracing::instrument(name = "RadioElem::layout", skip_all)]
+    fn layout(
+        &self,
+        engine: &mut Engine,
+        styles: StyleChain,
+        _: Regions,
+    ) -> SourceResult<Fragment> {
+        let size = self.size(styles);
+        let stroke = self.stroke(styles).map(Stroke::unwrap_or_default);
+
+        let mut off = Frame::hard(Size::splat(size));
+        let circle = ellipse(Size::splat(size), self.fill(styles), stroke);
+        off.push(Point::zero(), FrameItem::Shape(circle, self.span()));
+
+        let mut on = off.clone();
+        let dot = ellipse(Size::splat(size / 2.0), Some(TextElem::fill_in(styles)), None);
+        on.push(Point::splat(size / 4.0), FrameItem::Shape(dot, self.span()));
+
+        let kind = FormFieldKind::Radio {
+            value: self.value().clone(),
+            checked: self.checked(styles),
+        };
+        Ok(finish(engine, styles, self.name().clone(), kind, on, Some(off), self.span()))
+    }
+}
+
+/// A dropdown in a fillable PDF form.
+///
+/// Readers of the exported PDF can choose one of the dropdown's options.
+///
+/// # Example
+/// ```example
+/// Country: #pdf.dropdown(
+///   "country",
+///   ("Germany", "France", "Italy"),
+///   value: "France",
+/// )
+/// ```
+#[elem(Show, Layout)]
+pub struct DropdownElem {
+    /// The name of the field. It identifies the field's value when the form's
+    /// data is extracted.
+    #[required]
+    pub name: EcoString,
+
+    /// The options to choose from.
+    #[required]
+    pub options: Vec<EcoString>,
+
+    /// The option that is chosen initially. If `{none}`, the first option is
+    /// chosen.
+    pub value: Option<EcoString>,
+
+    /// The width of the dropdown.
+    #[default(Length::from(Em::new(10.0)).into())]
+    pub width: Rel<Length>,
+
+    /// The height of the dropdown. If `{auto}`, the dropdown is as high as
+    /// its value.
+    pub height: Smart<Rel<Length>>,
+
+    /// The dropdown's background color.
+    pub fill: Option<Paint>,
+
+    /// The dropdown's border. Defaults to a one point thick black line.
+    #[resolve]
+    #[fold]
+    #[default(Some(Stroke::default()))]
+    pub stroke: Option<Stroke>,
+
+    /// How much to pad the dropdown's value.
+    #[resolve]
+    #[fold]
+    #[default(Sides::splat(Abs::pt(2.0).into()))]
+    pub inset: Sides<Option<Rel<Length>>>,
+}
+
+impl Show for DropdownElem {
+    #[tracing::instrument(name = "DropdownElem::show", skip_all)]
+    fn show(&self, _: &mut Engine, _: StyleChain) -> SourceResult<Content> {
+        Ok(inline(self.clone().pack().guarded(Guard::Base(Self::elem()))))
+    }
+}
+
+impl Layout for DropdownElem {
+    #[tracing::instrument(name = "DropdownElem::layout", skip_all)]
+    fn layout(
+        &self,
+        engine: &mut Engine,
+        styles: StyleChain,
+        regions: Regions,
+    ) -> SourceResult<Fragment> {
+        let options = self.options();
+        let value = match self.value(styles) {
+            Some(value) if !options.contains(&value) => {
+                bail!(self.span(), "value must be one of the dropdown's options")
+            }
+            Some(value) => value,
+            None => match options.first() {
+                Some(first) => first.clone(),
+                None => bail!(self.span(), "dropdown must have at least one option"),
+            },
+        };
+
+        // Leave room for the arrow on the right.
+        let arrow = TextElem::size_in(styles) * 0.5;
+        let mut inset = self.inset(styles);
+        inset.right = inset.right + Rel::from(arrow * 2.0);
+
+        let mut appearance = layout_text_appearance(
+            engine,
+            styles,
+            regions,
+            Axes::new(Smart::Custom(self.width(styles)), self.height(styles)),
+            TextElem::packed(value.clone()),
+            self.fill(styles),
+            self.stroke(styles),
+            inset,
+            self.span(),
+        )?;
+
+        let size = appearance.size();
+        let x = size.x - arrow * 1.5;
+        let y = size.y / 2.0;
+        let mut path = Path::new();
+        path.move_to(Point::new(x, y - arrow / 4.0));
+        path.line_to(Point::new(x + arrow, y - arrow / 4.0));
+        path.line_to(Point::new(x + arrow / 2.0, y + arrow / 4.0));
+        path.close_path();
+        let shape = Geometry::Path(path).filled(TextElem::fill_in(styles));
+        appearance.push(Point::zero(), FrameItem::Shape(shape, self.span()));
+
+        let kind = FormFieldKind::Dropdown { options: options.clone(), value };
+        Ok(finish(
+            engine,
+            styles,
+            self.name().clone(),
+            kind,
+            appearance,
+            None,
+            self.span(),
+        ))
+    }
+}
+
+/// An interactive form field, as laid out into a frame.
+///
+/// The frame of a field shows its static appearance. Exporters that support
+/// forms replace it with an interactive widget.
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct FormField {
+    /// The name of the field.
+    pub name: EcoString,
+    /// What kind of field this is, with its initial value.
+    pub kind: FormFieldKind,
+    /// How the field looks. For checkboxes and radio buttons, this is how
+    /// they look when checked.
+    pub appearance: Prehashed<Frame>,
+    /// How a checkbox or radio button looks when it is not checked.
+    pub off: Option<Prehashed<Frame>>,
+    /// The size of text typed into the field.
+    pub text_size: Abs,
+    /// The color of text typed into the field.
+    pub text_fill: Color,
+    /// The font that text typed into the field should look like, if the text
+    /// style selects one.
+    pub text_font: Option<Font>,
+    /// The span of the element the field was created from.
+    pub span: Span,
+}
+
+impl FormField {
+    /// How the field looks initially.
+    pub fn current(&self) -> &Frame {
+        match (&self.kind, &self.off) {
+            (FormFieldKind::Checkbox { checked: false }, Some(off))
+            | (FormFieldKind::Radio { checked: false, .. }, Some(off)) => off,
+            _ => &self.appearance,
+        }
+    }
+}
+
+/// A kind of form field.
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub enum FormFieldKind {
+    /// A text field.
+    Text { value: EcoString, multiline: bool },
+    /// A checkbox.
+    Checkbox { checked: bool },
+    /// A radio button that sets its group's value to `value` when selected.
+    Radio { value: EcoString, checked: bool },
+    /// A dropdown with its options and the chosen one.
+    Dropdown { options: Vec<EcoString>, value: EcoString },
+}
+
+/// Wrap a field into a box so that it flows with the surrounding text.
+fn inline(field: Content) -> Content {
+    let span = field.span();
+    BoxElem::new().with_body(Some(field)).pack().spanned(span)
+}
+
+/// Lay out the appearance of a field that shows text like a box.
+#[allow(clippy::too_many_arguments)]
+fn layout_text_appearance(
+    engine: &mut Engine,
+    styles: StyleChain,
+    regions: Regions,
+    sizing: Axes<Smart<Rel<Length>>>,
+    body: Content,
+    fill: Option<Paint>,
+    stroke: Option<Stroke<Abs>>,
+    inset: Sides<Rel<Abs>>,
+    span: Span,
+) -> SourceResult<Frame> {
+    // Resolve the sizing to a concrete size.
+    let expand = sizing.as_ref().map(Smart::is_custom);
+    let size = sizing
+        .resolve(styles)
+        .zip_map(regions.base(), |s, b| s.map(|v| v.relative_to(b)))
+        .unwrap_or(regions.base());
+
+    let body = body.padded(inset.map(|side| side.map(Length::from)));
+    let pod = Regions::one(size, expand);
+    let mut frame = body.layout(engine, styles, pod)?.into_frame();
+    *frame.size_mut() = expand.select(size, frame.size());
+
+    // An empty field is still as high as a line of text.
+    if sizing.y.is_auto() {
+        let padding = inset.relative_to(frame.size()).sum_by_axis().y;
+        let min = TextElem::size_in(styles) + padding;
+        frame.size_mut().y.set_max(min);
+    }
+
+    frame.clip(Path::rect(frame.size()));
+    frame.fill_and_stroke(
+        fill,
+        Sides::splat(stroke.map(Stroke::unwrap_or_default)),
+        Sides::splat(Rel::zero()),
+        Corners::splat(Rel::zero()),
+        span,
+    );
+
+    Ok(frame)
+}
+
+/// Turn a field's appearances into a frame that shows how it looks initially
+/// and carries the field for exporters that support forms.
+fn finish(
+    engine: &mut Engine,
+    styles: StyleChain,
+    name: EcoString,
+    kind: FormFieldKind,
+    appearance: Frame,
+    off: Option<Frame>,
+    span: Span,
+) -> Fragment {
+    let text_fill = match TextElem::fill_in(styles) {
+        Paint::Solid(color) => color,
+        Paint::Gradient(_) | Paint::Pattern(_) => Color::BLACK,
+    };
+
+    let variant = variant(styles);
+    let world = engine.world;
+    let text_font = families(styles).find_map(|family| {
+        let id = world.book().select(family, variant)?;
+        world.font(id)
+    });
+
+    let field = FormField {
+        name,
+        kind,
+        appearance: Prehashed::new(appearance),
+        off: off.map(Prehashed::new),
+        text_size: TextElem::size_in(styles),
+        text_fill,
+        text_font,
+        span,
+    };
+
+    // The field's metadata precedes its appearance so that exporters can
+    // replace the rest of the frame with an interactive widget.
+    let mut frame = field.current().clone();
+    let size = frame.size();
+    frame.prepend(Point::zero(), FrameItem::Meta(Meta::FormField(field), size));
+    frame.set_kind(FrameKind::Hard);
+    frame.meta(styles, false);
+
+    Fragment::frame(frame)
+}
@@ -0,0 +1,20 @@
+//! PDF-specific functionality.
+
+mod embed;
+mod form;
+
+pub use self::embed::*;
+pub use self::form::*;
+
+use crate::foundations::{Module, Scope};
+
+/// A module with PDF-specific definitions.
+pub fn module() -> Module {
+    let mut scope = Scope::deduplicating();
+    scope.define_elem::<EmbedElem>();
+    scope.define_elem::<TextFieldElem>();
+    scope.define_elem::<CheckboxElem>();
+    scope.define_elem::<RadioElem>();
+    scope.define_elem::<DropdownElem>();
+    Module::new("pdf", scope)
+}
@@ -4,6 +4,7 @@ use crate::foundations::{
     elem, Array, CastInfo, Content, FromValue, Func, IntoValue, NativeElement, Reflect,
     Smart, StyleChain, Value,
 };
+use crate::introspection::TagKind;
 use crate::layout::{
     Abs, Align, AlignElem, Axes, Fragment, FrameItem, GridLayouter, Layout, Length,
     Point, Regions, Rel, Sides, Size, TrackSizings,
@@ -150,11 +151,45 @@ pub struct TableElem {
     #[default(Sides::splat(Abs::pt(5.0).into()))]
     pub inset: Sides<Option<Rel<Length>>>,
 
+    /// How many rows at the top of the table are header rows.
+    ///
+    /// This doesn't change the table's appearance, but HTML and PDF export mark
+    /// the cells of these rows as headers for the columns below them, which
+    /// helps screen readers to make sense of the table.
+    ///
+    /// ```example
+    /// #table(
+    ///   columns: 2,
+    ///   header-rows: 1,
+    ///   [*Name*], [*Age*],
+    ///   [Alice], [32],
+    ///   [Bob], [27],
+    /// )
+    /// ```
+    #[default(0)]
+    pub header_rows: usize,
+
     /// The contents of the table cells.
     #[variadic]
     pub children: Vec<Content>,
 }
 
+impl TableElem {
+    /// The table's cells in the order of its children, each with its position
+    /// and whether it is a header cell.
+    pub fn cells<'a>(
+        &'a self,
+        styles: StyleChain,
+    ) -> impl Iterator<Item = (TableCellElem, &'a Content)> + 'a {
+        let cols = self.columns(styles).0.len().max(1);
+        let header_rows = self.header_rows(styles);
+        self.children().iter().enumerate().map(move |(i, child)| {
+            let (x, y) = (i % cols, i / cols);
+            (TableCellElem::new(x, y, y < header_rows), child)
+        })
+    }
+}
+
 impl Layout for TableElem {
     #[tracing::instrument(name = "TableElem::layout", skip_all)]
     fn layout(
@@ -172,16 +207,18 @@ impl Layout for TableElem {
 
         let tracks = Axes::new(columns.0.as_slice(), rows.0.as_slice());
         let gutter = Axes::new(column_gutter.0.as_slice(), row_gutter.0.as_slice());
-        let cols = tracks.x.len().max(1);
+        let tag = engine.locator.tag(Self::elem(), self.span());
         let cells: Vec<_> = self
-            .children()
-            .iter()
+            .cells(styles)
             .enumerate()
-            .map(|(i, child)| {
+            .map(|(i, (cell, child))| {
                 let mut child = child.clone().padded(inset);
 
-                let x = i % cols;
-                let y = i / cols;
+                let (x, y) = (*cell.x(), *cell.y());
+                let kind = TagKind::Cell { x, y, header: *cell.header() };
+                child =
+                    child.attached(tag.part(TableCellElem::elem(), i).with_kind(kind));
+
                 if let Smart::Custom(alignment) = align.resolve(engine, x, y)? {
                     child = child.styled(AlignElem::set_alignment(alignment));
                 }
@@ -249,7 +286,7 @@ impl Layout for TableElem {
             }
         }
 
-        Ok(layout.fragment)
+        Ok(layout.fragment.tagged(tag))
     }
 }
 
@@ -368,3 +405,22 @@ impl LocalName for TableElem {
 }
 
 impl Figurable for TableElem {}
+
+/// A cell of a table.
+///
+/// Table layout attaches this to the content of each cell, so that exporters
+/// can recover the table's rows and header cells from the frames.
+#[elem]
+pub struct TableCellElem {
+    /// The cell's column.
+    #[required]
+    pub x: usize,
+
+    /// The cell's row.
+    #[required]
+    pub y: usize,
+
+    /// Whether the cell is in one of the table's header rows.
+    #[required]
+    pub header: bool,
+}
@@ -125,22 +125,29 @@ impl Layout for TermsElem {
                 .unwrap_or_else(|| *BlockElem::below_in(styles).amount())
         };
 
+        let tag = engine.locator.tag(Self::elem(), self.span());
         let mut seq = vec![];
         for (i, child) in self.children().iter().enumerate() {
             if i > 0 {
                 seq.push(VElem::new(gutter).with_weakness(1).pack());
             }
+
+            let mut item = vec![];
             if !indent.is_zero() {
-                seq.push(HElem::new(indent.into()).pack());
+                item.push(HElem::new(indent.into()).pack());
             }
-            seq.push(child.term().clone().strong());
-            seq.push((*separator).clone());
-            seq.push(child.description().clone());
+            item.push(child.term().clone().strong());
+            item.push((*separator).clone());
+            item.push(child.description().clone());
+
+            let item = Content::sequence(item);
+            seq.push(item.attached(tag.part(TermItem::elem(), i)));
         }
 
-        Content::sequence(seq)
+        let fragment = Content::sequence(seq)
             .styled(ParElem::set_hanging_indent(hanging_indent + indent))
-            .layout(engine, styles, regions)
+            .layout(engine, styles, regions)?;
+        Ok(fragment.tagged(tag))
     }
 }
 
@@ -1,13 +1,16 @@
 //! Image handling.
 
+mod pdf;
 mod raster;
 mod svg;
 
+pub use self::pdf::PdfImage;
 pub use self::raster::{RasterFormat, RasterImage};
 pub use self::svg::SvgImage;
 
 use std::ffi::OsStr;
 use std::fmt::{self, Debug, Formatter};
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 
 use comemo::{Prehashed, Tracked};
@@ -27,13 +30,17 @@ use crate::loading::Readable;
 use crate::model::Figurable;
 use crate::syntax::Spanned;
 use crate::text::{families, Lang, LocalName, Region};
-use crate::util::{option_eq, Numeric};
+use crate::util::{option_eq, NonZeroExt, Numeric};
 use crate::visualize::Path;
 use crate::World;
 
 /// A raster or vector graphic.
 ///
-/// Supported formats are PNG, JPEG, GIF and SVG.
+/// Supported formats are PNG, JPEG, GIF, SVG and PDF. Of a PDF, a single page
+/// is shown. In the PDF export, it is included with its text and images intact.
+/// Other exports only display a simplified version of the page that contains
+/// its shapes, but _no text or images._ For those exports, convert the page to
+/// SVG or PNG beforehand if it contains text.
 ///
 /// _Note:_ Work on SVG export is ongoing and there might be visual inaccuracies
 /// in the resulting PDF. Make sure to double-check embedded SVG images. If you
@@ -83,6 +90,14 @@ pub struct ImageElem {
     /// A text describing the image.
     pub alt: Option<EcoString>,
 
+    /// Which page of a PDF to show, starting at one.
+    ///
+    /// ```typ
+    /// #image("report.pdf", page: 2, width: 50%)
+    /// ```
+    #[default(NonZeroUsize::ONE)]
+    pub page: NonZeroUsize,
+
     /// How the image should adjust itself to a given area.
     #[default(ImageFit::Cover)]
     pub fit: ImageFit,
@@ -118,6 +133,9 @@ impl ImageElem {
         /// A text describing the image.
         #[named]
         alt: Option<Option<EcoString>>,
+        /// Which page of a PDF to show, starting at one.
+        #[named]
+        page: Option<NonZeroUsize>,
         /// How the image should adjust itself to a given area.
         #[named]
         fit: Option<ImageFit>,
@@ -135,6 +153,9 @@ impl ImageElem {
         if let Some(alt) = alt {
             elem.push_alt(alt);
         }
+        if let Some(page) = page {
+            elem.push_page(page);
+        }
         if let Some(fit) = fit {
             elem.push_fit(fit);
         }
@@ -142,18 +163,12 @@ impl ImageElem {
     }
 }
 
-impl Layout for ImageElem {
-    #[tracing::instrument(name = "ImageElem::layout", skip_all)]
-    fn layout(
-        &self,
-        engine: &mut Engine,
-        styles: StyleChain,
-        regions: Regions,
-    ) -> SourceResult<Fragment> {
-        // Take the format that was explicitly defined, or parse the extension,
-        // or try to detect the format.
+impl ImageElem {
+    /// The image's format: The one that was explicitly defined, or the one
+    /// that its path's extension or its data suggest.
+    pub fn determine_format(&self, styles: StyleChain) -> SourceResult<ImageFormat> {
         let data = self.data();
-        let format = match self.format(styles) {
+        Ok(match self.format(styles) {
             Smart::Custom(v) => v,
             Smart::Auto => {
                 let ext = std::path::Path::new(self.path().as_str())
@@ -167,8 +182,12 @@ impl Layout for ImageElem {
                     "jpg" | "jpeg" => ImageFormat::Raster(RasterFormat::Jpg),
                     "gif" => ImageFormat::Raster(RasterFormat::Gif),
                     "svg" | "svgz" => ImageFormat::Vector(VectorFormat::Svg),
-                    _ => match &data {
+                    "pdf" => ImageFormat::Vector(VectorFormat::Pdf),
+                    _ => match data {
                         Readable::Str(_) => ImageFormat::Vector(VectorFormat::Svg),
+                        Readable::Bytes(bytes) if bytes.starts_with(b"%PDF-") => {
+                            ImageFormat::Vector(VectorFormat::Pdf)
+                        }
                         Readable::Bytes(bytes) => match RasterFormat::detect(bytes) {
                             Some(f) => ImageFormat::Raster(f),
                             None => bail!(self.span(), "unknown image format"),
@@ -176,15 +195,33 @@ impl Layout for ImageElem {
                     },
                 }
             }
-        };
+        })
+    }
+}
+
+impl Layout for ImageElem {
+    #[tracing::instrument(name = "ImageElem::layout", skip_all)]
+    fn layout(
+        &self,
+        engine: &mut Engine,
+        styles: StyleChain,
+        regions: Regions,
+    ) -> SourceResult<Fragment> {
+        let data = self.data();
+        let format = self.determine_format(styles)?;
 
-        let image = Image::with_fonts(
-            data.clone().into(),
-            format,
-            self.alt(styles),
-            engine.world,
-            &families(styles).map(|s| s.into()).collect::<Vec<_>>(),
-        )
+        let image = match format {
+            ImageFormat::Vector(VectorFormat::Pdf) => {
+                Image::pdf(data.clone().into(), self.page(styles), self.alt(styles))
+            }
+            _ => Image::with_fonts(
+                data.clone().into(),
+                format,
+                self.alt(styles),
+                engine.world,
+                &families(styles).map(|s| s.into()).collect::<Vec<_>>(),
+            ),
+        }
         .at(self.span())?;
 
         let sizing = Axes::new(self.width(styles), self.height(styles));
@@ -316,6 +353,8 @@ pub enum ImageKind {
     Raster(RasterImage),
     /// An SVG image.
     Svg(SvgImage),
+    /// A page of a PDF file.
+    Pdf(PdfImage),
 }
 
 impl Image {
@@ -333,6 +372,9 @@ impl Image {
             ImageFormat::Vector(VectorFormat::Svg) => {
                 ImageKind::Svg(SvgImage::new(data)?)
             }
+            ImageFormat::Vector(VectorFormat::Pdf) => {
+                ImageKind::Pdf(PdfImage::new(data, NonZeroUsize::ONE)?)
+            }
         };
 
         Ok(Self(Arc::new(Prehashed::new(Repr { kind, alt }))))
@@ -354,16 +396,31 @@ impl Image {
             ImageFormat::Vector(VectorFormat::Svg) => {
                 ImageKind::Svg(SvgImage::with_fonts(data, world, families)?)
             }
+            ImageFormat::Vector(VectorFormat::Pdf) => {
+                ImageKind::Pdf(PdfImage::new(data, NonZeroUsize::ONE)?)
+            }
         };
 
         Ok(Self(Arc::new(Prehashed::new(Repr { kind, alt }))))
     }
 
+    /// Create an image from a page of a PDF file.
+    #[comemo::memoize]
+    pub fn pdf(
+        data: Bytes,
+        page: NonZeroUsize,
+        alt: Option<EcoString>,
+    ) -> StrResult<Self> {
+        let kind = ImageKind::Pdf(PdfImage::new(data, page)?);
+        Ok(Self(Arc::new(Prehashed::new(Repr { kind, alt }))))
+    }
+
     /// The raw image data.
     pub fn data(&self) -> &Bytes {
         match &self.0.kind {
             ImageKind::Raster(raster) => raster.data(),
             ImageKind::Svg(svg) => svg.data(),
+            ImageKind::Pdf(pdf) => pdf.data(),
         }
     }
 
@@ -372,6 +429,7 @@ impl Image {
         match &self.0.kind {
             ImageKind::Raster(raster) => raster.format().into(),
             ImageKind::Svg(_) => VectorFormat::Svg.into(),
+            ImageKind::Pdf(_) => VectorFormat::Pdf.into(),
         }
     }
 
@@ -380,6 +438,7 @@ impl Image {
         match &self.0.kind {
             ImageKind::Raster(raster) => raster.width(),
             ImageKind::Svg(svg) => svg.width(),
+            ImageKind::Pdf(pdf) => pdf.width(),
         }
     }
 
@@ -388,6 +447,7 @@ impl Image {
         match &self.0.kind {
             ImageKind::Raster(raster) => raster.height(),
             ImageKind::Svg(svg) => svg.height(),
+            ImageKind::Pdf(pdf) => pdf.height(),
         }
     }
 
@@ -427,6 +487,8 @@ pub enum ImageFormat {
 pub enum VectorFormat {
     /// The vector graphics format of the web.
     Svg,
+    /// The Portable Document Format. Only one page is shown.
+    Pdf,
 }
 
 impl From<RasterFormat> for ImageFormat {
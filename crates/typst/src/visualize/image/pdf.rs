@@ -0,0 +1,684 @@
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use ecow::eco_format;
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::diag::{bail, StrResult};
+use crate::foundations::Bytes;
+use crate::layout::{Abs, Frame, FrameItem, GroupItem, Point, Size};
+use crate::syntax::Span;
+use crate::visualize::{Color, FixedStroke, Geometry, Paint, Path, Shape};
+
+/// How deeply page tree nodes and nested form XObjects are followed before
+/// giving up on a (likely malformed) file.
+const MAX_DEPTH: usize = 32;
+
+/// The color of the placeholders for content that previews leave out.
+const PLACEHOLDER: Color = Color::SILVER;
+
+/// The identity matrix.
+const IDENTITY: [f64; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// A page of a PDF file.
+#[derive(Clone, Hash)]
+pub struct PdfImage(Arc<Repr>);
+
+/// The internal representation.
+struct Repr {
+    data: Bytes,
+    page: NonZeroUsize,
+    document: Document,
+    page_id: ObjectId,
+    bbox: [f64; 4],
+    rotation: u16,
+    content: Vec<u8>,
+    preview: Frame,
+}
+
+impl PdfImage {
+    /// Parse a PDF file and select one of its pages.
+    #[comemo::memoize]
+    pub fn new(data: Bytes, page: NonZeroUsize) -> StrResult<Self> {
+        let document = Document::load_mem(&data)
+            .map_err(|err| eco_format!("failed to parse PDF ({err})"))?;
+        if document.is_encrypted() {
+            bail!("encrypted PDFs are not supported");
+        }
+
+        let pages = document.get_pages();
+        let Some(&page_id) = u32::try_from(page.get()).ok().and_then(|i| pages.get(&i))
+        else {
+            let count = pages.len();
+            let noun = if count == 1 { "page" } else { "pages" };
+            bail!("page {page} does not exist (the PDF has {count} {noun})");
+        };
+
+        let bbox = page_box(&document, page_id);
+        let rotation = page_rotation(&document, page_id);
+        let content = document
+            .get_page_content(page_id)
+            .map_err(|err| eco_format!("failed to read PDF page ({err})"))?;
+        let resources = inherited(&document, page_id, b"Resources")
+            .and_then(|object| object.as_dict().ok());
+        let matrix = unit_matrix(bbox, rotation);
+        let preview = preview(&document, &content, resources, bbox, rotation, matrix);
+
+        Ok(Self(Arc::new(Repr {
+            data,
+            page,
+            document,
+            page_id,
+            bbox,
+            rotation,
+            content,
+            preview,
+        })))
+    }
+
+    /// The raw PDF data.
+    pub fn data(&self) -> &Bytes {
+        &self.0.data
+    }
+
+    /// The number of the selected page, starting at one.
+    pub fn page(&self) -> NonZeroUsize {
+        self.0.page
+    }
+
+    /// The page's width in points, as it is displayed.
+    pub fn width(&self) -> u32 {
+        let (width, _) = displayed_size(self.0.bbox, self.0.rotation);
+        width.round().max(1.0) as u32
+    }
+
+    /// The page's height in points, as it is displayed.
+    pub fn height(&self) -> u32 {
+        let (_, height) = displayed_size(self.0.bbox, self.0.rotation);
+        height.round().max(1.0) as u32
+    }
+
+    /// The visible area of the page in PDF user space, as
+    /// `[left, bottom, right, top]`.
+    pub fn bbox(&self) -> [f64; 4] {
+        self.0.bbox
+    }
+
+    /// How many degrees the page is rotated clockwise when displayed. One of
+    /// 0, 90, 180, and 270.
+    pub fn rotation(&self) -> u16 {
+        self.0.rotation
+    }
+
+    /// The PDF matrix that maps the page's user space into the unit square
+    /// with the y-axis pointing upwards, as the page is displayed.
+    pub fn matrix(&self) -> [f64; 6] {
+        unit_matrix(self.0.bbox, self.0.rotation)
+    }
+
+    /// The parsed PDF file.
+    pub fn document(&self) -> &Document {
+        &self.0.document
+    }
+
+    /// The object id of the selected page.
+    pub fn page_id(&self) -> ObjectId {
+        self.0.page_id
+    }
+
+    /// The page's decoded content stream.
+    pub fn content(&self) -> &[u8] {
+        &self.0.content
+    }
+
+    /// The resources used by the page's content stream.
+    pub fn resources(&self) -> Option<&Dictionary> {
+        inherited(&self.0.document, self.0.page_id, b"Resources")
+            .and_then(|object| object.as_dict().ok())
+    }
+
+    /// A simplified rendition of the page with its displayed size.
+    ///
+    /// It contains the page's paths with solid colors, clipped like on the
+    /// page. Text, images, and shadings are replaced by gray placeholders: a
+    /// bar for each run of text and a box for each image. This is meant for
+    /// previews in exports that cannot include the page itself.
+    pub fn preview(&self) -> &Frame {
+        &self.0.preview
+    }
+}
+
+impl Hash for Repr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Everything else is derived from the data and the page number.
+        self.data.hash(state);
+        self.page.hash(state);
+    }
+}
+
+/// Look up an attribute of a page, which may be inherited from its ancestors
+/// in the page tree.
+fn inherited<'a>(
+    document: &'a Document,
+    page_id: ObjectId,
+    key: &[u8],
+) -> Option<&'a Object> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    for _ in 0..MAX_DEPTH {
+        if let Some(object) = get(document, node, key) {
+            return Some(object);
+        }
+        let parent = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = document.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+/// Determine the visible area of a page.
+fn page_box(document: &Document, page_id: ObjectId) -> [f64; 4] {
+    inherited(document, page_id, b"CropBox")
+        .and_then(|object| rect(document, object))
+        .or_else(|| {
+            inherited(document, page_id, b"MediaBox")
+                .and_then(|object| rect(document, object))
+        })
+        // The media box is required, but readers fall back to US Letter.
+        .unwrap_or([0.0, 0.0, 612.0, 792.0])
+}
+
+/// Determine how many degrees a page is rotated clockwise, as a multiple of
+/// 90 in `0..360`.
+fn page_rotation(document: &Document, page_id: ObjectId) -> u16 {
+    let rotate = match inherited(document, page_id, b"Rotate") {
+        Some(Object::Integer(v)) => *v,
+        _ => 0,
+    };
+    if rotate % 90 == 0 {
+        rotate.rem_euclid(360) as u16
+    } else {
+        0
+    }
+}
+
+/// The size of a page's visible area when it is displayed with its rotation.
+fn displayed_size(bbox: [f64; 4], rotation: u16) -> (f64, f64) {
+    let [x0, y0, x1, y1] = bbox;
+    let (width, height) = (x1 - x0, y1 - y0);
+    if rotation % 180 == 0 {
+        (width, height)
+    } else {
+        (height, width)
+    }
+}
+
+/// The PDF matrix that maps a page's user space into the unit square with
+/// the y-axis pointing upwards, as the page is displayed.
+fn unit_matrix(bbox: [f64; 4], rotation: u16) -> [f64; 6] {
+    let [x0, y0, x1, y1] = bbox;
+    let (w, h) = (x1 - x0, y1 - y0);
+    let normalize = [1.0 / w, 0.0, 0.0, 1.0 / h, -x0 / w, -y0 / h];
+    let rotate = match rotation {
+        90 => [0.0, -1.0, 1.0, 0.0, 0.0, 1.0],
+        180 => [-1.0, 0.0, 0.0, -1.0, 1.0, 1.0],
+        270 => [0.0, 1.0, -1.0, 0.0, 1.0, 0.0],
+        _ => [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+    };
+    concat(normalize, rotate)
+}
+
+/// Read a rectangle, normalized to `[left, bottom, right, top]`.
+fn rect(document: &Document, object: &Object) -> Option<[f64; 4]> {
+    let values = object
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|value| resolve(document, value).and_then(number))
+        .collect::<Option<Vec<_>>>()?;
+    let &[x0, y0, x1, y1] = values.as_slice() else { return None };
+    let rect = [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)];
+    (rect[2] > rect[0] && rect[3] > rect[1]).then_some(rect)
+}
+
+/// Look up a key in a dictionary, following a reference.
+fn get<'a>(
+    document: &'a Document,
+    dict: &'a Dictionary,
+    key: &[u8],
+) -> Option<&'a Object> {
+    dict.get(key).ok().and_then(|object| resolve(document, object))
+}
+
+/// Follow a reference.
+fn resolve<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    match object {
+        Object::Reference(id) => document.get_object(*id).ok(),
+        _ => Some(object),
+    }
+}
+
+/// Read a number.
+fn number(object: &Object) -> Option<f64> {
+    match *object {
+        Object::Integer(v) => Some(v as f64),
+        Object::Real(v) => Some(v as f64),
+        _ => None,
+    }
+}
+
+/// The graphics state that matters for the preview.
+#[derive(Clone)]
+struct GraphicsState {
+    /// Maps user space to the frame's coordinate system, as a PDF matrix.
+    ctm: [f64; 6],
+    fill: Color,
+    stroke: Color,
+    line_width: f64,
+    /// The clipping paths in the frame's coordinate system. Content is only
+    /// visible within all of them.
+    clips: Vec<Path>,
+    /// The font size of text.
+    font_size: f64,
+    /// The distance between lines of text.
+    leading: f64,
+    /// Whether the current font encodes each glyph with two bytes.
+    two_byte: bool,
+}
+
+impl GraphicsState {
+    /// Map a point from user space into the frame.
+    fn point(&self, x: f64, y: f64) -> Point {
+        let [a, b, c, d, e, f] = self.ctm;
+        Point::new(Abs::pt(a * x + c * y + e), Abs::pt(b * x + d * y + f))
+    }
+}
+
+/// Draw the paths of a page into a frame of its displayed size.
+fn preview(
+    document: &Document,
+    content: &[u8],
+    resources: Option<&Dictionary>,
+    bbox: [f64; 4],
+    rotation: u16,
+    matrix: [f64; 6],
+) -> Frame {
+    let (width, height) = displayed_size(bbox, rotation);
+    let size = Size::new(Abs::pt(width), Abs::pt(height));
+    let mut frame = Frame::soft(size);
+
+    // Scale the unit square to the frame. PDF's y-axis points upwards, ours
+    // points downwards.
+    let state = GraphicsState {
+        ctm: concat(matrix, [width, 0.0, 0.0, -height, 0.0, height]),
+        fill: Color::BLACK,
+        stroke: Color::BLACK,
+        line_width: 1.0,
+        clips: vec![],
+        font_size: 0.0,
+        leading: 0.0,
+        two_byte: false,
+    };
+
+    draw(document, &mut frame, content, resources, state, 0);
+    frame.clip(Path::rect(size));
+    frame
+}
+
+/// Interpret the path operators of a content stream.
+fn draw(
+    document: &Document,
+    frame: &mut Frame,
+    content: &[u8],
+    resources: Option<&Dictionary>,
+    mut state: GraphicsState,
+    depth: usize,
+) {
+    let Ok(content) = Content::decode(content) else { return };
+
+    let mut stack = vec![];
+    let mut path = Path::new();
+    let mut clip = false;
+    let mut start = Point::zero();
+    let mut current = Point::zero();
+
+    // The text matrix and the matrix at the start of the current line.
+    let mut text = IDENTITY;
+    let mut line = IDENTITY;
+
+    for operation in &content.operations {
+        let operands: Vec<f64> = operation.operands.iter().filter_map(number).collect();
+        match (operation.operator.as_str(), operands.as_slice()) {
+            ("q", _) => stack.push(state.clone()),
+            ("Q", _) => {
+                if let Some(saved) = stack.pop() {
+                    state = saved;
+                }
+            }
+            ("cm", &[a, b, c, d, e, f]) => {
+                state.ctm = concat([a, b, c, d, e, f], state.ctm)
+            }
+            ("w", &[width]) => state.line_width = width,
+            ("m", &[x, y]) => {
+                start = state.point(x, y);
+                current = start;
+                path.move_to(start);
+            }
+            ("l", &[x, y]) => {
+                current = state.point(x, y);
+                path.line_to(current);
+            }
+            ("c", &[x1, y1, x2, y2, x3, y3]) => {
+                current = state.point(x3, y3);
+                path.cubic_to(state.point(x1, y1), state.point(x2, y2), current);
+            }
+            ("v", &[x2, y2, x3, y3]) => {
+                let p1 = current;
+                current = state.point(x3, y3);
+                path.cubic_to(p1, state.point(x2, y2), current);
+            }
+            ("y", &[x1, y1, x3, y3]) => {
+                current = state.point(x3, y3);
+                path.cubic_to(state.point(x1, y1), current, current);
+            }
+            ("h", _) => {
+                path.close_path();
+                current = start;
+            }
+            ("re", &[x, y, w, h]) => {
+                start = state.point(x, y);
+                current = start;
+                path.move_to(start);
+                path.line_to(state.point(x + w, y));
+                path.line_to(state.point(x + w, y + h));
+                path.line_to(state.point(x, y + h));
+                path.close_path();
+            }
+            ("S", _) => paint(frame, &mut state, &mut path, &mut clip, false, true),
+            ("s", _) => {
+                path.close_path();
+                paint(frame, &mut state, &mut path, &mut clip, false, true);
+            }
+            ("f" | "F" | "f*", _) => {
+                paint(frame, &mut state, &mut path, &mut clip, true, false)
+            }
+            ("B" | "B*", _) => paint(frame, &mut state, &mut path, &mut clip, true, true),
+            ("b" | "b*", _) => {
+                path.close_path();
+                paint(frame, &mut state, &mut path, &mut clip, true, true);
+            }
+            ("n", _) => paint(frame, &mut state, &mut path, &mut clip, false, false),
+            ("W" | "W*", _) => clip = true,
+            ("g" | "rg" | "k" | "sc" | "scn", _) => {
+                if let Some(color) = color(&operands) {
+                    state.fill = color;
+                }
+            }
+            ("G" | "RG" | "K" | "SC" | "SCN", _) => {
+                if let Some(color) = color(&operands) {
+                    state.stroke = color;
+                }
+            }
+            ("Do", _) => {
+                if let Some(Object::Name(name)) = operation.operands.first() {
+                    draw_x_object(document, frame, resources, name, &state, depth);
+                }
+            }
+            ("sh", _) => {
+                // A shading fills everything within the clipping paths.
+                placeholder(frame, &state, Path::rect(frame.size()));
+            }
+            ("BT", _) => {
+                text = IDENTITY;
+                line = IDENTITY;
+            }
+            ("Tf", &[size]) => {
+                state.font_size = size;
+                if let Some(Object::Name(name)) = operation.operands.first() {
+                    state.two_byte = is_two_byte(document, resources, name);
+                }
+            }
+            ("TL", &[leading]) => state.leading = leading,
+            ("Td" | "TD", &[x, y]) => {
+                if operation.operator == "TD" {
+                    state.leading = -y;
+                }
+                line = concat([1.0, 0.0, 0.0, 1.0, x, y], line);
+                text = line;
+            }
+            ("Tm", &[a, b, c, d, e, f]) => {
+                line = [a, b, c, d, e, f];
+                text = line;
+            }
+            ("T*" | "'" | "\"", _) => {
+                line = concat([1.0, 0.0, 0.0, 1.0, 0.0, -state.leading], line);
+                text = line;
+                if let Some(Object::String(string, _)) = operation.operands.last() {
+                    show_text(frame, &state, &mut text, string.len(), 0.0);
+                }
+            }
+            ("Tj", _) => {
+                if let Some(Object::String(string, _)) = operation.operands.first() {
+                    show_text(frame, &state, &mut text, string.len(), 0.0);
+                }
+            }
+            ("TJ", _) => {
+                let Some(Object::Array(parts)) = operation.operands.first() else {
+                    continue;
+                };
+                let mut len = 0;
+                let mut adjustment = 0.0;
+                for part in parts {
+                    match part {
+                        Object::String(string, _) => len += string.len(),
+                        _ => adjustment += number(part).unwrap_or(0.0),
+                    }
+                }
+                show_text(frame, &state, &mut text, len, adjustment);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Draw a form XObject from a content stream's resources, or a placeholder
+/// for an image XObject.
+fn draw_x_object(
+    document: &Document,
+    frame: &mut Frame,
+    resources: Option<&Dictionary>,
+    name: &[u8],
+    state: &GraphicsState,
+    depth: usize,
+) {
+    if depth >= MAX_DEPTH {
+        return;
+    }
+
+    let Some(Object::Stream(stream)) = resources
+        .and_then(|resources| get(document, resources, b"XObject"))
+        .and_then(|x_objects| x_objects.as_dict().ok())
+        .and_then(|x_objects| get(document, x_objects, name))
+    else {
+        return;
+    };
+
+    // Images fill the unit square of user space.
+    match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+        Ok(b"Form") => {}
+        Ok(b"Image") => {
+            let mut path = Path::new();
+            path.move_to(state.point(0.0, 0.0));
+            path.line_to(state.point(1.0, 0.0));
+            path.line_to(state.point(1.0, 1.0));
+            path.line_to(state.point(0.0, 1.0));
+            path.close_path();
+            placeholder(frame, state, path);
+            return;
+        }
+        _ => return,
+    }
+
+    let mut state = state.clone();
+    if let Some(matrix) = get(document, &stream.dict, b"Matrix")
+        .and_then(|matrix| matrix.as_array().ok())
+        .and_then(|matrix| matrix.iter().map(number).collect::<Option<Vec<_>>>())
+    {
+        if let &[a, b, c, d, e, f] = matrix.as_slice() {
+            state.ctm = concat([a, b, c, d, e, f], state.ctm);
+        }
+    }
+
+    // The form's bounding box clips its content.
+    if let Some([x0, y0, x1, y1]) =
+        get(document, &stream.dict, b"BBox").and_then(|bbox| rect(document, bbox))
+    {
+        let mut clip = Path::new();
+        clip.move_to(state.point(x0, y0));
+        clip.line_to(state.point(x1, y0));
+        clip.line_to(state.point(x1, y1));
+        clip.line_to(state.point(x0, y1));
+        clip.close_path();
+        state.clips.push(clip);
+    }
+
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    let form_resources = get(document, &stream.dict, b"Resources")
+        .and_then(|resources| resources.as_dict().ok())
+        .or(resources);
+
+    draw(document, frame, &content, form_resources, state, depth + 1);
+}
+
+/// Paint the current path and start a new one.
+///
+/// If the path was marked as a clipping path, it restricts what is painted
+/// afterwards, but not the path itself.
+fn paint(
+    frame: &mut Frame,
+    state: &mut GraphicsState,
+    path: &mut Path,
+    clip: &mut bool,
+    fill: bool,
+    stroke: bool,
+) {
+    let path = std::mem::take(path);
+    if path.0.is_empty() {
+        *clip = false;
+        return;
+    }
+
+    let clip_path = std::mem::take(clip).then(|| path.clone());
+    if fill || stroke {
+        // Scale the line width with the transformation's average stretch.
+        let [a, b, c, d, _, _] = state.ctm;
+        let scale = (a * d - b * c).abs().sqrt();
+
+        let shape = Shape {
+            geometry: Geometry::Path(path),
+            fill: fill.then(|| Paint::Solid(state.fill)),
+            stroke: stroke.then(|| FixedStroke {
+                paint: Paint::Solid(state.stroke),
+                thickness: Abs::pt(state.line_width * scale),
+                ..FixedStroke::default()
+            }),
+        };
+
+        // Nest the shape into one group per clipping path, which intersects
+        // them.
+        let mut item = FrameItem::Shape(shape, Span::detached());
+        for clip in &state.clips {
+            let mut inner = Frame::soft(frame.size());
+            inner.push(Point::zero(), item);
+            let mut group = GroupItem::new(inner);
+            group.clip_path = Some(clip.clone());
+            item = FrameItem::Group(group);
+        }
+
+        frame.push(Point::zero(), item);
+    }
+
+    if let Some(clip_path) = clip_path {
+        state.clips.push(clip_path);
+    }
+}
+
+/// Draw a bar in place of a run of text and move the text matrix past it.
+///
+/// Without the font's metrics, each glyph is assumed to be half as wide as
+/// the font size. The `adjustment` is the sum of the run's position
+/// adjustments, in thousandths of the font size.
+fn show_text(
+    frame: &mut Frame,
+    state: &GraphicsState,
+    text: &mut [f64; 6],
+    len: usize,
+    adjustment: f64,
+) {
+    let glyphs = if state.two_byte { len / 2 } else { len };
+    let size = state.font_size;
+    let width = glyphs as f64 * size / 2.0 - adjustment / 1000.0 * size;
+
+    let point = |x: f64, y: f64| {
+        let [a, b, c, d, e, f] = *text;
+        state.point(a * x + c * y + e, b * x + d * y + f)
+    };
+
+    if glyphs > 0 {
+        let mut path = Path::new();
+        path.move_to(point(0.0, 0.0));
+        path.line_to(point(width, 0.0));
+        path.line_to(point(width, size * 0.7));
+        path.line_to(point(0.0, size * 0.7));
+        path.close_path();
+        placeholder(frame, state, path);
+    }
+
+    *text = concat([1.0, 0.0, 0.0, 1.0, width, 0.0], *text);
+}
+
+/// Fill a placeholder for content that the preview leaves out.
+fn placeholder(frame: &mut Frame, state: &GraphicsState, mut path: Path) {
+    let mut state = GraphicsState { fill: PLACEHOLDER, ..state.clone() };
+    paint(frame, &mut state, &mut path, &mut false, true, false);
+}
+
+/// Whether a font from the resources encodes each glyph with two bytes, like
+/// the composite fonts that most PDF producers embed.
+fn is_two_byte(document: &Document, resources: Option<&Dictionary>, name: &[u8]) -> bool {
+    resources
+        .and_then(|resources| get(document, resources, b"Font"))
+        .and_then(|fonts| fonts.as_dict().ok())
+        .and_then(|fonts| get(document, fonts, name))
+        .and_then(|font| font.as_dict().ok())
+        .and_then(|font| font.get(b"Subtype").and_then(Object::as_name).ok())
+        == Some(b"Type0".as_slice())
+}
+
+/// Approximate a gray, RGB or CMYK color with the given components.
+fn color(components: &[f64]) -> Option<Color> {
+    let (r, g, b) = match *components {
+        [gray] => (gray, gray, gray),
+        [r, g, b] => (r, g, b),
+        [c, m, y, k] => {
+            ((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k))
+        }
+        _ => return None,
+    };
+    let channel = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Some(Color::from_u8(channel(r), channel(g), channel(b), 255))
+}
+
+/// Concatenate two PDF matrices, applying `m` before `n`.
+fn concat(m: [f64; 6], n: [f64; 6]) -> [f64; 6] {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
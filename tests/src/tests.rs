@@ -374,6 +374,8 @@ fn test(
     let mut compare_ref = None;
     let mut validate_hints = None;
     let mut compare_ever = false;
+    let mut exports = vec![];
+    let mut exported = HashMap::new();
     let mut rng = LinearShift::new();
 
     let parts: Vec<_> = text
@@ -402,6 +404,10 @@ fn test(
             for line in part.lines() {
                 compare_ref = get_flag_metadata(line, "Ref").or(compare_ref);
                 validate_hints = get_flag_metadata(line, "Hints").or(validate_hints);
+                exports.extend(
+                    Export::iter()
+                        .filter(|e| get_flag_metadata(line, e.key()) == Some(true)),
+                );
             }
         } else {
             let (part_ok, compare_here, part_frames) = test_part(
@@ -412,6 +418,8 @@ fn test(
                 i,
                 compare_ref.unwrap_or(true),
                 validate_hints.unwrap_or(true),
+                &exports,
+                &mut exported,
                 line,
                 &mut rng,
             );
@@ -431,7 +439,10 @@ fn test(
                 &document,
                 Some(&format!("typst-test: {}", name.display())),
                 world.today(Some(0)),
-            );
+                None,
+                typst_pdf::PdfStandard::default(),
+            )
+            .unwrap();
             fs::create_dir_all(pdf_path.parent().unwrap()).unwrap();
             fs::write(pdf_path, pdf_data).unwrap();
         }
@@ -478,6 +489,31 @@ fn test(
         }
     }
 
+    for &export in &exports {
+        let ext = export.extension();
+        let parts: Vec<String> = exported.remove(&export).unwrap_or_default();
+        let actual = parts.join("---\n");
+
+        let out_path = Path::new(ext).join(name).with_extension(ext);
+        fs::create_dir_all(out_path.parent().unwrap()).unwrap();
+        fs::write(&out_path, &actual).unwrap();
+
+        let ref_path = Path::new(REF_DIR).join(name).with_extension(ext);
+        let expected = fs::read_to_string(&ref_path).ok();
+        if expected.as_deref() != Some(actual.as_str()) {
+            if args.update {
+                fs::write(&ref_path, &actual).unwrap();
+                writeln!(output, "  Updated reference {ext} output.").unwrap();
+            } else if expected.is_some() {
+                writeln!(output, "  Does not match reference {ext} output.").unwrap();
+                ok = false;
+            } else {
+                writeln!(output, "  Failed to open reference {ext} output.").unwrap();
+                ok = false;
+            }
+        }
+    }
+
     {
         let mut stdout = io::stdout().lock();
         stdout.write_all(name.to_string_lossy().as_bytes()).unwrap();
@@ -527,6 +563,8 @@ fn test_part(
     i: usize,
     compare_ref: bool,
     validate_hints: bool,
+    exports: &[Export],
+    exported: &mut HashMap<Export, Vec<String>>,
     line: usize,
     rng: &mut LinearShift,
 ) -> (bool, bool, Vec<Frame>) {
@@ -557,7 +595,13 @@ fn test_part(
 
     let mut tracer = Tracer::new();
     let (mut frames, diagnostics) = match typst::compile(world, &mut tracer) {
-        Ok(document) => (document.pages, tracer.warnings()),
+        Ok(document) => {
+            for &export in exports {
+                let output = export.export(world, &document);
+                exported.entry(export).or_default().push(output);
+            }
+            (document.pages, tracer.warnings())
+        }
         Err(errors) => {
             let mut warnings = tracer.warnings();
             warnings.extend(errors);
@@ -637,6 +681,106 @@ fn test_part(
     (ok, compare_ref, frames)
 }
 
+/// An export whose output is compared with a reference file, in addition to
+/// the reference image. A test enables it with a header like `// Txt: true`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Export {
+    /// Plain text.
+    Txt,
+    /// A semantic HTML file.
+    Html,
+    /// The structure tree of a PDF file.
+    Tags,
+}
+
+impl Export {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Txt, Self::Html, Self::Tags].into_iter()
+    }
+
+    /// The key of the header that enables the export.
+    fn key(self) -> &'static str {
+        match self {
+            Self::Txt => "Txt",
+            Self::Html => "Html",
+            Self::Tags => "Tags",
+        }
+    }
+
+    /// The extension of the reference and output files, which is also the
+    /// name of the output directory.
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Txt => "txt",
+            Self::Html => "html",
+            Self::Tags => "tags",
+        }
+    }
+
+    /// Export the document of a subtest.
+    fn export(self, world: &TestWorld, document: &Document) -> String {
+        match self {
+            Self::Txt => typst_txt::txt(document, None),
+            Self::Html => match typst_html::html(world, document) {
+                Ok(html) => html,
+                Err(errors) => errors
+                    .iter()
+                    .map(|error| format!("error: {}\n", error.message))
+                    .collect(),
+            },
+            Self::Tags => {
+                let pdf = typst_pdf::pdf(document, None, None, None, Default::default())
+                    .unwrap();
+                write_structure(&pdf)
+            }
+        }
+    }
+}
+
+/// Write out the structure tree of a PDF file with one structure element per
+/// line, indented by its depth.
+fn write_structure(pdf: &[u8]) -> String {
+    fn visit(
+        doc: &lopdf::Document,
+        object: &lopdf::Object,
+        depth: usize,
+        output: &mut String,
+    ) {
+        let Ok((_, object)) = doc.dereference(object) else { return };
+        match object {
+            lopdf::Object::Array(kids) => {
+                for kid in kids {
+                    visit(doc, kid, depth, output);
+                }
+            }
+            lopdf::Object::Dictionary(dict) => {
+                // Marked content and annotations are leaves without a role.
+                let Ok(role) = dict.get(b"S").and_then(lopdf::Object::as_name_str) else {
+                    return;
+                };
+                writeln!(output, "{}{role}", "  ".repeat(depth)).unwrap();
+                if let Ok(kids) = dict.get(b"K") {
+                    visit(doc, kids, depth + 1, output);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let doc = lopdf::Document::load_mem(pdf).unwrap();
+    let root = doc
+        .catalog()
+        .and_then(|catalog| catalog.get_deref(b"StructTreeRoot", &doc))
+        .and_then(lopdf::Object::as_dict)
+        .unwrap();
+
+    let mut output = String::new();
+    if let Ok(kids) = root.get(b"K") {
+        visit(&doc, kids, 0, &mut output);
+    }
+    output
+}
+
 fn print_annotation(
     output: &mut String,
     source: &Source,
@@ -0,0 +1,28 @@
+// Test the HTML export.
+// Ref: false
+// Html: true
+
+---
+// Labelled text and metadata are link targets.
+See #link(<intro>)[the intro] and #link(<data>)[the data].
+
+Introduction. <intro>
+
+#metadata(1) <data>
+
+---
+// The label of a shown element stays as an anchor.
+#show strong: it => it.body
+*Bold* <bold>
+
+---
+// Header rows form the table's head.
+#table(columns: 2, header-rows: 1, [A], [B], [1], [2], [3])
+
+---
+// A reference to a footnote before the note itself.
+A#footnote(<note>) B#footnote[The note.] <note>
+
+---
+// Images keep their format.
+#image.decode("<svg xmlns='http://www.w3.org/2000/svg' width='10' height='10'></svg>")
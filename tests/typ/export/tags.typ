@@ -0,0 +1,13 @@
+// Test the structure tree of the PDF export.
+// Ref: false
+// Tags: true
+
+---
+// Rows, cells, and items nest within their table or list.
+= Heading
+A paragraph.
+
+- One
+- Two
+
+#table(columns: 2, header-rows: 1, [A], [B], [1], [2])
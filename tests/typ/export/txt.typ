@@ -0,0 +1,44 @@
+// Test the plain text export.
+// Ref: false
+// Txt: true
+
+---
+// Line breaks stay within a paragraph.
+One \ Two
+
+Three
+
+---
+// Wrapped lines stay within their paragraph.
+#block(width: 1pt)[
+  One two three
+
+  Four five
+]
+
+---
+// Test the gaps between words.
+A#h(1em)B
+
+---
+A#h(0.05em)B
+
+---
+#strong[Bold]normal
+
+---
+*Bold* normal
+
+---
+#text(2em)[Big] small
+
+---
+// Columns are read one after the other.
+#set page(height: 200pt, columns: 2)
+Left \ column
+#colbreak()
+Right \ column
+
+---
+// Pages are separated by form feeds.
+First #pagebreak() Second #pagebreak() Third
@@ -68,3 +68,13 @@
 ---
 // Error: 14-19 expected color, gradient, pattern, none, array, or function, found string
 #table(fill: "hey")
+
+---
+// Test that header rows are part of the table's fields.
+// Ref: false
+#test(table(header-rows: 1, [A], [B]).header-rows, 1)
+#test(table([A]).has("header-rows"), false)
+
+---
+// Error: 21-23 number must be at least zero
+#table(header-rows: -1)
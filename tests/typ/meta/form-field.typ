@@ -0,0 +1,27 @@
+// Test PDF form fields.
+// Ref: false
+
+---
+// Test that form fields flow with the surrounding text.
+#style(styles => {
+  let label = measure([Name:], styles)
+  let field = measure(pdf.text-field("name", width: 2cm), styles)
+  let line = measure([Name: #pdf.text-field("name", width: 2cm)], styles)
+  test(line.width > label.width + 2cm, true)
+  test(line.height < label.height + field.height, true)
+})
+
+---
+#style(styles => {
+  let field = measure(pdf.dropdown("c", ("x", "y"), width: 2cm), styles)
+  let line = measure([
+    #pdf.checkbox("a") A
+    #pdf.radio("b", "x") B
+    #pdf.dropdown("c", ("x", "y"), width: 2cm) C
+  ], styles)
+  test(line.height < 2 * field.height, true)
+})
+
+---
+// Error: 2-43 value must be one of the dropdown's options
+#pdf.dropdown("c", ("x", "y"), value: "z")
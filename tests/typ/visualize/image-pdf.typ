@@ -0,0 +1,15 @@
+// Test pages of PDF files as images.
+// Ref: false
+
+---
+// The first page is rotated by 90 degrees, the second one isn't.
+#style(styles => {
+  let rotated = measure(image("/files/shapes.pdf", width: 100pt), styles)
+  let upright = measure(image("/files/shapes.pdf", page: 2, width: 100pt), styles)
+  test(rotated.height, 200pt)
+  test(upright.height, 50pt)
+})
+
+---
+// Error: 2-37 page 3 does not exist (the PDF has 2 pages)
+#image("/files/shapes.pdf", page: 3)